@@ -0,0 +1,75 @@
+#![no_std]
+
+//! Verifies block headers with the light module of cryptobeca, built without
+//! std, from a crate without std itself.
+
+use cryptobeca::light::{verify_header, HashAlgorithm, HeaderError, PowFunction, RawHeader};
+
+const MERKLE_ROOT: &str = "7d1a54127b222502f5b79b5fb0803061152a44f92b37e23c6527baf665d4da9a";
+
+const PARENT_HASH: &str = "0f9c2e3a8f50b9b86e1c2f6477706cb1b0d6b81a1ac7a73f1c5a9437c60fb4e2";
+
+/// The header of a block mined at difficulty 8 on the parent header.
+pub const HEADER: RawHeader<'static> = RawHeader {
+    index: 1,
+    timestamp: 1_704_067_200,
+    timestamp_nanos: 250_000_000,
+    merkle_root: MERKLE_ROOT,
+    previous_hash: PARENT_HASH,
+    nonce: 392,
+    hash: "00a26d76cc72da24743cd9bdc3b1ccbfdb8c4399ed5f2b04ff0e859068be1ced",
+    difficulty: 8,
+    hash_algorithm: HashAlgorithm::Sha3_256,
+    pow_function: PowFunction::HashAlgorithm,
+};
+
+/// The header HEADER follows.
+pub const PARENT: RawHeader<'static> = RawHeader {
+    index: 0,
+    timestamp: 1_704_067_000,
+    timestamp_nanos: 0,
+    merkle_root: MERKLE_ROOT,
+    previous_hash: "0",
+    nonce: 0,
+    hash: PARENT_HASH,
+    difficulty: 0,
+    hash_algorithm: HashAlgorithm::Sha3_256,
+    pow_function: PowFunction::HashAlgorithm,
+};
+
+/// Verifies a header extends a parent header with light::verify_header().
+///
+/// # Parameters
+///
+/// * `header` - The header to verify
+/// * `parent` - The header of the block it claims to follow
+///
+/// # Returns
+///
+/// `Result<(), HeaderError>`
+///
+/// - `Ok(())` if the header follows the parent and carries valid proof of work
+/// - `Err(HeaderError)` with the failed check
+pub fn verify(header: &RawHeader<'_>, parent: &RawHeader<'_>) -> Result<(), HeaderError> {
+    verify_header(header, parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mined_header_verifies_without_std() {
+        assert_eq!(verify(&HEADER, &PARENT), Ok(()));
+    }
+
+    #[test]
+    fn tampered_header_is_rejected_without_std() {
+        let tampered = RawHeader {
+            nonce: HEADER.nonce + 1,
+            ..HEADER
+        };
+
+        assert_eq!(verify(&tampered, &PARENT), Err(HeaderError::HashMismatch));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::hasher::*;
+use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The version byte prefixed to the public key of an address.
+pub const ADDRESS_VERSION: u8 = 0x00;
+
+/// The number of checksum bytes appended to an address payload.
+pub const CHECKSUM_LEN: usize = 4;
+
+/// The length of a compressed public key.
+const PUBLIC_KEY_LEN: usize = 33;
+
+/// AddressError enum.
+///
+/// Represents the possible errors when decoding an address.
+///
+/// # Variants
+///
+/// * `InvalidBase58` - Returned when the address contains characters outside the Base58 alphabet.
+/// * `InvalidLength` - Returned when the decoded address does not have the expected length.
+/// * `InvalidVersion` - Returned when the address version byte is unknown.
+/// * `InvalidChecksum` - Returned when the checksum does not match the address payload.
+/// * `InvalidPublicKey` - Returned when the payload is not a valid secp256k1 public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    InvalidBase58,
+    InvalidLength,
+    InvalidVersion,
+    InvalidChecksum,
+    InvalidPublicKey,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            AddressError::InvalidBase58 => "invalid Base58 encoding",
+            AddressError::InvalidLength => "invalid address length",
+            AddressError::InvalidVersion => "invalid address version",
+            AddressError::InvalidChecksum => "invalid address checksum",
+            AddressError::InvalidPublicKey => "invalid public key",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Address struct.
+///
+/// Represents the address of an account, the public key controlling its coins.
+/// Wrapping the key means an address is validated once when it is constructed,
+/// so a private key or a block hash cannot be passed where an address belongs.
+///
+/// Addresses are written as the hex encoded 33-byte compressed public key, and
+/// can be parsed from a compressed or a 65-byte uncompressed hex encoded key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Address(pub PublicKey);
+
+impl Address {
+    /// Gets the public key of the address.
+    ///
+    /// # Returns
+    ///
+    /// The secp256k1 public key controlling the address
+    pub fn public_key(&self) -> &PublicKey {
+        &self.0
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    /// Parses an address from a hex encoded public key.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Address, AddressError>`
+    ///
+    /// - `Ok(Address)` if the string is a valid compressed or uncompressed public key
+    /// - `Err(AddressError::InvalidPublicKey)` otherwise
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PublicKey::from_str(s)
+            .map(Address)
+            .map_err(|_| AddressError::InvalidPublicKey)
+    }
+}
+
+impl From<PublicKey> for Address {
+    fn from(public_key: PublicKey) -> Self {
+        Address(public_key)
+    }
+}
+
+impl TryFrom<String> for Address {
+    type Error = AddressError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<&str> for Address {
+    type Error = AddressError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Address> for String {
+    fn from(address: Address) -> Self {
+        address.to_string()
+    }
+}
+
+impl PartialEq<str> for Address {
+    fn eq(&self, other: &str) -> bool {
+        hex::encode(self.0.serialize()) == other
+    }
+}
+
+impl PartialEq<&str> for Address {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// Computes the checksum of an address payload.
+///
+/// # Parameters
+///
+/// * `payload` - The version byte followed by the public key
+///
+/// # Returns
+///
+/// The first CHECKSUM_LEN bytes of the double SHA-256 hash of the payload.
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let hash = Sha256Hasher.hash(&Sha256Hasher.hash(payload));
+
+    let mut checksum = [0; CHECKSUM_LEN];
+    checksum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+    checksum
+}
+
+/// Encodes a public key as a Base58Check address.
+///
+/// # Parameters
+///
+/// * `pubkey` - The public key to encode
+///
+/// # Returns
+///
+/// The Base58Check encoded address.
+///
+/// # Functionality
+///
+/// - Builds the payload from the version byte and the compressed public key
+/// - Hashes the payload and appends a 4-byte checksum
+/// - Encodes the payload and checksum as Base58
+pub fn encode_address(pubkey: &PublicKey) -> String {
+    let mut payload = Vec::with_capacity(1 + PUBLIC_KEY_LEN + CHECKSUM_LEN);
+    payload.push(ADDRESS_VERSION);
+    payload.extend_from_slice(&pubkey.serialize());
+
+    let checksum = checksum(&payload);
+    payload.extend_from_slice(&checksum);
+
+    bs58::encode(payload).into_string()
+}
+
+/// Decodes a Base58Check address into a public key.
+///
+/// # Parameters
+///
+/// * `s` - The Base58Check encoded address
+///
+/// # Returns
+///
+/// `Result<PublicKey, AddressError>`
+///
+/// - `Ok(PublicKey)` if the address is well formed
+/// - `Err(AddressError)` describing why the address was rejected
+///
+/// # Functionality
+///
+/// - Decodes the address from Base58
+/// - Checks the decoded length and version byte
+/// - Recomputes the checksum of the payload and compares it to the appended one
+/// - Parses the payload as a compressed public key
+pub fn decode_address(s: &str) -> Result<PublicKey, AddressError> {
+    let bytes = bs58::decode(s)
+        .into_vec()
+        .map_err(|_| AddressError::InvalidBase58)?;
+
+    if bytes.len() != 1 + PUBLIC_KEY_LEN + CHECKSUM_LEN {
+        return Err(AddressError::InvalidLength);
+    }
+
+    let (payload, expected_checksum) = bytes.split_at(1 + PUBLIC_KEY_LEN);
+
+    if checksum(payload) != expected_checksum {
+        return Err(AddressError::InvalidChecksum);
+    }
+
+    if payload[0] != ADDRESS_VERSION {
+        return Err(AddressError::InvalidVersion);
+    }
+
+    PublicKey::from_slice(&payload[1..]).map_err(|_| AddressError::InvalidPublicKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::address;
+
+    #[test]
+    fn encoded_address_decodes_to_its_public_key() {
+        for seed in 1..=5 {
+            let encoded = encode_address(address(seed).public_key());
+            assert!(encoded.starts_with('1'));
+            assert_eq!(decode_address(&encoded), Ok(*address(seed).public_key()));
+        }
+    }
+
+    #[test]
+    fn flipped_character_fails_the_checksum() {
+        let encoded = encode_address(address(1).public_key());
+
+        for position in encoded.len() - 10..encoded.len() {
+            let mut flipped: Vec<char> = encoded.chars().collect();
+            flipped[position] = if flipped[position] == '2' { '3' } else { '2' };
+            let flipped: String = flipped.into_iter().collect();
+
+            assert_eq!(decode_address(&flipped), Err(AddressError::InvalidChecksum));
+        }
+    }
+
+    #[test]
+    fn malformed_addresses_are_rejected() {
+        let encoded = encode_address(address(1).public_key());
+
+        assert_eq!(decode_address("0OIl"), Err(AddressError::InvalidBase58));
+        assert_eq!(
+            decode_address(&encoded[..encoded.len() - 1]),
+            Err(AddressError::InvalidLength)
+        );
+
+        let mut payload = vec![0x01];
+        payload.extend_from_slice(&address(1).public_key().serialize());
+        payload.extend_from_slice(&checksum(&payload));
+        assert_eq!(
+            decode_address(&bs58::encode(payload).into_string()),
+            Err(AddressError::InvalidVersion)
+        );
+    }
+
+    #[test]
+    fn address_parses_compressed_and_uncompressed_keys_alike() {
+        let key = *address(1).public_key();
+        let compressed: Address = hex::encode(key.serialize()).parse().unwrap();
+        let uncompressed: Address = hex::encode(key.serialize_uncompressed()).parse().unwrap();
+
+        assert_eq!(compressed, uncompressed);
+        assert_eq!(compressed.to_string(), hex::encode(key.serialize()));
+        assert_eq!(
+            "not a key".parse::<Address>(),
+            Err(AddressError::InvalidPublicKey)
+        );
+    }
+}
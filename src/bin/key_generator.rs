@@ -1,28 +1,278 @@
-use rand::Rng;
+use bip39::Mnemonic;
+use cryptobeca::address::encode_address;
+use cryptobeca::keystore::Keystore;
+use hmac::{Hmac, Mac};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use sha2::Sha512;
+use std::env;
+use std::path::Path;
+use std::process;
 
-/// Generates a random secp256k1 key pair.
+/// Generates a secp256k1 key pair.
+///
+/// # Options
+///
+/// * `--compressed` - Outputs the 33-byte compressed public key instead of
+///   the 65-byte uncompressed one
+/// * `--mnemonic [12|24]` - Generates a BIP39 mnemonic phrase of 12 (default)
+///   or 24 words and derives the key pair from it
+/// * `--from-mnemonic "<phrase>"` - Reconstructs the key pair from an existing
+///   BIP39 mnemonic phrase
+/// * `--seed <hex>` - Generates the key pair deterministically from a 32-byte hex
+///   seed, the same seed always giving the same key pair. For test fixtures only.
+/// * `--address` - Also outputs the Base58Check address of the public key
+/// * `--out <path>` - Writes the key pair to a password encrypted keystore file
+///   instead of printing the private key
+/// * `--load <path>` - Decrypts the key pair of an existing keystore file
 ///
 /// # Functionality
 ///
-/// - Initializes thread RNG  
-/// - Generates 32 random bytes for private key
-/// - Converts private key bytes to SecretKey
+/// - Parses the command line options
+/// - Decrypts the private key from a keystore with a prompted password, if requested
+/// - Otherwise generates a random private key, or derives it from a mnemonic phrase
+///   or a seed
 /// - Derives public key from private key
 /// - Encodes private and public keys as hex strings
-/// - Prints private key and public key
+/// - Prints the mnemonic phrase if one was generated
+/// - Writes the keystore if requested, otherwise prints the private key
+/// - Prints the public key, and the Base58Check address if requested
 fn main() {
-    let mut rng = rand::thread_rng();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let compressed = args.iter().any(|arg| arg == "--compressed");
+    let address = args.iter().any(|arg| arg == "--address");
+
+    if args.iter().any(|arg| arg == "--load") {
+        let path = option_value(&args, "--load").unwrap_or_else(|| {
+            eprintln!("Missing path after --load");
+            process::exit(1);
+        });
+
+        let keystore = Keystore::load_from_file(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Invalid keystore: {}", err);
+            process::exit(1);
+        });
+
+        let password = prompt_password("Keystore password: ");
+        let secret_key = keystore.decrypt(&password).unwrap_or_else(|err| {
+            eprintln!("Could not decrypt keystore: {}", err);
+            process::exit(1);
+        });
+
+        print_key_pair(&secret_key, compressed, address);
+        return;
+    }
+
+    let secret_key = if args.iter().any(|arg| arg == "--from-mnemonic") {
+        let phrase = option_value(&args, "--from-mnemonic").unwrap_or_else(|| {
+            eprintln!("Missing phrase after --from-mnemonic");
+            process::exit(1);
+        });
+
+        let mnemonic = Mnemonic::parse(phrase).unwrap_or_else(|err| {
+            eprintln!("Invalid mnemonic phrase: {}", err);
+            process::exit(1);
+        });
+
+        secret_key_from_mnemonic(&mnemonic)
+    } else if args.iter().any(|arg| arg == "--mnemonic") {
+        let word_count = match option_value(&args, "--mnemonic") {
+            None | Some("12") => 12,
+            Some("24") => 24,
+            Some(other) => {
+                eprintln!("Invalid word count {}, expected 12 or 24", other);
+                process::exit(1);
+            }
+        };
+
+        let mnemonic = Mnemonic::generate(word_count).expect("Invalid word count");
+        println!("Mnemonic Phrase: {}", mnemonic);
+
+        secret_key_from_mnemonic(&mnemonic)
+    } else if args.iter().any(|arg| arg == "--seed") {
+        let seed = option_value(&args, "--seed").unwrap_or_else(|| {
+            eprintln!("Missing hex seed after --seed");
+            process::exit(1);
+        });
+
+        let seed = parse_seed(seed).unwrap_or_else(|| {
+            eprintln!("Invalid seed, expected 32 hex encoded bytes");
+            process::exit(1);
+        });
+
+        eprintln!("Warning: a key generated with --seed is known to anyone with the seed, only use it for testing");
+
+        random_secret_key(&mut ChaCha20Rng::from_seed(seed))
+    } else {
+        random_secret_key(&mut rand::thread_rng())
+    };
+
+    if args.iter().any(|arg| arg == "--out") {
+        let path = option_value(&args, "--out").unwrap_or_else(|| {
+            eprintln!("Missing path after --out");
+            process::exit(1);
+        });
+
+        let password = prompt_password("New keystore password: ");
+        if password != prompt_password("Repeat password: ") {
+            eprintln!("Passwords do not match");
+            process::exit(1);
+        }
+
+        Keystore::encrypt(&secret_key, &password, compressed)
+            .save_to_file(Path::new(path))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not write keystore: {}", err);
+                process::exit(1);
+            });
+
+        println!("Keystore written to {}", path);
+        print_public_key(&secret_key, compressed, address);
+    } else {
+        print_key_pair(&secret_key, compressed, address);
+    }
+}
+
+/// Prompts for a password without echoing it.
+///
+/// # Parameters
+///
+/// * `prompt` - The text displayed before reading the password
+///
+/// # Returns
+///
+/// The password typed by the user.
+fn prompt_password(prompt: &str) -> String {
+    rpassword::prompt_password(prompt).unwrap_or_else(|err| {
+        eprintln!("Could not read password: {}", err);
+        process::exit(1);
+    })
+}
+
+/// Gets the value following a command line option.
+///
+/// # Parameters
+///
+/// * `args` - The command line arguments
+/// * `name` - The option name, e.g. `--mnemonic`
+///
+/// # Returns
+///
+/// The argument after the option, or None if the option is absent or is
+/// followed by another option.
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == name)?;
+
+    args.get(position + 1)
+        .map(String::as_str)
+        .filter(|value| !value.starts_with("--"))
+}
+
+/// Parses the seed of the `--seed` option.
+///
+/// # Parameters
+///
+/// * `seed` - The hex encoded seed
+///
+/// # Returns
+///
+/// The 32 seed bytes, or None if the seed is not 32 hex encoded bytes.
+fn parse_seed(seed: &str) -> Option<[u8; 32]> {
+    hex::decode(seed).ok()?.try_into().ok()
+}
+
+/// Generates a random secp256k1 secret key.
+///
+/// # Parameters
+///
+/// * `rng` - The random number generator, the thread RNG or a seeded one
+///
+/// # Functionality
+///
+/// - Generates 32 random bytes for private key
+/// - Converts private key bytes to SecretKey
+fn random_secret_key(rng: &mut impl Rng) -> SecretKey {
     let mut private_key_bytes: [u8; 32] = [0; 32];
     rng.fill(&mut private_key_bytes);
 
-    let secp = Secp256k1::new();
-    let secret_key = SecretKey::from_slice(&private_key_bytes).expect("Invalid private key");
-    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    SecretKey::from_slice(&private_key_bytes).expect("Invalid private key")
+}
+
+/// Derives a secp256k1 secret key from a BIP39 mnemonic phrase.
+///
+/// # Parameters
+///
+/// * `mnemonic` - The mnemonic phrase, with a valid checksum
+///
+/// # Returns
+///
+/// The secret key derived from the mnemonic.
+///
+/// # Functionality
+///
+/// - Derives the 64-byte BIP39 seed with an empty passphrase
+/// - Computes the BIP32 master key as HMAC-SHA512 of the seed keyed with "Bitcoin seed"
+/// - Converts the first 32 bytes of the master key to a SecretKey
+fn secret_key_from_mnemonic(mnemonic: &Mnemonic) -> SecretKey {
+    let seed = mnemonic.to_seed("");
+
+    let mut mac =
+        Hmac::<Sha512>::new_from_slice(b"Bitcoin seed").expect("HMAC accepts any key length");
+    mac.update(&seed);
+    let master_key = mac.finalize().into_bytes();
 
+    SecretKey::from_slice(&master_key[..32]).expect("Invalid private key")
+}
+
+/// Prints a secp256k1 key pair.
+///
+/// # Parameters
+///
+/// * `secret_key` - The private key to print
+/// * `compressed` - Whether to print the public key in compressed format
+/// * `address` - Whether to also print the Base58Check address
+///
+/// # Functionality
+///
+/// - Encodes private key as a hex string
+/// - Prints private key
+/// - Calls print_public_key() to print the public key
+fn print_key_pair(secret_key: &SecretKey, compressed: bool, address: bool) {
     let private_key_hex = hex::encode(&secret_key[..]);
-    let public_key_hex = hex::encode(public_key.serialize_uncompressed());
 
     println!("Private Key (Hex): {}", private_key_hex);
-    println!("Public Key (Hex): {}", public_key_hex);
+
+    print_public_key(secret_key, compressed, address);
+}
+
+/// Prints the public key of a secp256k1 key pair.
+///
+/// # Parameters
+///
+/// * `secret_key` - The private key to derive the public key from
+/// * `compressed` - Whether to print the public key in compressed format
+/// * `address` - Whether to also print the Base58Check address
+///
+/// # Functionality
+///
+/// - Derives public key from private key
+/// - Encodes public key as a hex string
+/// - Prints public key
+/// - Prints the Base58Check address if requested
+fn print_public_key(secret_key: &SecretKey, compressed: bool, address: bool) {
+    let secp = Secp256k1::new();
+    let public_key = PublicKey::from_secret_key(&secp, secret_key);
+
+    if compressed {
+        let public_key_hex = hex::encode(public_key.serialize());
+        println!("Public Key (Hex, 33-byte compressed): {}", public_key_hex);
+    } else {
+        let public_key_hex = hex::encode(public_key.serialize_uncompressed());
+        println!("Public Key (Hex, 65-byte uncompressed): {}", public_key_hex);
+    }
+
+    if address {
+        println!("Address (Base58Check): {}", encode_address(&public_key));
+    }
 }
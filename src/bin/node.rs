@@ -0,0 +1,288 @@
+use clap::Parser;
+use cryptobeca::address::*;
+use cryptobeca::blockchain::*;
+use cryptobeca::rpc;
+use cryptobeca::storage::*;
+use cryptobeca::transaction::*;
+use cryptobeca::units::*;
+use log::{error, info};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// The difficulty of a newly created chain.
+const DEFAULT_DIFFICULTY: u32 = 8;
+
+/// The mining reward of a newly created chain, in smallest units.
+const DEFAULT_MINING_REWARD: u64 = 100 * COIN;
+
+/// The number of threads serving requests.
+const WORKER_THREADS: usize = 4;
+
+/// Cli struct.
+///
+/// Represents the node options.
+///
+/// # Fields
+///
+/// * `listen` - The address the HTTP server listens on
+/// * `chain` - The file the chain is loaded from and saved to after each change,
+///   the chain is only kept in memory if absent
+/// * `store` - The directory the blocks are stored in one file each, written as they
+///   are added, instead of saving the whole chain to a file
+#[derive(Parser)]
+#[command(version, about = "A node serving the cryptobeca blockchain over HTTP")]
+struct Cli {
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    #[arg(long)]
+    chain: Option<PathBuf>,
+    #[arg(long, conflicts_with = "chain")]
+    store: Option<PathBuf>,
+}
+
+/// Runs a node serving the chain state over HTTP.
+///
+/// # Routes
+///
+/// * `GET /blocks` - Returns the blocks of the chain as JSON
+/// * `GET /balance/<address>` - Returns the balance of an address as JSON
+/// * `POST /transactions` - Adds a JSON signed transaction to the pending transactions
+/// * `POST /mine?reward=<address>` - Mines the pending transactions, rewarding the address
+/// * `POST /rpc` - Answers a JSON-RPC 2.0 request
+///
+/// # Functionality
+///
+/// - Loads the environment variables and initializes logging
+/// - Opens the block store, or loads the chain file, or creates a new chain if it does not exist,
+///   rejecting a stored chain whose genesis block differs from the one of a new chain
+/// - Holds the chain behind a Mutex shared by WORKER_THREADS threads
+/// - Each thread answers incoming requests with handle_request()
+/// - Saves the chain back to its file after each change
+fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let cli = Cli::parse();
+
+    let blockchain = match (&cli.store, &cli.chain) {
+        (Some(dir), _) => match open_store(dir) {
+            Ok(blockchain) => blockchain,
+            Err(err) => {
+                error!("Could not open {}: {}", dir.display(), err);
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, Some(path)) if path.exists() => match Blockchain::load_from_file(path) {
+            Ok(blockchain) => blockchain,
+            Err(err) => {
+                error!("Could not load {}: {}", path.display(), err);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => Blockchain::new(DEFAULT_DIFFICULTY, DEFAULT_MINING_REWARD),
+    };
+
+    let server = match Server::http(&cli.listen) {
+        Ok(server) => Arc::new(server),
+        Err(err) => {
+            error!("Could not listen on {}: {}", cli.listen, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    info!("Listening on http://{}", cli.listen);
+
+    let blockchain = Arc::new(Mutex::new(blockchain));
+    let chain_path = Arc::new(cli.chain);
+
+    let workers: Vec<_> = (0..WORKER_THREADS)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let blockchain = Arc::clone(&blockchain);
+            let chain_path = Arc::clone(&chain_path);
+
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve(request, &blockchain, chain_path.as_ref().as_deref());
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Opens a chain kept in a block store.
+///
+/// # Parameters
+///
+/// * `dir` - The directory of the block files
+///
+/// # Returns
+///
+/// The chain, or the error message if the directory could not be opened or the
+/// stored chain is not valid or belongs to another network
+fn open_store(dir: &Path) -> Result<Blockchain, String> {
+    let storage = FileStorage::open(dir).map_err(|err| err.to_string())?;
+
+    Blockchain::with_storage(DEFAULT_DIFFICULTY, DEFAULT_MINING_REWARD, Box::new(storage))
+        .map_err(|err| err.to_string())
+}
+
+/// Answers a request and saves the chain if it changed.
+///
+/// # Parameters
+///
+/// * `request` - The incoming HTTP request
+/// * `blockchain` - The shared chain
+/// * `chain_path` - The file the chain is saved to, if any
+fn serve(mut request: Request, blockchain: &Mutex<Blockchain>, chain_path: Option<&Path>) {
+    let mut body = String::new();
+    let (status, json) = if request.as_reader().read_to_string(&mut body).is_err() {
+        (400, error_json("Request body is not valid UTF-8"))
+    } else {
+        let mut blockchain = blockchain.lock().expect("Blockchain lock poisoned");
+        let (status, json) =
+            handle_request(&mut blockchain, request.method(), request.url(), &body);
+
+        if let (Some(path), Method::Post, 200..=299) = (chain_path, request.method(), status) {
+            if let Err(err) = blockchain.save_to_file(path) {
+                error!("Could not save {}: {}", path.display(), err);
+            }
+        }
+
+        (status, json)
+    };
+
+    info!("{} {} -> {}", request.method(), request.url(), status);
+
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("Static header is valid");
+    let response = Response::from_string(json)
+        .with_status_code(status)
+        .with_header(content_type);
+
+    if let Err(err) = request.respond(response) {
+        error!("Could not send response: {}", err);
+    }
+}
+
+/// Routes a request to the chain.
+///
+/// # Parameters
+///
+/// * `blockchain` - The chain
+/// * `method` - The HTTP method
+/// * `url` - The request path, with its query string
+/// * `body` - The request body
+///
+/// # Returns
+///
+/// The HTTP status code and the JSON response body
+///
+/// # Functionality
+///
+/// - Returns the blocks for `GET /blocks`
+/// - Returns the balance of the address for `GET /balance/<address>`, answering
+///   400 if it is not a valid address
+/// - Parses the body as a transaction and adds it for `POST /transactions`,
+///   answering 400 if it is malformed or rejected
+/// - Mines the pending transactions for `POST /mine?reward=<address>`,
+///   answering 400 without a valid reward address and 409 if the consensus refuses the block
+/// - Answers the JSON-RPC 2.0 request in the body for `POST /rpc`, with 204 and no
+///   body for a notification
+/// - Answers 404 for any other route
+fn handle_request(
+    blockchain: &mut Blockchain,
+    method: &Method,
+    url: &str,
+    body: &str,
+) -> (u16, String) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    match (method, path) {
+        (Method::Get, "/blocks") => (200, to_json(&blockchain.blocks().collect::<Vec<_>>())),
+        (Method::Get, path) if path.starts_with("/balance/") => {
+            let address = match Address::from_str(&path["/balance/".len()..]) {
+                Ok(address) => address,
+                Err(err) => return (400, error_json(&format!("Invalid address: {}", err))),
+            };
+            let balance = blockchain.get_balance_of_address(&address);
+
+            (
+                200,
+                serde_json::json!({ "address": address, "balance": balance }).to_string(),
+            )
+        }
+        (Method::Post, "/transactions") => {
+            let transaction: Transaction = match serde_json::from_str(body) {
+                Ok(transaction) => transaction,
+                Err(err) => return (400, error_json(&format!("Invalid transaction: {}", err))),
+            };
+            let hash = transaction.compute_hash();
+
+            match blockchain.add_transaction(transaction) {
+                Ok(()) => (201, serde_json::json!({ "hash": hash }).to_string()),
+                Err(err) => (400, error_json(&err.to_string())),
+            }
+        }
+        (Method::Post, "/mine") => {
+            let reward_address = query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == "reward")
+                .map(|(_, value)| value)
+                .filter(|value| !value.is_empty());
+
+            match reward_address.map(Address::from_str) {
+                Some(Ok(reward_address)) => {
+                    match blockchain.mine_pending_transactions(reward_address) {
+                        Ok(()) => (200, to_json(blockchain.get_latest_block())),
+                        Err(err) => (409, error_json(&err.to_string())),
+                    }
+                }
+                Some(Err(err)) => (400, error_json(&format!("Invalid reward address: {}", err))),
+                None => (400, error_json("Missing reward address")),
+            }
+        }
+        (Method::Post, "/rpc") => match rpc::handle_request(blockchain, body) {
+            Some(json) => (200, json),
+            None => (204, String::new()),
+        },
+        _ => (404, error_json("Not found")),
+    }
+}
+
+/// Serializes a value into a JSON response body.
+///
+/// # Parameters
+///
+/// * `value` - The value to serialize
+///
+/// # Returns
+///
+/// The JSON string
+fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("Chain state is always serializable")
+}
+
+/// Builds a JSON error response body.
+///
+/// # Parameters
+///
+/// * `message` - The error message
+///
+/// # Returns
+///
+/// The JSON string with the message under an `error` key
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
@@ -1,6 +1,63 @@
+use crate::blockchain::ValidationError;
+use crate::error::*;
+use crate::hasher::*;
+use crate::light::RawHeader;
 use crate::transaction::*;
 use chrono::{DateTime, Utc};
-use sha3::Digest;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of nonces a mining worker tries between two checks of the cancel flag.
+pub const CANCEL_CHECK_INTERVAL: u64 = 4096;
+
+/// The interval between two invocations of a mining progress callback.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the mining thread wakes up to check if progress should be reported.
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The lowest difficulty blocks are mined at, a difficulty of 0 would accept any hash.
+pub const MIN_DIFFICULTY: u32 = 1;
+
+/// The highest difficulty blocks are mined at, the last one leaving a non-zero target.
+///
+/// Anything past 64 leading zero bits cannot be mined in practice, this bound
+/// only keeps the target meaningful.
+pub const MAX_DIFFICULTY: u32 = 255;
+
+/// MiningError enum.
+///
+/// Represents the reasons mining stopped without a valid nonce.
+///
+/// # Variants
+///
+/// * `Cancelled` - Returned when mining was aborted through the cancel flag.
+/// * `TargetUnreachable` - Returned when no nonce up to the maximum nonce gives a hash
+///   meeting the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiningError {
+    Cancelled,
+    TargetUnreachable,
+}
+
+impl fmt::Display for MiningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MiningError::Cancelled => write!(f, "Mining was cancelled"),
+            MiningError::TargetUnreachable => {
+                write!(f, "No nonce up to the maximum nonce meets the target")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MiningError {}
 
 /// Block struct.
 ///
@@ -8,25 +65,52 @@ use sha3::Digest;
 ///
 /// # Fields
 ///
-/// * `timestamp` - The timestamp when the block was created. 
+/// * `index` - The height of this block in the chain, 0 for the genesis block.
+/// * `timestamp` - The timestamp when the block was created, serialized as RFC3339.
 /// * `transactions` - The transactions included in this block.
+/// * `merkle_root` - The Merkle root of the transactions' hashes.
 /// * `previous_hash` - The hash of the previous block in the chain.
 /// * `hash` - The hash of this block.
 /// * `nonce` - The nonce used to mine this block.
-#[derive(Debug)]
+/// * `hash_algorithm` - The hash function used to hash and mine this block.
+/// * `pow_function` - The function the block hash is mined with, the hash algorithm
+///   unless a memory-hard function was set with set_pow_function().
+/// * `producer` - The address of the staker that produced this block, None for mined blocks.
+/// * `signature` - The producer's hex encoded DER signature of the block hash, None for
+///   mined blocks.
+/// * `difficulty` - The difficulty the block was mined at, its hash meets the
+///   target of this difficulty.
+/// * `pruned` - True if the transactions were discarded by Blockchain::prune(), only
+///   their Merkle root is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
+    pub index: u64,
     pub timestamp: DateTime<Utc>,
     pub transactions: Vec<Transaction>,
+    pub merkle_root: String,
     pub previous_hash: String,
     pub hash: String,
     pub nonce: u64,
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    #[serde(default)]
+    pub pow_function: PowFunction,
+    #[serde(default)]
+    pub producer: Option<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub difficulty: u32,
+    #[serde(default)]
+    pub pruned: bool,
 }
 
 impl Block {
-    /// Creates a new Block instance.
+    /// Creates a new Block instance hashed with SHA3-256.
     ///
     /// # Parameters
     ///
+    /// * `index` - The height of the block in the chain
     /// * `transactions` - The transactions to include in the block
     /// * `previous_hash` - The hash of the previous block
     ///
@@ -36,127 +120,1605 @@ impl Block {
     ///
     /// # Functionality
     ///
-    /// - Gets the current timestamp
+    /// - Calls new_with_algorithm() with the default hash algorithm
+    pub fn new(index: u64, transactions: Vec<Transaction>, previous_hash: String) -> Self {
+        Self::new_with_algorithm(index, transactions, previous_hash, HashAlgorithm::default())
+    }
+
+    /// Creates a new Block instance with a fixed timestamp.
+    ///
+    /// Meant for reproducible block hashes, e.g. in tests.
+    ///
+    /// # Parameters
+    ///
+    /// * `index` - The height of the block in the chain
+    /// * `transactions` - The transactions to include in the block
+    /// * `previous_hash` - The hash of the previous block
+    /// * `timestamp` - The timestamp of the block
+    ///
+    /// # Returns
+    ///
+    /// A new Block instance
+    ///
+    /// # Functionality
+    ///
+    /// - Calls new_with_algorithm_at() with the default hash algorithm
+    pub fn new_at(
+        index: u64,
+        transactions: Vec<Transaction>,
+        previous_hash: String,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self::new_with_algorithm_at(
+            index,
+            transactions,
+            previous_hash,
+            HashAlgorithm::default(),
+            timestamp,
+        )
+    }
+
+    /// Creates a new Block instance hashed with the provided hash algorithm.
+    ///
+    /// # Parameters
+    ///
+    /// * `index` - The height of the block in the chain
+    /// * `transactions` - The transactions to include in the block
+    /// * `previous_hash` - The hash of the previous block
+    /// * `hash_algorithm` - The hash function used to hash and mine the block
+    ///
+    /// # Returns
+    ///
+    /// A new Block instance
+    ///
+    /// # Functionality
+    ///
+    /// - Calls new_with_algorithm_at() with the current timestamp
+    pub fn new_with_algorithm(
+        index: u64,
+        transactions: Vec<Transaction>,
+        previous_hash: String,
+        hash_algorithm: HashAlgorithm,
+    ) -> Self {
+        Self::new_with_algorithm_at(
+            index,
+            transactions,
+            previous_hash,
+            hash_algorithm,
+            Utc::now(),
+        )
+    }
+
+    /// Creates a new Block instance hashed with the provided hash algorithm, with a fixed timestamp.
+    ///
+    /// # Parameters
+    ///
+    /// * `index` - The height of the block in the chain
+    /// * `transactions` - The transactions to include in the block
+    /// * `previous_hash` - The hash of the previous block
+    /// * `hash_algorithm` - The hash function used to hash and mine the block
+    /// * `timestamp` - The timestamp of the block
+    ///
+    /// # Returns
+    ///
+    /// A new Block instance
+    ///
+    /// # Functionality
+    ///
+    /// - Computes the Merkle root of the transactions
     /// - Calculates the hash for the new block
-    /// - Returns a Block with the provided transactions, previous hash, 
-    ///   calculated hash, and nonce of 0
-    pub fn new(transactions: Vec<Transaction>, previous_hash: String) -> Self {
-        let timestamp = Utc::now();
-        let hash = Self::calculate_hash(&timestamp, &transactions, &previous_hash, 0);
+    /// - Returns a Block with the provided index, timestamp, transactions, Merkle root,
+    ///   previous hash, hash algorithm, calculated hash, nonce and difficulty of 0,
+    ///   the default proof of work function and no producer
+    pub fn new_with_algorithm_at(
+        index: u64,
+        transactions: Vec<Transaction>,
+        previous_hash: String,
+        hash_algorithm: HashAlgorithm,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let merkle_root = Self::compute_merkle_root(&hash_algorithm, &transactions);
+        let hash = Self::calculate_hash(
+            &hash_algorithm,
+            index,
+            &timestamp,
+            &merkle_root,
+            &previous_hash,
+            0,
+        );
 
         Self {
+            index,
             timestamp,
             transactions,
+            merkle_root,
             previous_hash,
             hash,
             nonce: 0,
+            hash_algorithm,
+            pow_function: PowFunction::default(),
+            producer: None,
+            signature: None,
+            difficulty: 0,
+            pruned: false,
+        }
+    }
+
+    /// Gets the header of the block, without its transactions.
+    ///
+    /// # Returns
+    ///
+    /// A BlockHeader with the hashed fields, the hash and the mining parameters of the block
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp,
+            previous_hash: self.previous_hash.clone(),
+            merkle_root: self.merkle_root.clone(),
+            nonce: self.nonce,
+            hash: self.hash.clone(),
+            hash_algorithm: self.hash_algorithm,
+            pow_function: self.pow_function,
+            difficulty: self.difficulty,
+        }
+    }
+
+    /// Gets the hash function of the block hash.
+    ///
+    /// # Returns
+    ///
+    /// The BlockHasher combining the hash algorithm and the proof of work function of the block
+    pub fn hasher(&self) -> BlockHasher {
+        BlockHasher {
+            hash_algorithm: self.hash_algorithm,
+            pow_function: self.pow_function,
         }
     }
 
+    /// Sets the function the block hash is mined with.
+    ///
+    /// # Parameters
+    ///
+    /// * `pow_function` - The proof of work function, e.g. PowFunction::Scrypt
+    ///
+    /// # Functionality
+    ///
+    /// - Sets the proof of work function of the block
+    /// - Recalculates the block hash with hasher(), keeping the nonce
+    pub fn set_pow_function(&mut self, pow_function: PowFunction) {
+        self.pow_function = pow_function;
+        self.hash = Self::calculate_hash(
+            &self.hasher(),
+            self.index,
+            &self.timestamp,
+            &self.merkle_root,
+            &self.previous_hash,
+            self.nonce,
+        );
+    }
+
     /// Calculates the hash for a block.
     ///
     /// # Parameters
     ///
+    /// * `hasher` - The hash function to use
+    /// * `index` - The height of the block
     /// * `timestamp` - The timestamp of the block
-    /// * `transactions` - The transactions in the block
+    /// * `merkle_root` - The Merkle root of the block transactions
     /// * `previous_hash` - The previous block hash  
     /// * `nonce` - The mining nonce
     ///
     /// # Returns
     ///  
-    /// The SHA3-256 hash of the block details as a hex encoded string.
+    /// The hash of the block details as a hex encoded string.
     ///
     /// # Functionality
     ///
-    /// - Converts transactions to a string  
-    /// - Concatenates transaction string, timestamp, previous hash and nonce
-    /// - Feeds concatenated string into SHA3-256
+    /// - Builds the hashing prefix from index, Merkle root, timestamp and previous hash
+    /// - Appends the nonce to the prefix
+    /// - Feeds the concatenated bytes into the hasher
     /// - Encodes the raw bytes as hex
     pub fn calculate_hash(
+        hasher: &dyn Hasher,
+        index: u64,
         timestamp: &DateTime<Utc>,
-        transactions: &[Transaction],
+        merkle_root: &str,
         previous_hash: &str,
         nonce: u64,
     ) -> String {
-        let transactions_str: String = transactions
-            .iter()
-            .map(|transaction| format!("{:?}", transaction))
-            .collect();
+        let mut buffer = Self::hash_prefix(index, timestamp, merkle_root, previous_hash);
+        let prefix_len = buffer.len();
+        Self::hash_with_nonce(hasher, &mut buffer, prefix_len, nonce)
+    }
 
+    /// Builds the part of the hashed block data that does not depend on the nonce.
+    ///
+    /// # Parameters
+    ///
+    /// * `index` - The height of the block
+    /// * `timestamp` - The timestamp of the block
+    /// * `merkle_root` - The Merkle root of the block transactions
+    /// * `previous_hash` - The previous block hash
+    ///
+    /// # Returns
+    ///
+    /// The concatenated index, Merkle root, timestamp and previous hash as bytes.
+    pub(crate) fn hash_prefix(
+        index: u64,
+        timestamp: &DateTime<Utc>,
+        merkle_root: &str,
+        previous_hash: &str,
+    ) -> Vec<u8> {
         format!(
-            "{:x}",
-            sha3::Sha3_256::digest(
-                format!(
-                    "{}{}{}{}",
-                    transactions_str,
-                    timestamp.to_rfc3339(),
-                    previous_hash,
-                    nonce
-                )
-                .as_bytes()
-            )
+            "{}{}{}{}",
+            index,
+            merkle_root,
+            timestamp.to_rfc3339(),
+            previous_hash
         )
+        .into_bytes()
     }
 
-    /// Mines the block by finding a valid nonce.
+    /// Hashes a prefix buffer followed by a nonce.
     ///
     /// # Parameters
     ///
-    /// * `difficulty` - The mining difficulty 
+    /// * `hasher` - The hash function to use
+    /// * `buffer` - A buffer starting with the hashing prefix
+    /// * `prefix_len` - The length of the prefix, anything after it is discarded
+    /// * `nonce` - The mining nonce
     ///
-    /// # Returns 
+    /// # Returns
     ///
-    /// A success message with the block hash
+    /// The hash of the prefix and nonce as a hex encoded string.
     ///
     /// # Functionality
     ///
-    /// - Generates a string of 0s equal to the difficulty
-    /// - Increments the nonce and recalculates the hash until it starts with the 0s string
-    /// - Returns a success message with the final hash
-    pub fn mine_block(&mut self, difficulty: i64) -> String {
-        let prefix_zeros: String = (0..difficulty).map(|_| '0').collect();
+    /// - Truncates the buffer back to the prefix, so it can be reused across nonces
+    /// - Appends the decimal nonce without allocating a new string
+    /// - Feeds the buffer into the hasher and encodes the result as hex
+    fn hash_with_nonce(
+        hasher: &dyn Hasher,
+        buffer: &mut Vec<u8>,
+        prefix_len: usize,
+        nonce: u64,
+    ) -> String {
+        buffer.truncate(prefix_len);
+        write!(buffer, "{}", nonce).expect("Writing to a Vec cannot fail");
+        hasher.hash_hex(buffer)
+    }
 
-        while !self.hash.starts_with(&prefix_zeros) {
-            self.nonce += 1;
-            self.hash = Self::calculate_hash(
-                &self.timestamp,
-                &self.transactions,
-                &self.previous_hash,
-                self.nonce,
-            );
+    /// Computes the Merkle root of a list of transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `hasher` - The hash function to use
+    /// * `transactions` - The transactions to build the tree from
+    ///
+    /// # Returns
+    ///
+    /// The Merkle root as a hex encoded string.
+    ///
+    /// # Functionality
+    ///
+    /// - Returns a root of 64 zeros if there are no transactions
+    /// - Uses the hash of each transaction, recomputed with the hasher, as a leaf
+    /// - Duplicates the last hash of a level with an odd number of hashes
+    /// - Hashes each pair of hashes into a parent hash
+    /// - Repeats until a single hash, the root, remains
+    pub fn compute_merkle_root(hasher: &dyn Hasher, transactions: &[Transaction]) -> String {
+        if transactions.is_empty() {
+            return "0".repeat(64);
+        }
+
+        let mut level: Vec<String> = transactions
+            .iter()
+            .map(|transaction| transaction.compute_hash_with(hasher))
+            .collect();
+
+        while level.len() > 1 {
+            if level.len() % 2 == 1 {
+                level.push(level[level.len() - 1].clone());
+            }
+
+            level = level
+                .chunks(2)
+                .map(|pair| Self::hash_pair(hasher, &pair[0], &pair[1]))
+                .collect();
         }
 
-        format!("Block successfully mined: {}", self.hash)
+        level.remove(0)
     }
 
-    /// Validates all transactions in the block.
+    /// Builds a Merkle proof that a transaction is included in the block.
     ///
-    /// # Returns  
+    /// # Parameters
     ///
-    /// `Result<bool, String>`
+    /// * `tx_hash` - The hash of the transaction, computed with the block hash algorithm
     ///
-    /// - `Ok(true)` if all transactions are valid
-    /// - `Ok(false)` if any transaction is invalid
-    /// - `Err(String)` if there was an error validating a transaction
+    /// # Returns
+    ///
+    /// The sibling hash at each level of the tree from the leaf up, each paired with
+    /// true if the sibling is the left node, or None if the transaction is not in the block
     ///
     /// # Functionality
-    ///  
-    /// - Iterates through each transaction
-    /// - Calls transaction.is_valid() to validate
-    /// - If any transaction is invalid, returns Ok(false)
-    /// - If all are valid, returns Ok(true)
-    /// - Prints any validation error messages
-    pub fn has_valid_transactions(&self) -> Result<bool, String> {
-        for transaction in &self.transactions {
-            match transaction.is_valid() {
-                Ok(is_valid) => {
-                    if !is_valid {
-                        return Ok(false);
-                    }
-                }
-                Err(err_msg) => println!("Error validating transaction: {}", err_msg),
+    ///
+    /// - Finds the position of the transaction among the Merkle tree leaves
+    /// - At each level, duplicates the last hash of a level with an odd number of hashes,
+    ///   as compute_merkle_root() does
+    /// - Records the sibling of the current node and its side
+    /// - Hashes the level into its parent level and moves up to the parent node
+    pub fn merkle_proof(&self, tx_hash: &str) -> Option<Vec<(String, bool)>> {
+        let mut level: Vec<String> = self
+            .transactions
+            .iter()
+            .map(|transaction| transaction.compute_hash_with(&self.hash_algorithm))
+            .collect();
+
+        let mut position = level.iter().position(|hash| hash == tx_hash)?;
+        let mut proof = Vec::new();
+
+        while level.len() > 1 {
+            if level.len() % 2 == 1 {
+                level.push(level[level.len() - 1].clone());
             }
+
+            let sibling = position ^ 1;
+            proof.push((level[sibling].clone(), sibling < position));
+
+            level = level
+                .chunks(2)
+                .map(|pair| Self::hash_pair(&self.hash_algorithm, &pair[0], &pair[1]))
+                .collect();
+            position /= 2;
+        }
+
+        Some(proof)
+    }
+
+    /// Hashes a pair of Merkle tree nodes into their parent node.
+    ///
+    /// # Parameters
+    ///
+    /// * `hasher` - The hash function to use
+    /// * `left` - The left node hash
+    /// * `right` - The right node hash
+    ///
+    /// # Returns
+    ///
+    /// The hash of the concatenated node hashes as a hex encoded string.
+    fn hash_pair(hasher: &dyn Hasher, left: &str, right: &str) -> String {
+        hasher.hash_hex(format!("{}{}", left, right).as_bytes())
+    }
+
+    /// Converts a mining difficulty into a 256-bit target.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty, as the number of leading zero bits
+    ///
+    /// # Returns
+    ///
+    /// The target as a big-endian 32-byte array.
+    ///
+    /// # Functionality
+    ///
+    /// - Starts from the maximum target, with all bits set
+    /// - Shifts the target right by `difficulty` bits, so each unit of difficulty
+    ///   halves the target and doubles the expected mining work
+    /// - Caps the difficulty at 256 bits
+    pub fn difficulty_to_target(difficulty: u32) -> [u8; 32] {
+        let zero_bits = difficulty.min(256) as usize;
+        let mut target = [0xffu8; 32];
+
+        for (i, byte) in target.iter_mut().enumerate() {
+            let byte_start = i * 8;
+            if zero_bits >= byte_start + 8 {
+                *byte = 0;
+            } else if zero_bits > byte_start {
+                *byte = 0xff >> (zero_bits - byte_start);
+            }
+        }
+
+        target
+    }
+
+    /// Checks if a hash meets a 256-bit target.
+    ///
+    /// # Parameters
+    ///
+    /// * `hash` - The hex encoded block hash
+    /// * `target` - The big-endian 32-byte target
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the hash, read as a big-endian integer, is less than or
+    /// equal to the target, False otherwise or if the hash is not 32 hex encoded bytes
+    pub fn meets_target(hash: &str, target: &[u8; 32]) -> bool {
+        match hex::decode(hash) {
+            Ok(hash_bytes) if hash_bytes.len() == 32 => hash_bytes.as_slice() <= target.as_slice(),
+            _ => false,
+        }
+    }
+
+    /// Computes the expected number of hashes to find a nonce meeting a target.
+    ///
+    /// # Parameters
+    ///
+    /// * `target` - The big-endian 32-byte target
+    ///
+    /// # Returns
+    ///
+    /// The expected number of hashes as a f64, 2^256 divided by the number of
+    /// hashes meeting the target
+    pub fn expected_hashes(target: &[u8; 32]) -> f64 {
+        let matching_hashes = target
+            .iter()
+            .fold(0.0, |value, &byte| value * 256.0 + byte as f64)
+            + 1.0;
+
+        2f64.powi(256) / matching_hashes
+    }
+
+    /// Computes the expected work spent mining the block.
+    ///
+    /// # Returns
+    ///
+    /// The expected number of hashes to meet the target of the block difficulty,
+    /// 2 to the power of the difficulty, saturated at u128::MAX
+    pub fn work(&self) -> u128 {
+        1u128
+            .checked_shl(self.difficulty.min(128))
+            .unwrap_or(u128::MAX)
+    }
+
+    /// Gets the size of the block in bytes.
+    ///
+    /// # Returns
+    ///
+    /// The length of the hashed block header including an 8-byte nonce, plus the
+    /// serialized_size() of each transaction
+    pub fn serialized_size(&self) -> usize {
+        let header_size = Self::hash_prefix(
+            self.index,
+            &self.timestamp,
+            &self.merkle_root,
+            &self.previous_hash,
+        )
+        .len()
+            + std::mem::size_of::<u64>();
+
+        self.transactions
+            .iter()
+            .fold(header_size, |size, transaction| {
+                size + transaction.serialized_size()
+            })
+    }
+
+    /// Measures how many block hashes per second a single thread computes.
+    ///
+    /// # Parameters
+    ///
+    /// * `samples` - The number of hashes to time
+    ///
+    /// # Returns
+    ///
+    /// The measured hash rate in hashes per second, 0 for no samples
+    ///
+    /// # Functionality
+    ///
+    /// - Calls calculate_hash() with the default hash algorithm and a new nonce for each sample
+    /// - Divides the number of samples by the elapsed time
+    pub fn benchmark_hashrate(samples: u64) -> f64 {
+        if samples == 0 {
+            return 0.0;
+        }
+
+        let hasher = HashAlgorithm::default();
+        let timestamp = Utc::now();
+        let started = Instant::now();
+
+        for nonce in 0..samples {
+            std::hint::black_box(Self::calculate_hash(&hasher, 0, &timestamp, "", "0", nonce));
+        }
+
+        samples as f64 / started.elapsed().as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    /// Mines the block by finding a valid nonce.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty, as the number of leading zero bits
+    ///
+    /// # Returns 
+    ///
+    /// A success message with the block hash
+    ///
+    /// # Functionality
+    ///
+    /// - Calls mine_block_cancellable() with a cancel flag that is never set, so the
+    ///   difficulty is clamped between MIN_DIFFICULTY and MAX_DIFFICULTY
+    /// - Keeps searching until a nonce is found, use mine_block_with_max_nonce() to
+    ///   give up on a difficulty too high to be mined
+    ///
+    /// # Panics
+    ///
+    /// If no nonce up to u64::MAX meets the target
+    pub fn mine_block(&mut self, difficulty: u32) -> String {
+        self.mine_block_cancellable(difficulty, &AtomicBool::new(false))
+            .expect("Mining cannot stop before u64::MAX without setting the cancel flag")
+    }
+
+    /// Mines the block by finding a valid nonce, giving up past a maximum nonce.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty, as the number of leading zero bits
+    /// * `max_nonce` - The highest nonce tried
+    ///
+    /// # Returns
+    ///
+    /// `Result<String, MiningError>`
+    ///
+    /// - `Ok(String)` with a success message with the block hash
+    /// - `Err(MiningError::TargetUnreachable)` if no nonce up to max_nonce meets the
+    ///   target, the block being left unchanged
+    ///
+    /// # Functionality
+    ///
+    /// - Clamps the difficulty between MIN_DIFFICULTY and MAX_DIFFICULTY
+    /// - Calls mine_with() with the target of the difficulty, a cancel flag that is
+    ///   never set and the maximum nonce
+    /// - Records the difficulty on the block once mined
+    pub fn mine_block_with_max_nonce(
+        &mut self,
+        difficulty: u32,
+        max_nonce: u64,
+    ) -> Result<String, MiningError> {
+        let difficulty = difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+        let mined = self.mine_with(
+            &Self::difficulty_to_target(difficulty),
+            &AtomicBool::new(false),
+            max_nonce,
+            None,
+        )?;
+        self.difficulty = difficulty;
+        Ok(mined)
+    }
+
+    /// Mines the block by finding a nonce whose hash meets a target.
+    ///
+    /// # Parameters
+    ///
+    /// * `target` - The big-endian 32-byte target
+    ///
+    /// # Returns
+    ///
+    /// A success message with the block hash
+    ///
+    /// # Functionality
+    ///
+    /// - Calls mine_block_with_target_cancellable() with a cancel flag that is never set
+    ///
+    /// # Panics
+    ///
+    /// If no nonce up to u64::MAX meets the target
+    pub fn mine_block_with_target(&mut self, target: &[u8; 32]) -> String {
+        self.mine_block_with_target_cancellable(target, &AtomicBool::new(false))
+            .expect("Mining cannot stop before u64::MAX without setting the cancel flag")
+    }
+
+    /// Mines the block by finding a valid nonce, until a cancel flag is set.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty, as the number of leading zero bits
+    /// * `cancel` - A flag that aborts mining once set, e.g. from another thread
+    ///
+    /// # Returns
+    ///
+    /// `Result<String, MiningError>`
+    ///
+    /// - `Ok(String)` with a success message with the block hash
+    /// - `Err(MiningError::Cancelled)` if the cancel flag was set before a valid nonce
+    ///   was found
+    /// - `Err(MiningError::TargetUnreachable)` if no nonce up to u64::MAX meets the target
+    ///
+    /// # Functionality
+    ///
+    /// - Clamps the difficulty between MIN_DIFFICULTY and MAX_DIFFICULTY, so a
+    ///   difficulty of 0 cannot accept any hash
+    /// - Converts the difficulty to a 256-bit target
+    /// - Calls mine_block_with_target_cancellable() with the target
+    /// - Records the difficulty on the block once mined
+    pub fn mine_block_cancellable(
+        &mut self,
+        difficulty: u32,
+        cancel: &AtomicBool,
+    ) -> Result<String, MiningError> {
+        let difficulty = difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+        let mined = self
+            .mine_block_with_target_cancellable(&Self::difficulty_to_target(difficulty), cancel)?;
+        self.difficulty = difficulty;
+        Ok(mined)
+    }
+
+    /// Mines the block by finding a valid nonce, reporting progress along the way.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty, as the number of leading zero bits
+    /// * `on_progress` - A callback receiving the current nonce and the estimated
+    ///   hashes per second, invoked roughly every PROGRESS_INTERVAL
+    ///
+    /// # Returns
+    ///
+    /// A success message with the block hash
+    ///
+    /// # Functionality
+    ///
+    /// - Clamps the difficulty between MIN_DIFFICULTY and MAX_DIFFICULTY, so a
+    ///   difficulty of 0 cannot accept any hash
+    /// - Converts the difficulty to a 256-bit target
+    /// - Calls mine_with() with the target, a cancel flag that is never set and the callback
+    /// - Records the difficulty on the block once mined
+    ///
+    /// # Panics
+    ///
+    /// If no nonce up to u64::MAX meets the target
+    pub fn mine_block_with_progress(
+        &mut self,
+        difficulty: u32,
+        mut on_progress: impl FnMut(u64, f64),
+    ) -> String {
+        let difficulty = difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+        let mined = self
+            .mine_with(
+                &Self::difficulty_to_target(difficulty),
+                &AtomicBool::new(false),
+                u64::MAX,
+                Some(&mut on_progress),
+            )
+            .expect("Mining cannot stop before u64::MAX without setting the cancel flag");
+        self.difficulty = difficulty;
+        mined
+    }
+
+    /// Mines the block by finding a nonce whose hash meets a target, until a cancel flag is set.
+    ///
+    /// # Parameters
+    ///
+    /// * `target` - The big-endian 32-byte target
+    /// * `cancel` - A flag that aborts mining once set, e.g. from another thread
+    ///
+    /// # Returns
+    ///
+    /// `Result<String, MiningError>`
+    ///
+    /// - `Ok(String)` with a success message with the block hash
+    /// - `Err(MiningError::Cancelled)` if the cancel flag was set before a valid nonce
+    ///   was found
+    /// - `Err(MiningError::TargetUnreachable)` if no nonce up to u64::MAX meets the target
+    ///
+    /// # Functionality
+    ///
+    /// - Calls mine_with() with the target, the cancel flag, u64::MAX as maximum nonce
+    ///   and no progress callback
+    pub fn mine_block_with_target_cancellable(
+        &mut self,
+        target: &[u8; 32],
+        cancel: &AtomicBool,
+    ) -> Result<String, MiningError> {
+        self.mine_with(target, cancel, u64::MAX, None)
+    }
+
+    /// Mines the block by finding a nonce whose hash meets a target.
+    ///
+    /// # Parameters
+    ///
+    /// * `target` - The big-endian 32-byte target
+    /// * `cancel` - A flag that aborts mining once set, e.g. from another thread
+    /// * `max_nonce` - The highest nonce tried
+    /// * `on_progress` - An optional callback receiving the current nonce and the
+    ///   estimated hashes per second
+    ///
+    /// # Returns
+    ///
+    /// `Result<String, MiningError>`
+    ///
+    /// - `Ok(String)` with a success message with the block hash
+    /// - `Err(MiningError::Cancelled)` if the cancel flag was set before a valid nonce
+    ///   was found
+    /// - `Err(MiningError::TargetUnreachable)` if no nonce up to max_nonce meets the target
+    ///
+    /// # Functionality
+    ///
+    /// - Returns immediately if the current hash already meets the target
+    /// - Builds the nonce-independent hashing prefix once
+    /// - Spawns one worker thread per available CPU, each with its own copy of the prefix
+    /// - Each worker scans a disjoint set of nonces, worker `i` of `n` trying
+    ///   every nonce equal to `i` modulo `n`, hashing only the prefix followed
+    ///   by the nonce with the block hasher()
+    /// - Each worker stops past max_nonce, with checked additions so the nonce
+    ///   never wraps around to the nonces already tried
+    /// - Each worker checks the cancel flag every CANCEL_CHECK_INTERVAL nonces
+    ///   and stops all workers once it is set
+    /// - A worker finding a hash that meets the target records it if its nonce is
+    ///   the lowest found so far, and the workers stop once past the lowest found
+    ///   nonce, so the lowest valid nonce wins and mining is reproducible
+    /// - Meanwhile, the calling thread invokes the progress callback every
+    ///   PROGRESS_INTERVAL with the number of hashes computed so far, until all
+    ///   workers stopped
+    /// - Leaves the block unchanged if mining was cancelled or no nonce was found
+    /// - Otherwise sets the found nonce and hash on the block
+    /// - Returns a success message with the final hash
+    fn mine_with(
+        &mut self,
+        target: &[u8; 32],
+        cancel: &AtomicBool,
+        max_nonce: u64,
+        mut on_progress: Option<&mut dyn FnMut(u64, f64)>,
+    ) -> Result<String, MiningError> {
+        if !Self::meets_target(&self.hash, target) {
+            let workers = thread::available_parallelism()
+                .map(|workers| workers.get() as u64)
+                .unwrap_or(1);
+            let start_nonce = self
+                .nonce
+                .checked_add(1)
+                .ok_or(MiningError::TargetUnreachable)?;
+            let stop = AtomicBool::new(false);
+            let best_nonce = AtomicU64::new(u64::MAX);
+            let active_workers = AtomicU64::new(workers);
+            let hashes = AtomicU64::new(0);
+            let solution: Mutex<Option<(u64, String)>> = Mutex::new(None);
+            let hasher = self.hasher();
+            let prefix = Self::hash_prefix(
+                self.index,
+                &self.timestamp,
+                &self.merkle_root,
+                &self.previous_hash,
+            );
+
+            thread::scope(|scope| {
+                for worker in 0..workers {
+                    let stop = &stop;
+                    let best_nonce = &best_nonce;
+                    let active_workers = &active_workers;
+                    let hashes = &hashes;
+                    let solution = &solution;
+                    let mut buffer = prefix.clone();
+
+                    scope.spawn(move || {
+                        let prefix_len = buffer.len();
+                        let next_nonce = |nonce: u64, step: u64| {
+                            nonce.checked_add(step).filter(|&next| next <= max_nonce)
+                        };
+                        let mut next = next_nonce(start_nonce, worker);
+                        let mut attempts: u64 = 0;
+
+                        while let Some(nonce) = next {
+                            if nonce >= best_nonce.load(Ordering::Relaxed)
+                                || stop.load(Ordering::Relaxed)
+                            {
+                                break;
+                            }
+
+                            if attempts.is_multiple_of(CANCEL_CHECK_INTERVAL)
+                                && cancel.load(Ordering::Relaxed)
+                            {
+                                stop.store(true, Ordering::SeqCst);
+                                break;
+                            }
+
+                            let hash =
+                                Self::hash_with_nonce(&hasher, &mut buffer, prefix_len, nonce);
+                            hashes.fetch_add(1, Ordering::Relaxed);
+
+                            if Self::meets_target(&hash, target) {
+                                let mut solution =
+                                    solution.lock().expect("Mining solution lock poisoned");
+                                if solution.as_ref().is_none_or(|(best, _)| nonce < *best) {
+                                    *solution = Some((nonce, hash));
+                                }
+                                best_nonce.fetch_min(nonce, Ordering::SeqCst);
+                                break;
+                            }
+
+                            next = next_nonce(nonce, workers);
+                            attempts += 1;
+                        }
+
+                        active_workers.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+
+                if let Some(on_progress) = on_progress.as_mut() {
+                    let started = Instant::now();
+                    let mut last_report = started;
+
+                    while active_workers.load(Ordering::Relaxed) > 0 {
+                        thread::sleep(PROGRESS_POLL_INTERVAL);
+
+                        if last_report.elapsed() >= PROGRESS_INTERVAL {
+                            let hashes = hashes.load(Ordering::Relaxed);
+                            let hash_rate = hashes as f64 / started.elapsed().as_secs_f64();
+                            on_progress(start_nonce.saturating_add(hashes), hash_rate);
+                            last_report = Instant::now();
+                        }
+                    }
+                }
+            });
+
+            if stop.load(Ordering::SeqCst) {
+                return Err(MiningError::Cancelled);
+            }
+
+            let (nonce, hash) = solution
+                .into_inner()
+                .expect("Mining solution lock poisoned")
+                .ok_or(MiningError::TargetUnreachable)?;
+            self.nonce = nonce;
+            self.hash = hash;
+        }
+
+        Ok(format!("Block successfully mined: {}", self.hash))
+    }
+
+    /// Validates all transactions in the block.
+    ///
+    /// # Returns  
+    ///
+    /// `Result<bool, BlockchainError>`
+    ///
+    /// - `Ok(true)` if all transactions are valid
+    /// - `Ok(false)` if any transaction is invalid
+    /// - `Err(BlockchainError)` if a transaction could not be validated, e.g. it is
+    ///   unsigned or its stored hash does not match its contents
+    ///
+    /// # Functionality
+    ///  
+    /// - Iterates through each transaction
+    /// - Calls transaction.is_valid_with() to validate, with the shared context of
+    ///   secp256k1_context()
+    /// - If any transaction is invalid, returns Ok(false)
+    /// - If a transaction could not be validated, logs the error as a warning and
+    ///   returns it, so the block is never accepted
+    /// - If all are valid, returns Ok(true)
+    pub fn has_valid_transactions(&self) -> Result<bool, BlockchainError> {
+        let secp = secp256k1_context();
+
+        for transaction in &self.transactions {
+            match transaction.is_valid_with(secp) {
+                Ok(is_valid) => {
+                    if !is_valid {
+                        return Ok(false);
+                    }
+                }
+                Err(err_msg) => {
+                    warn!("Error validating transaction: {}", err_msg);
+                    return Err(err_msg);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks if the block includes a transaction past its deadline.
+    ///
+    /// # Returns
+    ///
+    /// bool - True if a transaction is_expired_at() the block index, False otherwise
+    pub fn has_expired_transactions(&self) -> bool {
+        self.transactions
+            .iter()
+            .any(|transaction| transaction.is_expired_at(self.index))
+    }
+
+    /// Sorts transactions into the canonical order of a block.
+    ///
+    /// The canonical order puts the transfers by descending fee, then by ascending
+    /// hash between equal fees, followed by the reward transactions, so the order of
+    /// the transactions of a block, and its hash, leave no choice to the miner.
+    ///
+    /// # Parameters
+    ///
+    /// * `transactions` - The transactions to sort
+    pub fn sort_canonically(transactions: &mut [Transaction]) {
+        transactions.sort_by_cached_key(Self::canonical_key);
+    }
+
+    /// Checks if the transactions of the block are in canonical order.
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the transactions are sorted as sort_canonically() sorts them,
+    /// False otherwise
+    pub fn has_canonical_order(&self) -> bool {
+        let keys: Vec<_> = self.transactions.iter().map(Self::canonical_key).collect();
+        keys.is_sorted()
+    }
+
+    /// Gets the sort key of a transaction in the canonical order.
+    ///
+    /// # Parameters
+    ///
+    /// * `transaction` - The transaction to sort
+    ///
+    /// # Returns
+    ///
+    /// Whether the transaction is a reward transaction, its reversed fee and its hash
+    fn canonical_key(transaction: &Transaction) -> (bool, std::cmp::Reverse<u64>, String) {
+        (
+            transaction.from_address.is_none(),
+            std::cmp::Reverse(transaction.fee),
+            transaction.compute_hash(),
+        )
+    }
+
+    /// Verifies the signatures of all the transactions in the block together.
+    ///
+    /// Unlike has_valid_transactions(), the failure tells which transaction is not
+    /// valid. This is the check verify() and Blockchain validation run.
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), (usize, BlockchainError)>`
+    ///
+    /// - `Ok(())` if every transaction is validly signed
+    /// - `Err((position, BlockchainError::Verification))` with the position in the block
+    ///   of the first invalid transaction and why it failed
+    ///
+    /// # Functionality
+    ///
+    /// - Gets the shared context of secp256k1_context() once for all the transactions
+    /// - Calls transaction.is_valid_with() for each transaction
+    /// - Stops at the first transaction that is not valid
+    pub fn verify_signatures(&self) -> Result<(), (usize, BlockchainError)> {
+        let secp = secp256k1_context();
+
+        for (position, transaction) in self.transactions.iter().enumerate() {
+            match transaction.is_valid_with(secp) {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err((
+                        position,
+                        BlockchainError::Verification(
+                            "The signature was not made by the key of from_address".to_string(),
+                        ),
+                    ))
+                }
+                Err(err) => return Err((position, err)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the block extends a known parent block, without the rest of its chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `parent` - The block this block claims to follow
+    /// * `difficulty` - The difficulty the block hash must meet
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the block is a valid child of the parent
+    /// - `Err(ValidationError)` with the index of the block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Checks the index follows the parent index
+    /// - Checks the previous hash is the parent hash
+    /// - Checks the Merkle root matches recalculation with the block hash algorithm
+    /// - Checks the hash matches recalculation with hasher()
+    /// - Checks the hash meets the target of the difficulty
+    /// - Checks the transaction signatures with verify_signatures() and the transactions
+    ///   are not expired
+    pub fn verify(&self, parent: &Block, difficulty: u32) -> Result<(), ValidationError> {
+        let index = self.index;
+
+        if self.index != parent.index + 1 {
+            return Err(ValidationError::IndexMismatch { index });
+        }
+
+        if self.previous_hash != parent.hash {
+            return Err(ValidationError::PreviousHashMismatch { index });
+        }
+
+        if self.merkle_root != Self::compute_merkle_root(&self.hash_algorithm, &self.transactions) {
+            return Err(ValidationError::MerkleRootMismatch { index });
+        }
+
+        let calculated_hash = Self::calculate_hash(
+            &self.hasher(),
+            self.index,
+            &self.timestamp,
+            &self.merkle_root,
+            &self.previous_hash,
+            self.nonce,
+        );
+        if self.hash != calculated_hash {
+            return Err(ValidationError::HashMismatch { index });
+        }
+
+        if !Self::meets_target(&self.hash, &Self::difficulty_to_target(difficulty)) {
+            return Err(ValidationError::InsufficientWork { index });
+        }
+
+        if let Err((position, err)) = self.verify_signatures() {
+            warn!(
+                "Block {} transaction {} is invalid: {}",
+                index, position, err
+            );
+            return Err(ValidationError::InvalidTransactions { index });
+        }
+
+        if self.has_expired_transactions() {
+            return Err(ValidationError::ExpiredTransaction { index });
+        }
+
+        Ok(())
+    }
+}
+
+/// BlockHeader struct.
+///
+/// Represents a block without its transactions, enough to check the proof of
+/// work and the hash chain of a chain without downloading its blocks.
+///
+/// # Fields
+///
+/// * `index` - The height of the block in the chain, 0 for the genesis block.
+/// * `timestamp` - The timestamp when the block was created, serialized as RFC3339.
+/// * `previous_hash` - The hash of the previous block in the chain.
+/// * `merkle_root` - The Merkle root of the block transactions' hashes.
+/// * `nonce` - The nonce used to mine the block.
+/// * `hash` - The hash of the block.
+/// * `hash_algorithm` - The hash function the block was hashed with.
+/// * `pow_function` - The function the block hash was mined with.
+/// * `difficulty` - The difficulty the block was mined at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub index: u64,
+    pub timestamp: DateTime<Utc>,
+    pub previous_hash: String,
+    pub merkle_root: String,
+    pub nonce: u64,
+    pub hash: String,
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    #[serde(default)]
+    pub pow_function: PowFunction,
+    #[serde(default)]
+    pub difficulty: u32,
+}
+
+impl BlockHeader {
+    /// Recalculates the hash of the block from its header.
+    ///
+    /// # Returns
+    ///
+    /// The hash of the header fields as a hex encoded string, equal to the hash
+    /// of an untampered block
+    ///
+    /// # Functionality
+    ///
+    /// - Calls Block::calculate_hash() with the hash algorithm and proof of work
+    ///   function of the header
+    pub fn calculate_hash(&self) -> String {
+        let hasher = BlockHasher {
+            hash_algorithm: self.hash_algorithm,
+            pow_function: self.pow_function,
+        };
+
+        Block::calculate_hash(
+            &hasher,
+            self.index,
+            &self.timestamp,
+            &self.merkle_root,
+            &self.previous_hash,
+            self.nonce,
+        )
+    }
+
+    /// Verifies the header extends a parent header.
+    ///
+    /// # Parameters
+    ///
+    /// * `parent` - The header of the block this block claims to follow
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the header is a valid child of the parent
+    /// - `Err(ValidationError)` with the index of the block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Checks the index follows the parent index
+    /// - Checks the previous hash is the parent hash
+    /// - Checks the hash matches calculate_hash()
+    /// - Checks the hash meets the target of the header difficulty
+    /// - The transactions are not checked, only their Merkle root is known
+    pub fn verify(&self, parent: &BlockHeader) -> Result<(), ValidationError> {
+        let index = self.index;
+
+        if self.index != parent.index + 1 {
+            return Err(ValidationError::IndexMismatch { index });
+        }
+
+        if self.previous_hash != parent.hash {
+            return Err(ValidationError::PreviousHashMismatch { index });
+        }
+
+        if self.hash != self.calculate_hash() {
+            return Err(ValidationError::HashMismatch { index });
+        }
+
+        if !Block::meets_target(&self.hash, &Block::difficulty_to_target(self.difficulty)) {
+            return Err(ValidationError::InsufficientWork { index });
+        }
+
+        Ok(())
+    }
+
+    /// Borrows the header as a light::RawHeader.
+    ///
+    /// # Returns
+    ///
+    /// A RawHeader with the fields of the header, so it can be verified with the
+    /// no_std functions of the light module
+    pub fn as_raw(&self) -> RawHeader<'_> {
+        RawHeader {
+            index: self.index,
+            timestamp: self.timestamp.timestamp(),
+            timestamp_nanos: self.timestamp.timestamp_subsec_nanos(),
+            merkle_root: &self.merkle_root,
+            previous_hash: &self.previous_hash,
+            nonce: self.nonce,
+            hash: &self.hash,
+            difficulty: self.difficulty,
+            hash_algorithm: self.hash_algorithm,
+            pow_function: self.pow_function,
+        }
+    }
+}
+
+/// Verifies a Merkle proof that a transaction is included in a block.
+///
+/// # Parameters
+///
+/// * `hasher` - The hash function of the block
+/// * `tx_hash` - The hash of the transaction
+/// * `proof` - The sibling hashes and sides, as returned by Block::merkle_proof()
+/// * `root` - The Merkle root of the block
+///
+/// # Returns
+///
+/// bool - True if the proof leads from the transaction hash to the root, False otherwise
+///
+/// # Functionality
+///
+/// - Starts from the transaction hash
+/// - Hashes the current node with each sibling, on the side given by the proof
+/// - Compares the resulting hash with the root
+pub fn verify_merkle_proof(
+    hasher: &dyn Hasher,
+    tx_hash: &str,
+    proof: &[(String, bool)],
+    root: &str,
+) -> bool {
+    let computed_root = proof
+        .iter()
+        .fold(tx_hash.to_string(), |node, (sibling, is_left)| {
+            if *is_left {
+                Block::hash_pair(hasher, sibling, &node)
+            } else {
+                Block::hash_pair(hasher, &node, sibling)
+            }
+        });
+
+    computed_root == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::test_utils::address;
+
+    #[test]
+    fn genesis_and_reward_blocks_round_trip_unchanged() {
+        let genesis_block = Blockchain::new(1, 100).get_latest_block().clone();
+        let mut reward_block = Block::new(
+            1,
+            vec![Transaction::coinbase(address(1), 100)],
+            genesis_block.hash.clone(),
+        );
+        reward_block.mine_block(1);
+
+        for block in [genesis_block, reward_block] {
+            let json = serde_json::to_value(&block).unwrap();
+            let timestamp = json["timestamp"].as_str().unwrap();
+            assert_eq!(
+                DateTime::parse_from_rfc3339(timestamp).unwrap(),
+                block.timestamp
+            );
+
+            let restored: Block = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+            assert_eq!(restored.header().calculate_hash(), block.hash);
+        }
+    }
+
+    #[test]
+    fn reward_transaction_keeps_its_missing_sender() {
+        let reward = Transaction::coinbase(address(1), 100);
+
+        let json = serde_json::to_value(&reward).unwrap();
+        assert!(json["from_address"].is_null());
+
+        let restored: Transaction = serde_json::from_value(json).unwrap();
+        assert!(restored.from_address.is_none());
+        assert!(restored.signature.is_none());
+        assert_eq!(restored.compute_hash(), reward.compute_hash());
+    }
+
+    #[test]
+    fn changed_amount_changes_the_merkle_root_and_the_hash() {
+        let transactions = vec![
+            Transaction::coinbase(address(1), 100),
+            Transaction::coinbase(address(2), 50),
+        ];
+        let block = Block::new(1, transactions, "0".repeat(64));
+
+        let mut tampered = block.clone();
+        tampered.transactions[1].outputs[0].amount = 51;
+        tampered.merkle_root =
+            Block::compute_merkle_root(&tampered.hash_algorithm, &tampered.transactions);
+
+        assert_ne!(tampered.merkle_root, block.merkle_root);
+        assert_ne!(tampered.header().calculate_hash(), block.hash);
+    }
+
+    #[test]
+    fn merkle_root_of_no_transactions_is_zero() {
+        assert_eq!(
+            Block::compute_merkle_root(&HashAlgorithm::default(), &[]),
+            "0".repeat(64)
+        );
+    }
+
+    #[test]
+    fn odd_merkle_level_duplicates_its_last_hash() {
+        let transactions: Vec<Transaction> = (1..=3)
+            .map(|seed| Transaction::coinbase(address(seed), 10))
+            .collect();
+        let mut padded = transactions.clone();
+        padded.push(transactions[2].clone());
+
+        let hash_algorithm = HashAlgorithm::default();
+        assert_eq!(
+            Block::compute_merkle_root(&hash_algorithm, &transactions),
+            Block::compute_merkle_root(&hash_algorithm, &padded)
+        );
+        assert_ne!(
+            Block::compute_merkle_root(&hash_algorithm, &transactions),
+            Block::compute_merkle_root(&hash_algorithm, &transactions[..2])
+        );
+    }
+
+    #[test]
+    fn each_unit_of_difficulty_halves_the_target() {
+        assert_eq!(Block::difficulty_to_target(0), [0xff; 32]);
+        assert_eq!(Block::difficulty_to_target(1)[0], 0x7f);
+        assert_eq!(Block::difficulty_to_target(8)[..2], [0x00, 0xff]);
+        assert_eq!(Block::difficulty_to_target(9)[..2], [0x00, 0x7f]);
+        assert_eq!(Block::difficulty_to_target(300), [0; 32]);
+
+        let easy = Block::expected_hashes(&Block::difficulty_to_target(10));
+        let hard = Block::expected_hashes(&Block::difficulty_to_target(11));
+        assert!((hard / easy - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hash_is_compared_to_the_target_as_a_number() {
+        let target = Block::difficulty_to_target(4);
+        assert!(Block::meets_target(
+            &format!("0f{}", "ff".repeat(31)),
+            &target
+        ));
+        assert!(!Block::meets_target(
+            &format!("10{}", "00".repeat(31)),
+            &target
+        ));
+        assert!(!Block::meets_target("0f", &target));
+        assert!(!Block::meets_target(&"zz".repeat(32), &target));
+    }
+
+    #[test]
+    fn higher_difficulty_finds_a_smaller_hash_with_more_nonces() {
+        let mine_all = |difficulty: u32| {
+            (0..8)
+                .map(|index| {
+                    let mut block = Block::new(index, Vec::new(), "0".repeat(64));
+                    block.mine_block(difficulty);
+                    assert!(Block::meets_target(
+                        &block.hash,
+                        &Block::difficulty_to_target(difficulty)
+                    ));
+                    block
+                })
+                .collect::<Vec<Block>>()
+        };
+        let easy = mine_all(2);
+        let hard = mine_all(12);
+
+        let highest_hash = |blocks: &[Block]| {
+            blocks
+                .iter()
+                .map(|block| hex::decode(&block.hash).unwrap())
+                .max()
+                .unwrap()
+        };
+        assert!(highest_hash(&hard) < highest_hash(&easy));
+        let nonces = |blocks: &[Block]| blocks.iter().map(|block| block.nonce).sum::<u64>();
+        assert!(nonces(&hard) > 16 * nonces(&easy));
+    }
+
+    #[test]
+    fn mining_stops_promptly_once_cancelled() {
+        let mut block = Block::new(1, Vec::new(), "0".repeat(64));
+        let hash = block.hash.clone();
+        let cancel = AtomicBool::new(false);
+
+        let started = Instant::now();
+        let mined = thread::scope(|scope| {
+            let mining = scope.spawn(|| block.mine_block_cancellable(MAX_DIFFICULTY, &cancel));
+            thread::sleep(Duration::from_millis(100));
+            cancel.store(true, Ordering::SeqCst);
+            mining.join().unwrap()
+        });
+
+        assert_eq!(mined, Err(MiningError::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(block.hash, hash);
+        assert_eq!(block.nonce, 0);
+    }
+
+    #[test]
+    fn mining_with_the_cancel_flag_already_set_is_cancelled() {
+        let mut block = Block::new(1, Vec::new(), "0".repeat(64));
+
+        assert_eq!(
+            block.mine_block_cancellable(MAX_DIFFICULTY, &AtomicBool::new(true)),
+            Err(MiningError::Cancelled)
+        );
+    }
+
+    #[test]
+    fn progress_is_reported_while_mining() {
+        let mut block = Block::new(1, Vec::new(), "0".repeat(64));
+        let cancel = AtomicBool::new(false);
+        let mut reports = Vec::new();
+
+        // Stops mining from the first report, as the target is out of reach
+        let mined = block.mine_with(
+            &Block::difficulty_to_target(MAX_DIFFICULTY),
+            &cancel,
+            u64::MAX,
+            Some(&mut |nonce, hash_rate| {
+                reports.push((nonce, hash_rate));
+                cancel.store(true, Ordering::SeqCst);
+            }),
+        );
+
+        assert_eq!(mined, Err(MiningError::Cancelled));
+        assert!(!reports.is_empty());
+        assert!(reports
+            .iter()
+            .all(|&(nonce, hash_rate)| nonce > 0 && hash_rate > 0.0));
+    }
+
+    #[test]
+    fn mining_with_progress_returns_the_success_message() {
+        let mut block = Block::new(1, Vec::new(), "0".repeat(64));
+
+        let mined = block.mine_block_with_progress(8, |_, _| {});
+
+        assert_eq!(mined, format!("Block successfully mined: {}", block.hash));
+        assert!(Block::meets_target(
+            &block.hash,
+            &Block::difficulty_to_target(8)
+        ));
+        assert_eq!(block.difficulty, 8);
+    }
+
+    #[test]
+    fn reused_prefix_buffer_hashes_like_a_fresh_one() {
+        let hasher = HashAlgorithm::default();
+        let timestamp = Utc::now();
+        let (merkle_root, previous_hash) = ("a".repeat(64), "b".repeat(64));
+        let mut buffer = Block::hash_prefix(3, &timestamp, &merkle_root, &previous_hash);
+        let prefix_len = buffer.len();
+
+        for nonce in [u64::MAX, 7, 123_456] {
+            assert_eq!(
+                Block::hash_with_nonce(&hasher, &mut buffer, prefix_len, nonce),
+                Block::calculate_hash(&hasher, 3, &timestamp, &merkle_root, &previous_hash, nonce)
+            );
+        }
+    }
+
+    #[test]
+    fn block_with_500_transactions_mines_quickly() {
+        let transactions: Vec<Transaction> = (0..500)
+            .map(|amount| Transaction::coinbase(address(1), amount))
+            .collect();
+        let mut block = Block::new(1, transactions, "0".repeat(64));
+
+        let started = Instant::now();
+        block.mine_block(12);
+
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert_eq!(block.header().calculate_hash(), block.hash);
+    }
+
+    #[test]
+    fn merkle_proof_of_each_transaction_verifies_against_the_root() {
+        let transactions: Vec<Transaction> = (1..=5)
+            .map(|seed| Transaction::coinbase(address(seed), 10))
+            .collect();
+        let block = Block::new(1, transactions, "0".repeat(64));
+
+        for transaction in &block.transactions {
+            let tx_hash = transaction.compute_hash_with(&block.hash_algorithm);
+            let proof = block.merkle_proof(&tx_hash).unwrap();
+            assert_eq!(proof.len(), 3);
+            assert!(verify_merkle_proof(
+                &block.hash_algorithm,
+                &tx_hash,
+                &proof,
+                &block.merkle_root
+            ));
+
+            let mut altered = proof.clone();
+            altered[1].0 = "0".repeat(64);
+            assert!(!verify_merkle_proof(
+                &block.hash_algorithm,
+                &tx_hash,
+                &altered,
+                &block.merkle_root
+            ));
+
+            let mut swapped = proof;
+            swapped[2].1 = !swapped[2].1;
+            assert!(!verify_merkle_proof(
+                &block.hash_algorithm,
+                &tx_hash,
+                &swapped,
+                &block.merkle_root
+            ));
+        }
+
+        assert!(block.merkle_proof(&"ab".repeat(32)).is_none());
+    }
+
+    #[test]
+    fn block_mined_at_a_fixed_timestamp_has_a_pinned_hash() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mine = || {
+            let mut block = Block::new_at(
+                1,
+                vec![Transaction::coinbase(address(1), 100)],
+                "0".repeat(64),
+                timestamp,
+            );
+            block.mine_block(8);
+            block
+        };
+
+        let block = mine();
+        assert_eq!(block.timestamp, timestamp);
+        assert_eq!(block.nonce, 135);
+        assert_eq!(
+            block.hash,
+            "00c3c75625185a082c7d9d850486b514a58e56c38b729928b6987ab7d21d4d7a"
+        );
+        assert_eq!(mine().hash, block.hash);
+    }
+
+    #[test]
+    fn block_verifies_only_against_the_parent_it_links_to() {
+        let mut parent = Block::new(0, Vec::new(), "0".repeat(64));
+        parent.mine_block(4);
+        let mut child = Block::new(
+            1,
+            vec![Transaction::coinbase(address(1), 100)],
+            parent.hash.clone(),
+        );
+        child.mine_block(4);
+        assert_eq!(child.verify(&parent, 4), Ok(()));
+
+        let mut unlinked = child.clone();
+        unlinked.previous_hash = "0".repeat(64);
+        unlinked.hash = "f".repeat(64);
+        unlinked.mine_block(4);
+        assert_eq!(
+            unlinked.verify(&parent, 4),
+            Err(ValidationError::PreviousHashMismatch { index: 1 })
+        );
+
+        let mut tampered = child;
+        tampered.nonce += 1;
+        assert_eq!(
+            tampered.verify(&parent, 4),
+            Err(ValidationError::HashMismatch { index: 1 })
+        );
+    }
+
+    #[test]
+    fn unreachable_difficulty_gives_up_at_the_nonce_cap() {
+        let mut block = Block::new(
+            1,
+            vec![Transaction::coinbase(address(1), 100)],
+            "0".repeat(64),
+        );
+        let (nonce, hash) = (block.nonce, block.hash.clone());
+
+        let started = Instant::now();
+        assert_eq!(
+            block.mine_block_with_max_nonce(MAX_DIFFICULTY, 10_000),
+            Err(MiningError::TargetUnreachable)
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!((block.nonce, &block.hash), (nonce, &hash));
+
+        block.nonce = u64::MAX - 3;
+        assert_eq!(
+            block.mine_block_with_max_nonce(MAX_DIFFICULTY, u64::MAX),
+            Err(MiningError::TargetUnreachable)
+        );
+        block.nonce = u64::MAX;
+        assert_eq!(
+            block.mine_block_with_max_nonce(MAX_DIFFICULTY, u64::MAX),
+            Err(MiningError::TargetUnreachable)
+        );
+    }
+
+    #[test]
+    fn parallel_mining_finds_the_lowest_valid_nonce() {
+        let block = Block::new(
+            1,
+            vec![Transaction::coinbase(address(1), 100)],
+            "0".repeat(64),
+        );
+        let target = Block::difficulty_to_target(8);
+
+        let mut first = block.clone();
+        first.mine_block(8);
+        let mut second = block.clone();
+        second.mine_block(8);
+
+        assert!(Block::meets_target(&first.hash, &target));
+        assert_eq!(first.header().calculate_hash(), first.hash);
+        assert_eq!((first.nonce, &first.hash), (second.nonce, &second.hash));
+
+        let mut earlier = block;
+        for nonce in 1..first.nonce {
+            earlier.nonce = nonce;
+            assert!(!Block::meets_target(
+                &earlier.header().calculate_hash(),
+                &target
+            ));
         }
-        Ok(true)
     }
 }
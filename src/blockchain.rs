@@ -1,5 +1,50 @@
+use crate::address::*;
 use crate::block::*;
+use crate::consensus::*;
+use crate::error::*;
+use crate::hasher::*;
+use crate::storage::*;
 use crate::transaction::*;
+use crate::units::*;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// The default target time between two mined blocks.
+pub const DEFAULT_TARGET_BLOCK_TIME: Duration = Duration::from_secs(10);
+
+/// The default lowest difficulty the chain can adjust to.
+pub const DEFAULT_MIN_DIFFICULTY: u32 = 1;
+
+/// The default tolerance for block timestamps ahead of the validating node's clock.
+pub const DEFAULT_MAX_FUTURE_BLOCK_TIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// The default maximum number of pending transactions included in a block.
+pub const DEFAULT_MAX_TRANSACTIONS_PER_BLOCK: usize = 100;
+
+/// The default maximum size of the transactions of a block, reward included, in bytes.
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 1_000_000;
+
+/// The default number of blocks between two mining reward halvings.
+pub const DEFAULT_HALVING_INTERVAL: u64 = 210_000;
+
+/// The default highest difficulty the chain can adjust to.
+pub const DEFAULT_MAX_DIFFICULTY: u32 = 24;
+
+/// The Unix timestamp of the default genesis block, 2024-01-01T00:00:00Z.
+///
+/// A fixed timestamp gives every node creating a chain with new() the same
+/// genesis block, so their chains can be synchronized.
+pub const GENESIS_TIMESTAMP: i64 = 1_704_067_200;
+
+/// The previous hash of the default genesis block.
+pub const GENESIS_PREVIOUS_HASH: &str = "0";
 
 /// Blockchain struct.
 ///
@@ -7,25 +52,457 @@ use crate::transaction::*;
 ///
 /// # Fields
 ///
-/// * `chain` - The chain of mined blocks
-/// * `difficulty` - The mining difficulty 
+/// * `storage` - The storage of the mined blocks, serialized as the `chain` list of blocks
+/// * `difficulty` - The mining difficulty, as the number of leading zero bits of block hashes
+/// * `initial_difficulty` - The difficulty the chain started with, which validation
+///   replays the difficulty adjustments from, 0 for chains saved before it was recorded
 /// * `pending_transactions` - Unmined transactions  
-/// * `mining_reward` - The mining reward amount
-#[derive(Debug)]
+/// * `mining_reward` - The initial mining reward amount, in smallest units
+/// * `halving_interval` - The number of blocks after which the mining reward halves,
+///   0 to never halve it
+/// * `target_block_time` - The desired time between two mined blocks
+/// * `min_difficulty` - The lowest difficulty the chain can adjust to
+/// * `max_difficulty` - The highest difficulty the chain can adjust to
+/// * `max_future_block_time` - How far ahead of the current time a block timestamp can be
+/// * `max_transactions_per_block` - The maximum number of pending transactions mined into a
+///   block, not counting the reward transaction
+/// * `max_block_bytes` - The maximum total serialized size of the transactions of a block,
+///   reward transaction included
+/// * `max_supply` - The maximum number of coins ever issued by reward transactions, in
+///   smallest units, None for no cap
+/// * `require_transactions` - Whether mining is skipped when no pending transaction can
+///   be mined, instead of mining a block holding only the reward transaction
+/// * `canonical_transaction_order` - Whether the transactions of mined blocks are sorted
+///   with Block::sort_canonically(), and blocks out of that order are invalid
+/// * `hash_algorithm` - The hash function used to hash and validate blocks
+/// * `pow_function` - The function block hashes are mined and validated with, the hash
+///   algorithm by default
+/// * `pruned_checkpoint` - The state of the blocks whose transactions were discarded by
+///   prune(), None if the chain was never pruned
+/// * `min_relay_fee` - The lowest fee accepted by add_transaction(), in smallest units,
+///   0 by default
+/// * `consensus_mode` - The mode of the consensus the blocks are verified with, set
+///   by set_consensus() and saved with the chain
+/// * `consensus` - The rules sealing blocks, proof of work unless set with
+///   set_consensus(), not serialized
+/// * `subscribers` - The callbacks notified of each block added as the tip, not
+///   serialized
+/// * `transaction_subscribers` - The callbacks notified of each added transaction,
+///   not serialized
+/// * `balance_index` - The balance of every address involved in the chain, not
+///   serialized and rebuilt by rebuild_index()
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Blockchain {
-    pub chain: Vec<Block>,
-    pub difficulty: i64,
+    #[serde(rename = "chain")]
+    storage: ChainStorage,
+    pub difficulty: u32,
+    #[serde(default)]
+    initial_difficulty: u32,
     pub pending_transactions: Vec<Transaction>,
-    pub mining_reward: f64,
+    pub mining_reward: u64,
+    pub halving_interval: u64,
+    pub target_block_time: Duration,
+    pub min_difficulty: u32,
+    pub max_difficulty: u32,
+    pub max_future_block_time: Duration,
+    pub max_transactions_per_block: usize,
+    #[serde(default = "default_max_block_bytes")]
+    pub max_block_bytes: usize,
+    #[serde(default)]
+    pub max_supply: Option<u64>,
+    #[serde(default)]
+    pub require_transactions: bool,
+    #[serde(default)]
+    pub canonical_transaction_order: bool,
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    #[serde(default)]
+    pub pow_function: PowFunction,
+    #[serde(default)]
+    pub pruned_checkpoint: Option<Checkpoint>,
+    #[serde(default)]
+    min_relay_fee: u64,
+    #[serde(default)]
+    consensus_mode: ConsensusMode,
+    #[serde(skip)]
+    consensus: ConsensusStrategy,
+    #[serde(skip)]
+    subscribers: Subscribers<BlockCallback>,
+    #[serde(skip)]
+    transaction_subscribers: Subscribers<TransactionCallback>,
+    #[serde(skip)]
+    balance_index: HashMap<Address, u64>,
+}
+
+/// Gets the maximum block size of chains saved before it was configurable.
+///
+/// # Returns
+///
+/// DEFAULT_MAX_BLOCK_BYTES
+fn default_max_block_bytes() -> usize {
+    DEFAULT_MAX_BLOCK_BYTES
+}
+
+/// A callback notified of each mined block.
+pub type BlockCallback = Box<dyn Fn(&Block) + Send>;
+
+/// A callback notified of each transaction added to the pending transactions.
+pub type TransactionCallback = Box<dyn Fn(&Transaction) + Send>;
+
+/// ConsensusStrategy struct.
+///
+/// Holds the consensus of a blockchain, proof of work by default.
+struct ConsensusStrategy(Box<dyn Consensus + Send>);
+
+impl Default for ConsensusStrategy {
+    fn default() -> Self {
+        ConsensusStrategy(Box::new(ProofOfWork))
+    }
+}
+
+impl fmt::Debug for ConsensusStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ConsensusStrategy")
+    }
+}
+
+/// ChainStorage struct.
+///
+/// Holds the storage of the blocks of a blockchain, in memory by default.
+/// with_storage() reads every stored block, so the blocks of an opened chain
+/// are read from memory and only storing and dropping blocks can fail.
+struct ChainStorage(Box<dyn Storage + Send>);
+
+impl Default for ChainStorage {
+    fn default() -> Self {
+        ChainStorage(Box::new(MemoryStorage::new()))
+    }
+}
+
+impl ChainStorage {
+    /// Stores a block of a chain being built in memory.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block to store
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block could not be stored, which a MemoryStorage never fails
+    fn put_in_memory(&mut self, block: Block) {
+        self.0
+            .put_block(block)
+            .expect("Storing a block in memory cannot fail")
+    }
+}
+
+impl fmt::Debug for ChainStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ChainStorage({})", self.0.len())
+    }
+}
+
+impl Serialize for ChainStorage {
+    /// Serializes the stored blocks as a list, in height order, failing if a
+    /// block cannot be read.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let blocks = self
+            .0
+            .blocks_from(0)
+            .collect::<Result<Vec<&Block>, io::Error>>()
+            .map_err(serde::ser::Error::custom)?;
+        serializer.collect_seq(blocks)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChainStorage {
+    /// Deserializes a list of blocks into a MemoryStorage.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let blocks = Vec::<Block>::deserialize(deserializer)?;
+        Ok(ChainStorage(Box::new(MemoryStorage::from(blocks))))
+    }
+}
+
+/// Subscribers struct.
+///
+/// Holds the callbacks notified of a chain event, in registration order.
+struct Subscribers<C>(Vec<C>);
+
+impl<C> Default for Subscribers<C> {
+    fn default() -> Self {
+        Subscribers(Vec::new())
+    }
+}
+
+impl<C> fmt::Debug for Subscribers<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Subscribers({})", self.0.len())
+    }
+}
+
+/// ValidationState struct.
+///
+/// The state of a chain after its latest validated block, carried from block to
+/// block by validate_block() so each block is checked against its parent only.
+///
+/// # Fields
+///
+/// * `difficulty` - The difficulty expected of the latest block, the initial difficulty
+///   until a block after the genesis block is validated
+/// * `parent_timestamp` - The timestamp of the block before the latest block, None
+///   until a block after the genesis block is validated
+/// * `issued` - The coins issued up to the latest block, in smallest units
+/// * `balances` - The balance of every address involved up to the latest block
+#[derive(Debug, Clone, Default)]
+struct ValidationState {
+    difficulty: u32,
+    parent_timestamp: Option<DateTime<Utc>>,
+    issued: u64,
+    balances: HashMap<Address, u64>,
+}
+
+/// ValidationError enum.
+///
+/// Represents the reason a chain failed validation. Each variant carries the
+/// index of the offending block, except GenesisMismatch, always about block 0.
+///
+/// # Variants
+///
+/// * `IndexMismatch` - Returned when the block index does not follow the previous block.
+/// * `PreviousHashMismatch` - Returned when the block previous hash does not match the previous block hash.
+/// * `MerkleRootMismatch` - Returned when the block Merkle root does not match its transactions.
+/// * `HashMismatch` - Returned when the block hash does not match its recalculated hash.
+/// * `InvalidTransactions` - Returned when the block contains an invalid transaction.
+/// * `BadTimestamp` - Returned when the block timestamp is not after the previous block
+///   or is too far in the future.
+/// * `InvalidCoinbase` - Returned when the block has more than one reward transaction or
+///   its reward exceeds the mining reward plus fees.
+/// * `CheckpointMismatch` - Returned when the block at a checkpoint height does not have
+///   the checkpoint hash.
+/// * `InsufficientWork` - Returned when the block hash does not meet the difficulty.
+/// * `InvalidSeal` - Returned when the block is not sealed according to the consensus.
+/// * `PrunedBlock` - Returned when a block without its transactions is not covered by the
+///   pruning checkpoint of the chain.
+/// * `ExpiredTransaction` - Returned when the block includes a transaction past its
+///   valid_until height.
+/// * `TransactionOrder` - Returned when the chain requires canonical transaction order
+///   and the block transactions are not in it.
+/// * `DuplicateTransaction` - Returned when the block includes the same transaction twice.
+/// * `GenesisMismatch` - Returned when the genesis block is not the expected one, the
+///   chain belonging to a different network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    IndexMismatch { index: u64 },
+    PreviousHashMismatch { index: u64 },
+    MerkleRootMismatch { index: u64 },
+    HashMismatch { index: u64 },
+    InvalidTransactions { index: u64 },
+    BadTimestamp { index: u64 },
+    InvalidCoinbase { index: u64 },
+    CheckpointMismatch { index: u64 },
+    InsufficientWork { index: u64 },
+    InvalidSeal { index: u64 },
+    PrunedBlock { index: u64 },
+    ExpiredTransaction { index: u64 },
+    TransactionOrder { index: u64 },
+    DuplicateTransaction { index: u64 },
+    GenesisMismatch,
+}
+
+/// SimResult struct.
+///
+/// Represents the outcome of simulating a transaction with Blockchain::simulate().
+///
+/// # Fields
+///
+/// * `valid` - True if the transaction hash matches its contents and the signature
+///   is valid, so add_transaction() would accept it
+/// * `balance_after` - The sender projected balance once the transaction is mined,
+///   in smallest units
+/// * `fee` - The fee paid to the miner, in smallest units
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimResult {
+    pub valid: bool,
+    pub balance_after: u64,
+    pub fee: u64,
+}
+
+/// ChainStats struct.
+///
+/// Represents a summary of the chain state, as returned by Blockchain::stats().
+///
+/// # Fields
+///
+/// * `height` - The index of the latest block
+/// * `total_transactions` - The number of transactions in the blocks, reward
+///   transactions included and pruned transactions not included
+/// * `total_coins` - The coins created by reward transactions, premine included,
+///   in smallest units
+/// * `difficulty` - The current mining difficulty
+/// * `current_reward` - The mining reward of the next block, in smallest units
+/// * `mempool_size` - The number of pending transactions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainStats {
+    pub height: u64,
+    pub total_transactions: usize,
+    pub total_coins: u64,
+    pub difficulty: u32,
+    pub current_reward: u64,
+    pub mempool_size: usize,
+}
+
+impl fmt::Display for ChainStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Height: {}", self.height)?;
+        writeln!(f, "Transactions: {}", self.total_transactions)?;
+        writeln!(f, "Coins mined: {}", Amount::new(self.total_coins))?;
+        writeln!(f, "Difficulty: {}", self.difficulty)?;
+        writeln!(f, "Current reward: {}", Amount::new(self.current_reward))?;
+        write!(f, "Pending transactions: {}", self.mempool_size)
+    }
+}
+
+/// BlockTemplate struct.
+///
+/// Represents the contents of the next block, as returned by
+/// Blockchain::block_template(), for a miner outside the node to find its nonce.
+///
+/// # Fields
+///
+/// * `index` - The index of the next block
+/// * `timestamp` - The timestamp of the next block
+/// * `previous_hash` - The hash of the latest block
+/// * `transactions` - The selected pending transactions followed by the reward transaction
+/// * `merkle_root` - The Merkle root of the transactions
+/// * `difficulty` - The difficulty the block hash must meet
+/// * `hash_algorithm` - The hash algorithm of the chain
+/// * `pow_function` - The function the block hash is mined with
+/// * `header` - The bytes hashed before the nonce, the block hash being the hash of
+///   these bytes followed by the decimal nonce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTemplate {
+    pub index: u64,
+    pub timestamp: DateTime<Utc>,
+    pub previous_hash: String,
+    pub transactions: Vec<Transaction>,
+    pub merkle_root: String,
+    pub difficulty: u32,
+    pub hash_algorithm: HashAlgorithm,
+    pub pow_function: PowFunction,
+    pub header: Vec<u8>,
+}
+
+impl BlockTemplate {
+    /// Builds the block of the template with a nonce.
+    ///
+    /// # Parameters
+    ///
+    /// * `nonce` - The nonce found by the miner
+    ///
+    /// # Returns
+    ///
+    /// The block with the template contents, the nonce and its hash, ready for
+    /// Blockchain::submit_block()
+    pub fn to_block(&self, nonce: u64) -> Block {
+        let mut block = Block::new_with_algorithm_at(
+            self.index,
+            self.transactions.clone(),
+            self.previous_hash.clone(),
+            self.hash_algorithm,
+            self.timestamp,
+        );
+        block.difficulty = self.difficulty;
+        block.nonce = nonce;
+        block.set_pow_function(self.pow_function);
+
+        block
+    }
 }
 
+/// Checkpoint struct.
+///
+/// Represents a snapshot of the chain state at a height, so the blocks up to
+/// it do not have to be validated or walked again.
+///
+/// # Fields
+///
+/// * `height` - The index of the last block covered by the checkpoint
+/// * `block_hash` - The hash of the block at the checkpoint height
+/// * `balances` - The balance of every address involved in the covered blocks
+/// * `next_nonces` - The next nonce of every address that sent a transaction in the
+///   covered blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub height: u64,
+    pub block_hash: String,
+    pub balances: BTreeMap<Address, u64>,
+    #[serde(default)]
+    pub next_nonces: BTreeMap<Address, u64>,
+}
+
+impl ValidationError {
+    /// Gets the index of the offending block.
+    ///
+    /// # Returns
+    ///
+    /// The index of the block that failed validation as a u64
+    pub fn index(&self) -> u64 {
+        match self {
+            ValidationError::IndexMismatch { index }
+            | ValidationError::PreviousHashMismatch { index }
+            | ValidationError::MerkleRootMismatch { index }
+            | ValidationError::HashMismatch { index }
+            | ValidationError::InvalidTransactions { index }
+            | ValidationError::BadTimestamp { index }
+            | ValidationError::InvalidCoinbase { index }
+            | ValidationError::CheckpointMismatch { index }
+            | ValidationError::InsufficientWork { index }
+            | ValidationError::InvalidSeal { index }
+            | ValidationError::PrunedBlock { index }
+            | ValidationError::ExpiredTransaction { index }
+            | ValidationError::TransactionOrder { index }
+            | ValidationError::DuplicateTransaction { index } => *index,
+            ValidationError::GenesisMismatch => 0,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            ValidationError::IndexMismatch { .. } => "index does not follow the previous block",
+            ValidationError::PreviousHashMismatch { .. } => {
+                "previous hash does not match the previous block hash"
+            }
+            ValidationError::MerkleRootMismatch { .. } => {
+                "Merkle root does not match the transactions"
+            }
+            ValidationError::HashMismatch { .. } => "hash does not match the recalculated hash",
+            ValidationError::InvalidTransactions { .. } => "contains an invalid transaction",
+            ValidationError::BadTimestamp { .. } => "timestamp is out of range",
+            ValidationError::InvalidCoinbase { .. } => "reward transaction is invalid",
+            ValidationError::CheckpointMismatch { .. } => "hash does not match the checkpoint",
+            ValidationError::InsufficientWork { .. } => "hash does not meet the difficulty",
+            ValidationError::InvalidSeal { .. } => "is not sealed according to the consensus",
+            ValidationError::PrunedBlock { .. } => "is pruned but not covered by the checkpoint",
+            ValidationError::ExpiredTransaction { .. } => "contains an expired transaction",
+            ValidationError::TransactionOrder { .. } => "transactions are not in canonical order",
+            ValidationError::DuplicateTransaction { .. } => "contains a transaction twice",
+            ValidationError::GenesisMismatch => "is not the expected genesis block",
+        };
+        write!(f, "block {} {}", self.index(), reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
 impl Blockchain {
     /// Creates a new Blockchain instance.
     ///
     /// # Parameters
     ///
-    /// * `difficulty` - The mining difficulty
-    /// * `mining_reward` - The mining reward amount  
+    /// * `difficulty` - The mining difficulty, clamped between MIN_DIFFICULTY and MAX_DIFFICULTY
+    /// * `mining_reward` - The mining reward amount, in smallest units  
     ///
     /// # Returns
     ///
@@ -33,173 +510,5995 @@ impl Blockchain {
     ///
     /// # Functionality
     /// 
-    /// - Creates a genesis block with no transactions and hash "0"
+    /// - Calls new_with_algorithm() with SHA3-256 as hash algorithm
+    pub fn new(difficulty: u32, mining_reward: u64) -> Self {
+        Self::new_with_algorithm(difficulty, mining_reward, HashAlgorithm::default())
+    }
+
+    /// Creates a new Blockchain instance hashing blocks with the provided hash algorithm.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `hash_algorithm` - The hash function used to hash and validate blocks
+    ///
+    /// # Returns
+    ///
+    /// A new Blockchain instance.
+    ///
+    /// # Functionality
+    ///
+    /// - Calls new_with_pow_function() with the default proof of work function
+    pub fn new_with_algorithm(
+        difficulty: u32,
+        mining_reward: u64,
+        hash_algorithm: HashAlgorithm,
+    ) -> Self {
+        Self::new_with_pow_function(
+            difficulty,
+            mining_reward,
+            hash_algorithm,
+            PowFunction::default(),
+        )
+    }
+
+    /// Creates a new Blockchain instance mining blocks with the provided proof of work function.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `hash_algorithm` - The hash function used to hash and validate blocks
+    /// * `pow_function` - The function block hashes are mined and validated with,
+    ///   e.g. the memory-hard PowFunction::Scrypt
+    ///
+    /// # Returns
+    ///
+    /// A new Blockchain instance.
+    ///
+    /// # Functionality
+    ///
+    /// - Creates a genesis block with no transactions, timestamped GENESIS_TIMESTAMP with
+    ///   previous hash GENESIS_PREVIOUS_HASH, hashed with the proof of work function
     /// - Initializes a chain with just the genesis block
-    /// - Sets the provided difficulty and mining reward  
+    /// - Sets the provided difficulty clamped between MIN_DIFFICULTY and MAX_DIFFICULTY,
+    ///   so a chain never starts at a difficulty of 0
+    /// - Sets the provided mining reward, hash algorithm and proof of work function
+    /// - Uses the default halving interval, target block time, difficulty bounds,
+    ///   timestamp tolerance and block size
     /// - Returns the initialized Blockchain
-    pub fn new(difficulty: i64, mining_reward: f64) -> Self {
-        let genesis_block = Block::new(vec![], "0".to_string());
-        let chain = vec![genesis_block];
+    pub fn new_with_pow_function(
+        difficulty: u32,
+        mining_reward: u64,
+        hash_algorithm: HashAlgorithm,
+        pow_function: PowFunction,
+    ) -> Self {
+        let mut genesis_block = Block::new_with_algorithm_at(
+            0,
+            vec![],
+            GENESIS_PREVIOUS_HASH.to_string(),
+            hash_algorithm,
+            Self::genesis_time(),
+        );
+        genesis_block.set_pow_function(pow_function);
+        let mut storage = ChainStorage::default();
+        storage.put_in_memory(genesis_block);
+        let difficulty = difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
         Self {
-            chain,
+            storage,
             difficulty,
+            initial_difficulty: difficulty,
             pending_transactions: vec![],
             mining_reward,
+            halving_interval: DEFAULT_HALVING_INTERVAL,
+            target_block_time: DEFAULT_TARGET_BLOCK_TIME,
+            min_difficulty: DEFAULT_MIN_DIFFICULTY,
+            max_difficulty: DEFAULT_MAX_DIFFICULTY,
+            max_future_block_time: DEFAULT_MAX_FUTURE_BLOCK_TIME,
+            max_transactions_per_block: DEFAULT_MAX_TRANSACTIONS_PER_BLOCK,
+            max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+            max_supply: None,
+            require_transactions: false,
+            canonical_transaction_order: false,
+            pruned_checkpoint: None,
+            min_relay_fee: 0,
+            consensus_mode: ConsensusMode::default(),
+            consensus: ConsensusStrategy::default(),
+            subscribers: Subscribers::default(),
+            transaction_subscribers: Subscribers::default(),
+            balance_index: HashMap::new(),
+            hash_algorithm,
+            pow_function,
         }
     }
 
-    /// Gets the latest block in the chain.
+    /// Creates a new Blockchain instance whose genesis block premines coins.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `allocations` - The founding addresses and the amounts, in smallest units,
+    ///   credited to each of them
     ///
     /// # Returns
     ///
-    /// Option<&Block> - The latest block if available, else None.
-    /// 
+    /// A new Blockchain instance.
+    ///
     /// # Functionality
     ///
-    /// - Calls last() on the chain to get the latest block
-    /// - Returns the block wrapped in Some(), or None if chain is empty
-    fn get_latest_block(&self) -> Option<&Block> {
-        self.chain.last()
+    /// - Calls new() to initialize the Blockchain
+    /// - Replaces the genesis block with one holding a reward transaction per allocation,
+    ///   with the same timestamp and previous hash
+    /// - Rebuilds the balance index so the allocations are credited from the start
+    pub fn with_genesis(
+        difficulty: u32,
+        mining_reward: u64,
+        allocations: Vec<(Address, u64)>,
+    ) -> Self {
+        let mut blockchain = Self::new(difficulty, mining_reward);
+
+        let transactions = allocations
+            .into_iter()
+            .map(|(address, amount)| Transaction::coinbase(address, amount))
+            .collect();
+
+        blockchain
+            .storage
+            .put_in_memory(Block::new_with_algorithm_at(
+                0,
+                transactions,
+                GENESIS_PREVIOUS_HASH.to_string(),
+                blockchain.hash_algorithm,
+                Self::genesis_time(),
+            ));
+        blockchain.rebuild_index();
+
+        blockchain
     }
 
-    /// Mines pending transactions into a new block.
+    /// Creates a new Blockchain instance with a custom genesis block, for a chain
+    /// that must not share the default genesis block.
     ///
     /// # Parameters
     ///
-    /// * `mining_reward_address`: The address to send the mining reward to.
+    /// * `difficulty` - The mining difficulty
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `timestamp` - The timestamp of the genesis block
+    /// * `previous_hash` - The previous hash of the genesis block
+    ///
+    /// # Returns
+    ///
+    /// A new Blockchain instance.
     ///
     /// # Functionality
     ///
-    /// - Creates a reward transaction to the provided address  
-    /// - Gets previous block hash
-    /// - Creates a new block with pending transactions 
-    /// - Mines the block by finding a valid nonce
-    /// - Adds the mined block to the chain
-    /// - Resets pending transactions
-    pub fn mine_pending_transactions(&mut self, mining_reward_address: String) {
-        let reward_transaction = Transaction {
-            from_address: None,
-            to_address: mining_reward_address,
-            amount: self.mining_reward,
-            signature: None,
-            hash: None,
-        };
-        self.pending_transactions.push(reward_transaction);
+    /// - Calls new() to initialize the Blockchain
+    /// - Replaces the genesis block with one with no transactions, the timestamp and
+    ///   the previous hash
+    pub fn with_genesis_time(
+        difficulty: u32,
+        mining_reward: u64,
+        timestamp: DateTime<Utc>,
+        previous_hash: String,
+    ) -> Self {
+        let mut blockchain = Self::new(difficulty, mining_reward);
 
-        let prev_block_hash = match self.get_latest_block() {
-            Some(block) => block.hash.clone(),
-            None => String::from("GenesisBlockHash"),
-        };
+        blockchain
+            .storage
+            .put_in_memory(Block::new_with_algorithm_at(
+                0,
+                vec![],
+                previous_hash,
+                blockchain.hash_algorithm,
+                timestamp,
+            ));
 
-        let mut block = Block::new(self.pending_transactions.clone(), prev_block_hash);
-        block.mine_block(self.difficulty);
+        blockchain
+    }
 
-        self.chain.push(block);
-        self.pending_transactions = vec![];
+    /// Gets the timestamp of the default genesis block.
+    ///
+    /// # Returns
+    ///
+    /// GENESIS_TIMESTAMP as a DateTime
+    fn genesis_time() -> DateTime<Utc> {
+        DateTime::from_timestamp(GENESIS_TIMESTAMP, 0)
+            .expect("GENESIS_TIMESTAMP is a valid timestamp")
     }
 
-    /// Adds a transaction to the blockchain pending transactions.
+    /// Creates a Blockchain instance from untrusted blocks, validating each block
+    /// as it is appended.
     ///
     /// # Parameters
     ///
-    /// * `transaction` - The transaction to add
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `blocks` - The blocks, starting with the genesis block
     ///
-    /// # Returns  
+    /// # Returns
     ///
-    /// `Result<(), TransactionError>`
+    /// `Result<Blockchain, ValidationError>`
     ///
-    /// - `Ok(())` if the transaction was added successfully
-    /// - `Err(TransactionError)` if the transaction is invalid
+    /// - `Ok(Blockchain)` with the blocks as its chain
+    /// - `Err(ValidationError::GenesisMismatch)` if the first block is not the default
+    ///   genesis block
+    /// - `Err(ValidationError)` with the index of the first invalid block and the
+    ///   failed check otherwise, `IndexMismatch` at index 0 if there is no genesis block
     ///
     /// # Functionality
     ///
-    /// - Validates the transaction fields are present
-    /// - Calls transaction.is_valid() to validate the signature  
-    /// - If valid, adds the transaction to pending_transactions
-    /// - Returns a result indicating if the transaction was added
-    pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), TransactionError> {
-        if transaction.from_address.is_none() || transaction.to_address.is_empty() {
-            return Err(TransactionError::InvalidTransaction);
+    /// - Checks the first block with check_genesis() against the default genesis
+    ///   block, then the following blocks as from_blocks_with_genesis() does
+    pub fn from_blocks(
+        difficulty: u32,
+        mining_reward: u64,
+        blocks: Vec<Block>,
+    ) -> Result<Blockchain, ValidationError> {
+        Self::from_untrusted_blocks(difficulty, mining_reward, blocks, None)
+    }
+
+    /// Creates a Blockchain instance from untrusted blocks of a network, validating
+    /// each block as it is appended.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `blocks` - The blocks, starting with the genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network, e.g. the
+    ///   genesis_hash() of with_genesis()
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, ValidationError>`
+    ///
+    /// - `Ok(Blockchain)` with the blocks as its chain
+    /// - `Err(ValidationError::GenesisMismatch)` if the first block does not have the
+    ///   genesis hash
+    /// - `Err(ValidationError)` with the index of the first invalid block and the
+    ///   failed check otherwise, `IndexMismatch` at index 0 if there is no genesis block
+    ///
+    /// # Functionality
+    ///
+    /// - Calls from_genesis_block() with the first block
+    /// - Calls append_untrusted_block() with each following block
+    /// - Rebuilds the balance index
+    pub fn from_blocks_with_genesis(
+        difficulty: u32,
+        mining_reward: u64,
+        blocks: Vec<Block>,
+        genesis_hash: &str,
+    ) -> Result<Blockchain, ValidationError> {
+        Self::from_untrusted_blocks(difficulty, mining_reward, blocks, Some(genesis_hash))
+    }
+
+    /// Creates a Blockchain instance from untrusted blocks, as from_blocks_with_genesis()
+    /// does.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `blocks` - The blocks, starting with the genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network, None for the
+    ///   default genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, ValidationError>` as from_blocks_with_genesis() returns
+    fn from_untrusted_blocks(
+        difficulty: u32,
+        mining_reward: u64,
+        blocks: Vec<Block>,
+        genesis_hash: Option<&str>,
+    ) -> Result<Blockchain, ValidationError> {
+        let mut blocks = blocks.into_iter();
+
+        let genesis_block = blocks
+            .next()
+            .ok_or(ValidationError::IndexMismatch { index: 0 })?;
+        let (mut blockchain, mut state) =
+            Self::from_genesis_block(difficulty, mining_reward, genesis_block, genesis_hash)?;
+
+        for block in blocks {
+            blockchain.append_untrusted_block(&mut state, block)?;
         }
 
-        match transaction.is_valid() {
-            Ok(is_valid) => {
-                if !is_valid {
-                    return Err(TransactionError::InvalidTransaction);
-                }
+        blockchain.rebuild_index();
+
+        Ok(blockchain)
+    }
+
+    /// Checks a block is the genesis block of a network.
+    ///
+    /// # Parameters
+    ///
+    /// * `genesis_block` - The first block of a chain
+    /// * `genesis_hash` - The hash of the genesis block of the network, None for the
+    ///   default genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the block has the genesis hash
+    /// - `Err(ValidationError::GenesisMismatch)` otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Without a genesis hash, compares the block hash with the hash of the genesis
+    ///   block of new_with_pow_function() for the hash algorithm and proof of work
+    ///   function of the block, which is the same on every node as it is timestamped
+    ///   GENESIS_TIMESTAMP
+    fn check_genesis(
+        genesis_block: &Block,
+        genesis_hash: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        let matches = match genesis_hash {
+            Some(genesis_hash) => genesis_block.hash == genesis_hash,
+            None => {
+                let default = Self::new_with_pow_function(
+                    MIN_DIFFICULTY,
+                    0,
+                    genesis_block.hash_algorithm,
+                    genesis_block.pow_function,
+                );
+                genesis_block.hash == default.genesis_hash()
             }
-            Err(err_msg) => println!("Error validating transaction: {}", err_msg),
-        }
+        };
 
-        self.pending_transactions.push(transaction);
-        Ok(())
+        if matches {
+            Ok(())
+        } else {
+            Err(ValidationError::GenesisMismatch)
+        }
     }
 
-    /// Gets the balance for the provided address by iterating through the blockchain.
+    /// Starts a chain from an untrusted genesis block.
     ///
     /// # Parameters
     ///
-    /// * `address` - The address to get the balance for
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `genesis_block` - The genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network, None for the
+    ///   default genesis block
     ///
     /// # Returns
-    ///  
-    /// The current balance of the address as a f64
+    ///
+    /// `Result<(Blockchain, ValidationState), ValidationError>`
+    ///
+    /// - `Ok((Blockchain, ValidationState))` with the genesis block as its only block
+    ///   and the state to check the next block against
+    /// - `Err(ValidationError::GenesisMismatch)` if the block does not have the genesis hash
+    /// - `Err(ValidationError)` if the block index is not 0 or the block is invalid
     ///
     /// # Functionality
     ///
-    /// - Initializes the balance to 0.0
-    /// - Iterates through each block in the chain
-    ///   - In each block, iterates through the transactions
-    ///     - If the address is the recipient, add the amount to the balance
-    ///     - If the address is the sender, subtract the amount from the balance
-    /// - Returns the calculated balance
-    pub fn get_balance_of_address(&self, address: &str) -> f64 {
-        let balance = self.chain.iter().fold(0.0, |acc, block| {
-            block.transactions.iter().fold(acc, |acc, transaction| {
-                if transaction.to_address == address {
-                    acc + transaction.amount
-                } else if transaction.from_address.as_deref() == Some(address) {
-                    acc - transaction.amount
-                } else {
-                    acc
-                }
-            })
-        });
-        balance
+    /// - Calls check_genesis() before any other check, so a chain of another
+    ///   network is rejected immediately
+    /// - Calls new_with_pow_function() with the hash algorithm and proof of work
+    ///   function of the genesis block
+    /// - Replaces the genesis block of the new chain and validates it
+    fn from_genesis_block(
+        difficulty: u32,
+        mining_reward: u64,
+        genesis_block: Block,
+        genesis_hash: Option<&str>,
+    ) -> Result<(Blockchain, ValidationState), ValidationError> {
+        Self::check_genesis(&genesis_block, genesis_hash)?;
+
+        if genesis_block.index != 0 {
+            return Err(ValidationError::IndexMismatch { index: 0 });
+        }
+
+        let mut blockchain = Self::new_with_pow_function(
+            difficulty,
+            mining_reward,
+            genesis_block.hash_algorithm,
+            genesis_block.pow_function,
+        );
+        blockchain.storage.put_in_memory(genesis_block);
+
+        let mut state = blockchain.initial_state();
+        blockchain.validate_block(
+            blockchain.get_latest_block(),
+            None,
+            &mut state,
+            0,
+            blockchain.max_block_timestamp(),
+        )?;
+
+        Ok((blockchain, state))
     }
 
-    /// Validates the blockchain by checking:
+    /// Appends an untrusted block to the chain, after checking it.
     ///
-    /// - The hash of each block matches the calculation
-    /// - The previous hash matches the next block
-    /// - Each block has valid transactions
+    /// The balance index is not updated, rebuild_index() must be called once the
+    /// last block is appended.
+    ///
+    /// # Parameters
+    ///
+    /// * `state` - The validation state after the latest block, advanced past the block
+    /// * `block` - The block following the latest block
     ///
     /// # Returns
     ///
-    /// bool - True if the blockchain is valid, False otherwise
+    /// `Result<(), ValidationError>`
     ///
-    /// # Functionality  
+    /// - `Ok(())` if the block was appended
+    /// - `Err(ValidationError)` with the index of the block and the failed check
     ///
-    /// - Zips the chain with itself offset by 1 to pair blocks
-    /// - For each pair:
-    ///   - Checks hash matches recalculation
-    ///   - Checks previous hash matches next hash
-    ///   - Checks block transactions are valid
-    /// - Returns true if all checks pass, false otherwise
-    pub fn is_valid(&self) -> bool {
-        self.chain
-            .iter()
-            .zip(self.chain.iter().skip(1))
-            .all(|(current_block, next_block)| {
-                current_block.hash
-                    == Block::calculate_hash(
-                        &current_block.timestamp,
-                        &current_block.transactions,
-                        &current_block.previous_hash,
-                        current_block.nonce,
-                    )
-                    && current_block.hash == next_block.previous_hash
-                    && current_block.has_valid_transactions().unwrap_or(false)
-            })
+    /// # Functionality
+    ///
+    /// - Checks the block with validate_block() against the latest block and the state
+    ///   only, the blocks before it were checked when they were appended
+    /// - Sets the difficulty expected of the block, as mine_pending_transactions()
+    ///   adjusted it before mining the block
+    /// - Appends the block
+    fn append_untrusted_block(
+        &mut self,
+        state: &mut ValidationState,
+        block: Block,
+    ) -> Result<(), ValidationError> {
+        let index = self.storage.0.len();
+
+        self.validate_block(
+            &block,
+            Some(self.get_latest_block()),
+            state,
+            index,
+            self.max_block_timestamp(),
+        )?;
+        self.difficulty = state.difficulty;
+
+        self.storage.put_in_memory(block);
+        Ok(())
+    }
+
+    /// Creates a Blockchain instance keeping its blocks in a storage, e.g. a
+    /// FileStorage to keep the chain across restarts.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `storage` - The storage, either empty or holding a chain starting with a genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>` as with_storage_and_genesis() returns, with
+    /// `GenesisMismatch` if the stored genesis block is not the default genesis block
+    ///
+    /// # Functionality
+    ///
+    /// - Opens the storage as with_storage_and_genesis() does, checking the stored
+    ///   genesis block with check_genesis() against the default genesis block
+    pub fn with_storage(
+        difficulty: u32,
+        mining_reward: u64,
+        storage: Box<dyn Storage + Send>,
+    ) -> Result<Blockchain, BlockchainError> {
+        Self::open_storage(difficulty, mining_reward, storage, None)
+    }
+
+    /// Creates a Blockchain instance of a network keeping its blocks in a storage.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `storage` - The storage, either empty or holding a chain starting with a genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network, e.g. the
+    ///   genesis_hash() of new()
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>`
+    ///
+    /// - `Ok(Blockchain)` with the stored blocks as its chain
+    /// - `Err(BlockchainError::Io)` if a stored block could not be read, or the genesis
+    ///   block could not be stored
+    /// - `Err(BlockchainError::InvalidChain)` with `GenesisMismatch` if the stored genesis
+    ///   block, or the genesis block of new() for an empty storage, does not have the
+    ///   genesis hash
+    /// - `Err(BlockchainError::InvalidChain)` with the index of the first invalid stored
+    ///   block and the failed check otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Stores the genesis block of new() if the storage is empty
+    /// - Compares the hash of the genesis block with the genesis hash before any
+    ///   other check
+    /// - Otherwise calls new_with_pow_function() with the hash algorithm of the stored
+    ///   genesis block and restores the stored pruning checkpoint
+    /// - Reads and checks the stored blocks one at a time as validate_stream() does,
+    ///   without keeping them in memory, so the chain is not opened if a block cannot
+    ///   be read
+    /// - Sets the difficulty expected of the latest stored block and the balance index
+    ///   from the state after it
+    /// - The pending transactions are not stored, a reopened chain starts without
+    ///   pending transactions
+    pub fn with_storage_and_genesis(
+        difficulty: u32,
+        mining_reward: u64,
+        storage: Box<dyn Storage + Send>,
+        genesis_hash: &str,
+    ) -> Result<Blockchain, BlockchainError> {
+        Self::open_storage(difficulty, mining_reward, storage, Some(genesis_hash))
+    }
+
+    /// Opens a chain kept in a storage, as with_storage_and_genesis() does.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `storage` - The storage, either empty or holding a chain starting with a genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network, None for the
+    ///   default genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>` as with_storage_and_genesis() returns
+    fn open_storage(
+        difficulty: u32,
+        mining_reward: u64,
+        mut storage: Box<dyn Storage + Send>,
+        genesis_hash: Option<&str>,
+    ) -> Result<Blockchain, BlockchainError> {
+        let Some(genesis_block) = storage.get_block(0)? else {
+            let mut blockchain = Self::new(difficulty, mining_reward);
+            Self::check_genesis(blockchain.get_latest_block(), genesis_hash)?;
+            storage.put_block(blockchain.get_latest_block().clone())?;
+            blockchain.storage = ChainStorage(storage);
+            return Ok(blockchain);
+        };
+
+        Self::check_genesis(genesis_block, genesis_hash)?;
+
+        let mut blockchain = Self::new_with_pow_function(
+            difficulty,
+            mining_reward,
+            genesis_block.hash_algorithm,
+            genesis_block.pow_function,
+        );
+        blockchain.pruned_checkpoint = storage.checkpoint()?;
+
+        let state = blockchain.validate_read_blocks(
+            (0..storage.len()).map_while(|height| storage.read_block(height).transpose()),
+        )?;
+        blockchain.storage = ChainStorage(storage);
+        blockchain.difficulty = state.difficulty;
+        blockchain.balance_index = state.balances;
+
+        Ok(blockchain)
+    }
+
+    /// Gets the latest block in the chain.
+    ///
+    /// The chain is never empty, since the constructor always seeds it with a
+    /// genesis block, so a latest block always exists.
+    ///
+    /// # Returns
+    ///
+    /// &Block - The latest block, which is the genesis block for a fresh chain.
+    /// 
+    /// # Functionality
+    ///
+    /// - Asserts in debug builds that the chain is not empty
+    /// - Calls tip() on the storage to get the latest block
+    pub fn get_latest_block(&self) -> &Block {
+        debug_assert!(
+            !self.storage.0.is_empty(),
+            "The chain must contain a genesis block"
+        );
+        self.storage
+            .0
+            .tip()
+            .ok()
+            .flatten()
+            .expect("The chain must contain a genesis block")
+    }
+
+    /// Gets the hash of the genesis block, identifying the network of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The hex encoded hash of the block at height 0
+    pub fn genesis_hash(&self) -> &str {
+        &self
+            .block_at(0)
+            .expect("The chain must contain a genesis block")
+            .hash
+    }
+
+    /// Gets the height of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The index of the latest block as a u64, 0 for a chain with only the genesis block
+    pub fn height(&self) -> u64 {
+        self.get_latest_block().index
+    }
+
+    /// Gets the headers of the blocks of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The header of each block, from the genesis block to the latest block
+    pub fn headers(&self) -> Vec<BlockHeader> {
+        self.blocks().map(Block::header).collect()
+    }
+
+    /// Iterates over the blocks of the chain.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the blocks, from the genesis block to the latest block
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> {
+        self.blocks_from(0)
+    }
+
+    /// Iterates over the blocks of the chain from a position.
+    ///
+    /// # Parameters
+    ///
+    /// * `position` - The index of the first block
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the blocks, from the position to the latest block
+    fn blocks_from(&self, position: usize) -> impl Iterator<Item = &Block> {
+        self.storage
+            .0
+            .blocks_from(position as u64)
+            .map_while(Result::ok)
+    }
+
+    /// Gets the block at the provided height.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    ///
+    /// # Returns
+    ///
+    /// The block at the height, or None if the chain is not that high
+    pub fn block_at(&self, height: u64) -> Option<&Block> {
+        self.storage.0.get_block(height).ok().flatten()
+    }
+
+    /// Gets the pending transactions.
+    ///
+    /// # Returns
+    ///
+    /// The transactions waiting to be mined, in insertion order
+    pub fn pending(&self) -> &[Transaction] {
+        &self.pending_transactions
+    }
+
+    /// Gets the number of pending transactions.
+    ///
+    /// # Returns
+    ///
+    /// The number of transactions waiting to be mined as a usize
+    pub fn pending_count(&self) -> usize {
+        self.pending_transactions.len()
+    }
+
+    /// Drops all the pending transactions without mining them.
+    pub fn clear_pending(&mut self) {
+        self.pending_transactions.clear();
+    }
+
+    /// Drops a pending transaction without mining it.
+    ///
+    /// # Parameters
+    ///
+    /// * `tx_hash` - The hash of the transaction, as returned by compute_hash()
+    ///
+    /// # Returns
+    ///
+    /// bool - True if a pending transaction had the hash and was removed, False otherwise
+    pub fn remove_pending(&mut self, tx_hash: &str) -> bool {
+        let count = self.pending_transactions.len();
+
+        self.pending_transactions
+            .retain(|transaction| transaction.compute_hash() != tx_hash);
+
+        self.pending_transactions.len() != count
+    }
+
+    /// Drops the pending transactions waiting to be mined for too long.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_age` - How long a transaction may stay pending
+    ///
+    /// # Returns
+    ///
+    /// The dropped transactions, in insertion order
+    ///
+    /// # Functionality
+    ///
+    /// - Removes each pending transaction received more than max_age ago
+    /// - Keeps the transactions without a received_at time, which were not added
+    ///   with add_transaction()
+    pub fn evict_stale(&mut self, max_age: Duration) -> Vec<Transaction> {
+        let now = Utc::now();
+        let is_stale = |transaction: &Transaction| {
+            transaction.received_at.is_some_and(|received_at| {
+                (now - received_at).to_std().is_ok_and(|age| age > max_age)
+            })
+        };
+
+        let (stale, fresh) = std::mem::take(&mut self.pending_transactions)
+            .into_iter()
+            .partition(is_stale);
+        self.pending_transactions = fresh;
+
+        stale
+    }
+
+    /// Drops the pending transactions that can no longer be mined.
+    ///
+    /// # Returns
+    ///
+    /// The dropped transactions, in insertion order
+    ///
+    /// # Functionality
+    ///
+    /// - Removes each pending transaction expired at the height of the next block
+    pub fn drop_expired(&mut self) -> Vec<Transaction> {
+        let height = self.height() + 1;
+
+        let (expired, valid) = std::mem::take(&mut self.pending_transactions)
+            .into_iter()
+            .partition(|transaction: &Transaction| transaction.is_expired_at(height));
+        self.pending_transactions = valid;
+
+        expired
+    }
+
+    /// Gets the mining reward for the next block.
+    ///
+    /// # Returns
+    ///
+    /// The reward amount in smallest units
+    ///
+    /// # Functionality
+    ///
+    /// - Calls capped_reward() with the next block height and total_issued()
+    pub fn current_reward(&self) -> u64 {
+        self.capped_reward(self.height() + 1, self.total_issued())
+    }
+
+    /// Gets the mining reward for a block under the supply cap.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    /// * `issued` - The coins issued by the blocks before it, in smallest units
+    ///
+    /// # Returns
+    ///
+    /// The reward amount in smallest units
+    ///
+    /// # Functionality
+    ///
+    /// - Calls reward_at() with the height
+    /// - Reduces the reward to the coins left to issue under max_supply, down to 0
+    ///   once the cap is reached
+    fn capped_reward(&self, height: u64, issued: u64) -> u64 {
+        let reward = self.reward_at(height);
+
+        match self.max_supply {
+            Some(max_supply) => reward.min(max_supply.saturating_sub(issued)),
+            None => reward,
+        }
+    }
+
+    /// Gets the mining reward for a block at the provided height.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    ///
+    /// # Returns
+    ///
+    /// The reward amount in smallest units
+    ///
+    /// # Functionality
+    ///
+    /// - Computes the number of halvings as the height divided by halving_interval
+    /// - Returns mining_reward shifted right by the number of halvings, rounding down to 0
+    /// - Returns mining_reward unchanged if halving_interval is 0
+    pub fn reward_at(&self, height: u64) -> u64 {
+        if self.halving_interval == 0 {
+            return self.mining_reward;
+        }
+
+        let halvings = height / self.halving_interval;
+        self.mining_reward
+            .checked_shr(halvings.min(u32::MAX as u64) as u32)
+            .unwrap_or(0)
+    }
+
+    /// Adjusts the mining difficulty based on the time between the last two blocks.
+    ///
+    /// # Functionality
+    ///
+    /// - Does nothing if the chain has fewer than two blocks
+    /// - Computes the time elapsed between the last two blocks
+    /// - Increases the difficulty by one if it is shorter than the target block time
+    /// - Decreases the difficulty by one if it is longer than the target block time
+    /// - Clamps the difficulty between min_difficulty and max_difficulty
+    pub fn adjust_difficulty(&mut self) {
+        self.adjust_difficulty_at(self.storage.0.len());
+    }
+
+    /// Adjusts the mining difficulty based on the time between the last two
+    /// blocks of the start of the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `len` - The number of blocks of the start of the chain
+    ///
+    /// # Functionality
+    ///
+    /// - Applies the rules of adjust_difficulty() to the blocks at `len - 2` and `len - 1`
+    fn adjust_difficulty_at(&mut self, len: u64) {
+        self.difficulty = self.next_difficulty_at(len);
+    }
+
+    /// Computes the difficulty adjust_difficulty_at() would set, without setting it.
+    ///
+    /// # Parameters
+    ///
+    /// * `len` - The number of blocks of the start of the chain
+    ///
+    /// # Returns
+    ///
+    /// The adjusted difficulty, the current difficulty if there are fewer than two blocks
+    fn next_difficulty_at(&self, len: u64) -> u32 {
+        let (Some(previous_block), Some(latest_block)) = (
+            len.checked_sub(2).and_then(|height| self.block_at(height)),
+            len.checked_sub(1).and_then(|height| self.block_at(height)),
+        ) else {
+            return self.difficulty;
+        };
+
+        self.adjusted_difficulty(
+            self.difficulty,
+            previous_block.timestamp,
+            latest_block.timestamp,
+        )
+    }
+
+    /// Adjusts a difficulty to the time between two blocks.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The difficulty the latest block was expected to meet
+    /// * `previous_timestamp` - The timestamp of the block before the latest block
+    /// * `latest_timestamp` - The timestamp of the latest block
+    ///
+    /// # Returns
+    ///
+    /// The difficulty of the next block, following the rules of adjust_difficulty()
+    fn adjusted_difficulty(
+        &self,
+        difficulty: u32,
+        previous_timestamp: DateTime<Utc>,
+        latest_timestamp: DateTime<Utc>,
+    ) -> u32 {
+        let block_time = (latest_timestamp - previous_timestamp)
+            .to_std()
+            .unwrap_or(Duration::ZERO);
+
+        let difficulty = if block_time < self.target_block_time {
+            difficulty.saturating_add(1)
+        } else if block_time > self.target_block_time {
+            difficulty.saturating_sub(1)
+        } else {
+            difficulty
+        };
+
+        difficulty.max(self.min_difficulty).min(self.max_difficulty)
+    }
+
+    /// Estimates how long mining a block takes at the current difficulty.
+    ///
+    /// # Parameters
+    ///
+    /// * `measured_hashrate` - The hash rate of the miner in hashes per second,
+    ///   e.g. from Block::benchmark_hashrate()
+    ///
+    /// # Returns
+    ///
+    /// The expected mining time in seconds as a f64, infinite for a hash rate of 0
+    ///
+    /// # Functionality
+    ///
+    /// - Converts the difficulty to a 256-bit target
+    /// - Computes the expected number of hashes for the target, which doubles
+    ///   with each unit of difficulty
+    /// - Divides it by the hash rate
+    pub fn estimate_mine_seconds(&self, measured_hashrate: f64) -> f64 {
+        let target = Block::difficulty_to_target(self.difficulty);
+        Block::expected_hashes(&target) / measured_hashrate
+    }
+
+    /// Selects the pending transactions to include in the next block.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_bytes` - The maximum total serialized size of the selected transactions
+    ///
+    /// # Returns
+    ///
+    /// The selected transactions, in the order they were selected
+    ///
+    /// # Functionality
+    ///
+    /// - Queues the pending transactions of each sender by ascending nonce
+    /// - Repeatedly selects the highest fee transaction among the first queued
+    ///   transaction of each sender, keeping insertion order between equal fees,
+    ///   so a sender's lower nonce is never mined after a higher one
+    /// - Takes at most max_transactions_per_block transactions
+    /// - Skips the remaining transactions of a sender once its next transaction does
+    ///   not fit in the bytes left, so smaller transactions of other senders can
+    ///   still fill the block
+    /// - Removes the selected transactions from pending_transactions, leaving the
+    ///   rest in insertion order for the next block
+    fn select_transactions_to_mine(&mut self, max_bytes: usize) -> Vec<Transaction> {
+        let order = self.selection_order(max_bytes);
+
+        let mut pending: Vec<Option<Transaction>> = std::mem::take(&mut self.pending_transactions)
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        let selected = order
+            .iter()
+            .filter_map(|&position| pending[position].take())
+            .collect();
+
+        self.pending_transactions = pending.into_iter().flatten().collect();
+
+        selected
+    }
+
+    /// Chooses the pending transactions to include in the next block, as
+    /// select_transactions_to_mine() does, without removing them.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_bytes` - The maximum total serialized size of the selected transactions
+    ///
+    /// # Returns
+    ///
+    /// The positions in pending_transactions of the selected transactions, in the
+    /// order they were selected, never including a transaction expired at the
+    /// height of the next block
+    fn selection_order(&self, max_bytes: usize) -> Vec<usize> {
+        let height = self.height() + 1;
+        let mut queues: Vec<VecDeque<usize>> = Vec::new();
+        let mut queue_of_sender: HashMap<Option<Address>, usize> = HashMap::new();
+
+        for (position, transaction) in self.pending_transactions.iter().enumerate() {
+            if transaction.is_expired_at(height) {
+                continue;
+            }
+
+            let queue = *queue_of_sender
+                .entry(transaction.from_address)
+                .or_insert_with(|| {
+                    queues.push(VecDeque::new());
+                    queues.len() - 1
+                });
+            queues[queue].push_back(position);
+        }
+
+        for queue in &mut queues {
+            queue
+                .make_contiguous()
+                .sort_by_key(|&position| self.pending_transactions[position].nonce);
+        }
+
+        let mut order: Vec<usize> = Vec::new();
+        let mut bytes_left = max_bytes;
+
+        while order.len() < self.max_transactions_per_block {
+            let best = queues
+                .iter()
+                .enumerate()
+                .filter_map(|(queue, positions)| {
+                    positions.front().map(|&position| (queue, position))
+                })
+                .max_by(|&(_, a), &(_, b)| {
+                    self.pending_transactions[a]
+                        .fee
+                        .cmp(&self.pending_transactions[b].fee)
+                        .then(b.cmp(&a))
+                });
+
+            let Some((queue, position)) = best else {
+                break;
+            };
+
+            let size = self.pending_transactions[position].serialized_size();
+            if size > bytes_left {
+                queues[queue].clear();
+                continue;
+            }
+
+            queues[queue].pop_front();
+            order.push(position);
+            bytes_left -= size;
+        }
+
+        order
+    }
+
+    /// Mines pending transactions into a new block.
+    ///
+    /// # Parameters
+    ///
+    /// * `mining_reward_address`: The address to send the mining reward to.
+    ///
+    /// # Functionality
+    ///
+    /// - Adjusts the difficulty based on recent block times
+    /// - Drops the expired pending transactions with drop_expired()
+    /// - Selects up to max_transactions_per_block pending transactions, highest fee first
+    ///   while keeping each sender's transactions in nonce order, fitting in
+    ///   max_block_bytes along with the reward transaction
+    /// - If require_transactions is set and no transaction was selected, restores the
+    ///   difficulty and returns without mining a block
+    /// - Sums the fees of the selected transactions
+    /// - Creates a reward transaction of current_reward(), capped by max_supply, plus
+    ///   fees to the provided address, which is always included on top of the selected
+    ///   transactions
+    /// - Gets previous block hash and the next block index
+    /// - Creates a new block with the selected transactions and the reward transaction,
+    ///   sorted with Block::sort_canonically() if canonical_transaction_order is set,
+    ///   hashed with the chain hash algorithm
+    /// - Seals the block with the consensus, by default mining it by finding a valid nonce
+    /// - Adds the sealed block to the chain with append_block()
+    /// - Leaves the unselected transactions pending
+    /// - If the consensus refuses to seal the block, or the storage cannot store
+    ///   it, returns the selected transactions to the pending transactions and
+    ///   restores the difficulty
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ConsensusError>`
+    ///
+    /// - `Ok(())` if the block was added to the chain
+    /// - `Err(ConsensusError::NoTransactions)` if require_transactions is set and there
+    ///   is no transaction to mine, the chain being left unchanged
+    /// - `Err(ConsensusError::Storage)` if the storage could not store the sealed block,
+    ///   the chain being left unchanged
+    /// - `Err(ConsensusError)` if the consensus does not let this node produce the block,
+    ///   never the case with the default proof of work
+    pub fn mine_pending_transactions(
+        &mut self,
+        mining_reward_address: Address,
+    ) -> Result<(), ConsensusError> {
+        self.mine_pending_transactions_at(mining_reward_address, Utc::now())
+    }
+
+    /// Mines the pending transactions into a new block with a fixed timestamp.
+    ///
+    /// Meant for reproducible block hashes, e.g. in tests. The timestamp must be
+    /// after the latest block timestamp for the chain to stay valid.
+    ///
+    /// # Parameters
+    ///
+    /// * `mining_reward_address`: The address to send the mining reward to.
+    /// * `timestamp`: The timestamp of the new block.
+    ///
+    /// # Functionality
+    ///
+    /// - Works like mine_pending_transactions(), with the provided timestamp
+    ///   instead of the current time
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ConsensusError>` - The result of sealing the block, as with
+    /// mine_pending_transactions()
+    pub fn mine_pending_transactions_at(
+        &mut self,
+        mining_reward_address: Address,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), ConsensusError> {
+        let difficulty = self.difficulty;
+        self.adjust_difficulty();
+        self.drop_expired();
+
+        let reward_size = Transaction::coinbase(mining_reward_address, 0).serialized_size();
+        let mut transactions =
+            self.select_transactions_to_mine(self.max_block_bytes.saturating_sub(reward_size));
+
+        if self.require_transactions && transactions.is_empty() {
+            self.difficulty = difficulty;
+            return Err(ConsensusError::NoTransactions);
+        }
+
+        let total_fees = Self::total_fees(transactions.iter());
+
+        let reward_transaction = Transaction::coinbase(
+            mining_reward_address,
+            self.current_reward().saturating_add(total_fees),
+        );
+        transactions.push(reward_transaction);
+        if self.canonical_transaction_order {
+            Block::sort_canonically(&mut transactions);
+        }
+
+        let latest_block = self.get_latest_block();
+        let index = latest_block.index + 1;
+        let prev_block_hash = latest_block.hash.clone();
+
+        let mut block = Block::new_with_algorithm_at(
+            index,
+            transactions,
+            prev_block_hash,
+            self.hash_algorithm,
+            timestamp,
+        );
+        block.set_pow_function(self.pow_function);
+        if let Err(err) = self.consensus.0.produce_block(self, &mut block) {
+            self.restore_selected(block, difficulty);
+            return Err(err);
+        }
+
+        if let Err(err) = self.append_block(block.clone()) {
+            self.restore_selected(block, difficulty);
+            return Err(ConsensusError::Storage(err.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the contents of the next block, for a miner outside the node.
+    ///
+    /// # Parameters
+    ///
+    /// * `reward_to` - The address to send the mining reward to
+    ///
+    /// # Returns
+    ///
+    /// The BlockTemplate of the next block, to be solved and passed to submit_block()
+    ///
+    /// # Functionality
+    ///
+    /// - Computes the difficulty mine_pending_transactions() would adjust to
+    /// - Selects the pending transactions and creates the reward transaction as
+    ///   mine_pending_transactions() does, timestamped now, leaving
+    ///   pending_transactions untouched
+    pub fn block_template(&self, reward_to: Address) -> BlockTemplate {
+        let reward_size = Transaction::coinbase(reward_to, 0).serialized_size();
+        let mut transactions: Vec<Transaction> = self
+            .selection_order(self.max_block_bytes.saturating_sub(reward_size))
+            .into_iter()
+            .map(|position| self.pending_transactions[position].clone())
+            .collect();
+
+        let total_fees = Self::total_fees(transactions.iter());
+        transactions.push(Transaction::coinbase(
+            reward_to,
+            self.current_reward().saturating_add(total_fees),
+        ));
+        if self.canonical_transaction_order {
+            Block::sort_canonically(&mut transactions);
+        }
+
+        let latest_block = self.get_latest_block();
+        let index = latest_block.index + 1;
+        let timestamp = Utc::now();
+        let merkle_root = Block::compute_merkle_root(&self.hash_algorithm, &transactions);
+        let header = Block::hash_prefix(index, &timestamp, &merkle_root, &latest_block.hash);
+
+        BlockTemplate {
+            index,
+            timestamp,
+            previous_hash: latest_block.hash.clone(),
+            transactions,
+            merkle_root,
+            difficulty: self.next_difficulty_at(self.storage.0.len()),
+            hash_algorithm: self.hash_algorithm,
+            pow_function: self.pow_function,
+            header,
+        }
+    }
+
+    /// Adds a block solved by a miner outside the node to the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `solved` - The block, e.g. built with BlockTemplate::to_block()
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), BlockchainError>`
+    ///
+    /// - `Ok(())` if the block was added to the chain
+    /// - `Err(BlockchainError::InvalidChain)` with the index of the block and the
+    ///   failed check
+    /// - `Err(BlockchainError::Io)` if the storage could not store the block, the
+    ///   chain and the pending transactions being left unchanged
+    ///
+    /// # Functionality
+    ///
+    /// - Calls solved.verify() with the latest block and the difficulty
+    ///   mine_pending_transactions() would adjust to
+    /// - Checks each transaction other than the reward transaction is pending, so
+    ///   it passed the checks of add_transaction()
+    /// - Checks the block with the rules of validate() against the chain
+    /// - Adds the block to the chain, applies its transactions to the balance index
+    ///   and notifies the subscribers, as mine_pending_transactions() does
+    /// - Sets the adjusted difficulty and removes the block transactions from the
+    ///   pending transactions once the block is stored
+    pub fn submit_block(&mut self, solved: Block) -> Result<(), BlockchainError> {
+        let index = solved.index;
+        let difficulty = self.next_difficulty_at(self.storage.0.len());
+        let latest_block = self.get_latest_block();
+
+        solved.verify(latest_block, difficulty)?;
+
+        let pending_hashes: HashSet<String> = self
+            .pending_transactions
+            .iter()
+            .map(Transaction::compute_hash)
+            .collect();
+        let block_hashes: HashSet<String> = solved
+            .transactions
+            .iter()
+            .filter(|transaction| transaction.from_address.is_some())
+            .map(Transaction::compute_hash)
+            .collect();
+
+        if !block_hashes.is_subset(&pending_hashes) {
+            return Err(ValidationError::InvalidTransactions { index }.into());
+        }
+
+        self.validate_block(
+            &solved,
+            Some(latest_block),
+            &mut self.tip_state(),
+            index,
+            self.max_block_timestamp(),
+        )?;
+
+        self.append_block(solved)?;
+        self.difficulty = difficulty;
+        self.pending_transactions
+            .retain(|transaction| !block_hashes.contains(&transaction.compute_hash()));
+
+        Ok(())
+    }
+
+    /// Returns the transactions of a block that could not be added to the chain
+    /// to the pending transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block assembled by mine_pending_transactions_at()
+    /// * `difficulty` - The difficulty before the block was assembled
+    ///
+    /// # Functionality
+    ///
+    /// - Puts the block transactions other than the reward transaction back in
+    ///   front of the pending transactions, in the order they were selected
+    /// - Restores the difficulty
+    fn restore_selected(&mut self, block: Block, difficulty: u32) {
+        self.pending_transactions = block
+            .transactions
+            .into_iter()
+            .filter(|transaction| transaction.from_address.is_some())
+            .chain(std::mem::take(&mut self.pending_transactions))
+            .collect();
+        self.difficulty = difficulty;
+    }
+
+    /// Sets the consensus sealing new blocks and verifying the blocks of chains.
+    ///
+    /// # Parameters
+    ///
+    /// * `consensus` - The consensus, e.g. ProofOfStake instead of the default ProofOfWork
+    ///
+    /// # Functionality
+    ///
+    /// - Records the mode of the consensus, which is saved with the chain, so the
+    ///   blocks of a reloaded chain are verified with it
+    /// - Seals the next blocks with the consensus
+    pub fn set_consensus(&mut self, consensus: Box<dyn Consensus + Send>) {
+        self.consensus_mode = consensus.mode();
+        self.consensus = ConsensusStrategy(consensus);
+    }
+
+    /// Gets the mode of the consensus the blocks of the chain are verified with.
+    ///
+    /// # Returns
+    ///
+    /// The ConsensusMode recorded by set_consensus(), proof of work by default
+    pub fn consensus_mode(&self) -> ConsensusMode {
+        self.consensus_mode
+    }
+
+    /// Gets the lowest fee accepted by add_transaction().
+    ///
+    /// # Returns
+    ///
+    /// The minimum relay fee, in smallest units
+    pub fn min_relay_fee(&self) -> u64 {
+        self.min_relay_fee
+    }
+
+    /// Sets the lowest fee accepted by add_transaction().
+    ///
+    /// Transactions already pending are kept even if their fee is below the new floor.
+    ///
+    /// # Parameters
+    ///
+    /// * `fee` - The minimum relay fee, in smallest units, 0 to accept any fee
+    pub fn set_min_relay_fee(&mut self, fee: u64) {
+        self.min_relay_fee = fee;
+    }
+
+    /// Estimates the fee a transaction needs to be mined soon.
+    ///
+    /// # Parameters
+    ///
+    /// * `within_blocks` - The number of blocks the transaction should be mined within,
+    ///   also the number of recent blocks looked at, 0 is treated as 1
+    ///
+    /// # Returns
+    ///
+    /// The recommended fee per transaction, in smallest units, never below the
+    /// minimum relay fee, which is returned without recent or pending transactions
+    ///
+    /// # Functionality
+    ///
+    /// - Takes the median of the fees of the non-reward transactions of the last
+    ///   within_blocks blocks, the genesis block excluded, the higher of the two
+    ///   middle fees for an even number of transactions
+    /// - If more transactions are pending than within_blocks blocks of
+    ///   max_transactions_per_block hold, raises the estimate to one more than the fee
+    ///   of the last pending transaction that would still be mined in time
+    /// - Returns the highest of the median, the congestion fee and the minimum relay fee
+    pub fn estimate_fee(&self, within_blocks: u64) -> u64 {
+        let within_blocks = within_blocks.max(1);
+        let start = (self.height() + 1).saturating_sub(within_blocks).max(1);
+
+        let mut fees: Vec<u64> = self
+            .blocks_from(start as usize)
+            .flat_map(|block| &block.transactions)
+            .filter(|transaction| transaction.from_address.is_some())
+            .map(|transaction| transaction.fee)
+            .collect();
+        fees.sort_unstable();
+
+        let median = fees.get(fees.len() / 2).copied().unwrap_or(0);
+
+        let mut pending_fees: Vec<u64> = self
+            .pending_transactions
+            .iter()
+            .map(|transaction| transaction.fee)
+            .collect();
+        pending_fees.sort_unstable_by(|a, b| b.cmp(a));
+
+        let capacity = (within_blocks as usize).saturating_mul(self.max_transactions_per_block);
+        let congestion = pending_fees
+            .get(capacity.saturating_sub(1))
+            .filter(|_| pending_fees.len() > capacity)
+            .map_or(0, |&fee| fee.saturating_add(1));
+
+        median.max(congestion).max(self.min_relay_fee)
+    }
+
+    /// Registers a callback notified of each block added as the tip of the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `cb` - The callback, receiving the freshly added block
+    ///
+    /// # Functionality
+    ///
+    /// - Notifies the callback of each block mined by mine_pending_transactions(),
+    ///   submitted with submit_block() or accepted with accept_block(), including a
+    ///   block accept_block() swaps with the tip
+    /// - Does not notify the callback of the blocks adopted by calling
+    ///   replace_chain() directly, nor of the blocks loaded when the chain is opened
+    pub fn subscribe(&mut self, cb: BlockCallback) {
+        self.subscribers.0.push(cb);
+    }
+
+    /// Registers a callback notified of each transaction accepted by add_transaction().
+    ///
+    /// # Parameters
+    ///
+    /// * `cb` - The callback, receiving the freshly added transaction
+    pub fn subscribe_transactions(&mut self, cb: TransactionCallback) {
+        self.transaction_subscribers.0.push(cb);
+    }
+
+    /// Adds a transaction to the blockchain pending transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `transaction` - The transaction to add
+    ///
+    /// # Returns  
+    ///
+    /// `Result<(), BlockchainError>`
+    ///
+    /// - `Ok(())` if the transaction was added successfully
+    /// - `Err(BlockchainError::Transaction)` if the transaction is invalid
+    ///
+    /// # Functionality
+    ///
+    /// - Validates the transaction fields are present, with at least one output
+    /// - Rejects the transaction if its stored hash does not match its contents
+    /// - Rejects the transaction if it cannot fit in a block along with a reward transaction
+    /// - Rejects the transaction if its fee is below min_relay_fee, reward transactions
+    ///   being rejected before as they have no sender
+    /// - Rejects the transaction if its memo is longer than MAX_MEMO_BYTES
+    /// - Calls transaction.is_valid() to validate the signature, rejecting the
+    ///   transaction if the signature is wrong or could not be checked at all
+    /// - Calls check_transfer() to check the transaction is new, its nonce and the
+    ///   sender projected balance
+    /// - If valid, sets received_at to now, adds the transaction to pending_transactions
+    ///   and notifies the transaction subscribers, in registration order
+    /// - Returns a result indicating if the transaction was added
+    pub fn add_transaction(&mut self, mut transaction: Transaction) -> Result<(), BlockchainError> {
+        if transaction.from_address.is_none() || transaction.outputs.is_empty() {
+            return Err(TransactionError::InvalidTransaction.into());
+        }
+
+        if !transaction.has_matching_hash() {
+            return Err(TransactionError::HashMismatch.into());
+        }
+
+        if transaction.serialized_size() > self.max_transaction_bytes(&transaction) {
+            return Err(TransactionError::TooLarge.into());
+        }
+
+        if transaction.fee < self.min_relay_fee {
+            return Err(TransactionError::FeeTooLow.into());
+        }
+
+        if transaction.memo_len() > MAX_MEMO_BYTES {
+            return Err(TransactionError::MemoTooLarge.into());
+        }
+
+        match transaction.is_valid() {
+            Ok(is_valid) => {
+                if !is_valid {
+                    return Err(TransactionError::InvalidTransaction.into());
+                }
+            }
+            Err(err_msg) => {
+                warn!("Error validating transaction: {}", err_msg);
+                return Err(TransactionError::InvalidTransaction.into());
+            }
+        }
+
+        self.check_transfer(&transaction)?;
+
+        transaction.received_at = Some(Utc::now());
+
+        for subscriber in &self.transaction_subscribers.0 {
+            subscriber(&transaction);
+        }
+
+        self.pending_transactions.push(transaction);
+        Ok(())
+    }
+
+    /// Simulates adding a transaction, without modifying the blockchain.
+    ///
+    /// # Parameters
+    ///
+    /// * `tx` - The transaction to simulate, signed or not
+    ///
+    /// # Returns
+    ///
+    /// `Result<SimResult, TransactionError>`
+    ///
+    /// - `Ok(SimResult)` with the outcome if the sender can afford the transaction
+    /// - `Err(TransactionError)` if add_transaction() would reject the transaction
+    ///   regardless of its signature
+    ///
+    /// # Functionality
+    ///
+    /// - Validates the transaction fields are present, with at least one output
+    /// - Rejects the transaction if it cannot fit in a block along with a reward transaction
+    /// - Rejects the transaction if its fee is below min_relay_fee
+    /// - Rejects the transaction if its memo is longer than MAX_MEMO_BYTES
+    /// - Calls check_transfer() to check the transaction is new, its nonce and the
+    ///   sender projected balance
+    /// - Reports whether the stored hash matches the contents and the signature is
+    ///   valid, so a transaction can be simulated before it is signed
+    /// - Leaves pending_transactions untouched and does not notify the subscribers
+    pub fn simulate(&self, tx: &Transaction) -> Result<SimResult, TransactionError> {
+        if tx.from_address.is_none() || tx.outputs.is_empty() {
+            return Err(TransactionError::InvalidTransaction);
+        }
+
+        if tx.serialized_size() > self.max_transaction_bytes(tx) {
+            return Err(TransactionError::TooLarge);
+        }
+
+        if tx.fee < self.min_relay_fee {
+            return Err(TransactionError::FeeTooLow);
+        }
+
+        if tx.memo_len() > MAX_MEMO_BYTES {
+            return Err(TransactionError::MemoTooLarge);
+        }
+
+        let balance_after = self.check_transfer(tx)?;
+
+        Ok(SimResult {
+            valid: tx.has_matching_hash() && tx.is_valid().unwrap_or(false),
+            balance_after,
+            fee: tx.fee,
+        })
+    }
+
+    /// Gets the largest size a transaction can have to fit in a block.
+    ///
+    /// # Parameters
+    ///
+    /// * `transaction` - The transaction to fit
+    ///
+    /// # Returns
+    ///
+    /// max_block_bytes minus the size of a reward transaction, whose size does not
+    /// depend on its recipient or amount
+    fn max_transaction_bytes(&self, transaction: &Transaction) -> usize {
+        let reward_size = transaction.from_address.map_or(0, |address| {
+            Transaction::coinbase(address, 0).serialized_size()
+        });
+
+        self.max_block_bytes.saturating_sub(reward_size)
+    }
+
+    /// Checks the sender of a transaction can make the transfer.
+    ///
+    /// # Parameters
+    ///
+    /// * `transaction` - The transaction to check
+    ///
+    /// # Returns
+    ///
+    /// `Result<u64, TransactionError>`
+    ///
+    /// - `Ok(u64)` with the sender projected balance once the transaction is mined,
+    ///   0 for mining rewards
+    /// - `Err(TransactionError)` if the transaction is known, its nonce is too low
+    ///   or the sender cannot afford it
+    ///
+    /// # Functionality
+    ///
+    /// - Rejects the transaction if its recomputed hash is already pending or mined
+    /// - Rejects the transaction if it is expired at the height of the next block
+    /// - Rejects the transaction if its nonce is lower than the sender's next nonce
+    /// - Checks the sender projected balance covers the total amount of the outputs and fee
+    fn check_transfer(&self, transaction: &Transaction) -> Result<u64, TransactionError> {
+        if self.is_known_transaction(&transaction.compute_hash()) {
+            return Err(TransactionError::DuplicateTransaction);
+        }
+
+        if transaction.is_expired_at(self.height() + 1) {
+            return Err(TransactionError::Expired);
+        }
+
+        let Some(from_address) = transaction.from_address else {
+            return Ok(0);
+        };
+
+        if transaction.nonce < self.next_nonce_for(&from_address) {
+            return Err(TransactionError::InvalidNonce);
+        }
+
+        self.projected_balance(&from_address)
+            .checked_sub(transaction.total_amount().saturating_add(transaction.fee))
+            .ok_or(TransactionError::InsufficientFunds)
+    }
+
+    /// Adds a batch of transactions to the pending transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `txs` - The transactions to add, in order
+    ///
+    /// # Returns
+    ///
+    /// The outcome of add_transaction() for each transaction, in the same order
+    ///
+    /// # Functionality
+    ///
+    /// - Calls add_transaction() with each transaction in order, so the balance
+    ///   and nonce checks of a transaction account for the ones accepted before it
+    /// - Keeps going after a rejected transaction
+    pub fn add_transactions(&mut self, txs: Vec<Transaction>) -> Vec<Result<(), BlockchainError>> {
+        txs.into_iter()
+            .map(|transaction| self.add_transaction(transaction))
+            .collect()
+    }
+
+    /// Gets the balance for the provided address from the balance index.
+    ///
+    /// The index is kept up to date by mine_pending_transactions() and
+    /// replace_chain(), rebuild_index() must be called after modifying the chain directly.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The address to get the balance for
+    ///
+    /// # Returns
+    ///
+    /// The current balance of the address in smallest units, 0 for an unknown address
+    pub fn get_balance_of_address(&self, address: &Address) -> u64 {
+        self.balance_index.get(address).copied().unwrap_or(0)
+    }
+
+    /// Gets the balance of every address holding coins.
+    ///
+    /// # Returns
+    ///
+    /// A map from each address to its balance in smallest units, without the
+    /// addresses whose balance is 0
+    ///
+    /// # Functionality
+    ///
+    /// - Copies the non-zero entries of the balance index, which is built in a
+    ///   single pass over the chain by rebuild_index()
+    pub fn all_balances(&self) -> HashMap<Address, u64> {
+        self.balance_index
+            .iter()
+            .filter(|(_, &balance)| balance > 0)
+            .map(|(&address, &balance)| (address, balance))
+            .collect()
+    }
+
+    /// Counts the blocks mined by each reward address.
+    ///
+    /// # Returns
+    ///
+    /// A map from each address to the number of blocks whose reward it received
+    ///
+    /// # Functionality
+    ///
+    /// - Skips the genesis block, whose transactions without sender are allocations
+    /// - Reads the recipient of the last transaction without sender of each block,
+    ///   the coinbase transaction being added after the mined transactions
+    /// - Pruned blocks are not counted, their reward transaction was discarded
+    pub fn miner_distribution(&self) -> HashMap<String, u64> {
+        let mut distribution = HashMap::new();
+
+        for block in self.blocks_from(1) {
+            let miner = block
+                .transactions
+                .iter()
+                .rev()
+                .find(|transaction| transaction.from_address.is_none())
+                .and_then(|reward| reward.outputs.first());
+
+            if let Some(miner) = miner {
+                *distribution
+                    .entry(miner.to_address.to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        distribution
+    }
+
+    /// Gets a summary of the chain state.
+    ///
+    /// # Returns
+    ///
+    /// The ChainStats of the chain
+    ///
+    /// # Functionality
+    ///
+    /// - Counts the transactions of each unpruned block and the coins they issued,
+    ///   as total_issued() does, in a single pass
+    pub fn stats(&self) -> ChainStats {
+        let (total_transactions, total_coins) = self
+            .unpruned_transactions()
+            .fold((0, self.pruned_issued()), |(count, coins), transaction| {
+                (count + 1, Self::apply_issuance(coins, transaction))
+            });
+
+        ChainStats {
+            height: self.height(),
+            total_transactions,
+            total_coins,
+            difficulty: self.difficulty,
+            current_reward: self.current_reward(),
+            mempool_size: self.pending_count(),
+        }
+    }
+
+    /// Gets the number of coins issued by the chain.
+    ///
+    /// # Returns
+    ///
+    /// The coins created by reward transactions, premine included, in smallest units
+    ///
+    /// # Functionality
+    ///
+    /// - Starts from the balances of the pruning checkpoint, which hold all the
+    ///   coins issued in the pruned blocks
+    /// - Adds the outputs of each reward transaction of the unpruned blocks and
+    ///   subtracts the fees of the other transactions, as fees are paid out of
+    ///   existing coins
+    pub fn total_issued(&self) -> u64 {
+        self.unpruned_transactions()
+            .fold(self.pruned_issued(), Self::apply_issuance)
+    }
+
+    /// Gets the coins issued by the blocks discarded by prune().
+    ///
+    /// # Returns
+    ///
+    /// The sum of the balances of the pruning checkpoint, 0 if the chain was never pruned
+    fn pruned_issued(&self) -> u64 {
+        self.pruned_checkpoint.as_ref().map_or(0, |cp| {
+            cp.balances
+                .values()
+                .fold(0u64, |total, &balance| total.saturating_add(balance))
+        })
+    }
+
+    /// Iterates over the transactions of the blocks not discarded by prune().
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the transactions, in chain order
+    fn unpruned_transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.blocks_from(self.first_unpruned_position())
+            .flat_map(|block| block.transactions.iter())
+    }
+
+    /// Applies the coins a transaction issues to a number of issued coins.
+    ///
+    /// # Parameters
+    ///
+    /// * `coins` - The coins issued before the transaction, in smallest units
+    /// * `transaction` - The transaction to apply
+    ///
+    /// # Returns
+    ///
+    /// The coins plus the outputs of a reward transaction, or minus the fee of any
+    /// other transaction, whose fee is part of the reward of its block
+    fn apply_issuance(coins: u64, transaction: &Transaction) -> u64 {
+        match transaction.from_address {
+            None => coins.saturating_add(transaction.total_amount()),
+            Some(_) => coins.saturating_sub(transaction.fee),
+        }
+    }
+
+    /// Rebuilds the balance index from the chain.
+    ///
+    /// # Functionality
+    ///
+    /// - Starts from the balances of the pruning checkpoint, or an empty index
+    /// - Applies the transactions of each block after it in chain order
+    pub fn rebuild_index(&mut self) {
+        let mut balance_index: HashMap<Address, u64> = self
+            .pruned_checkpoint
+            .iter()
+            .flat_map(|cp| cp.balances.clone())
+            .collect();
+
+        for block in self.blocks_from(self.first_unpruned_position()) {
+            for (address, change) in Self::balance_changes(&block.transactions) {
+                let balance = balance_index.entry(address).or_insert(0);
+                *balance = Self::apply_change(*balance, change);
+            }
+        }
+
+        self.balance_index = balance_index;
+    }
+
+    /// Computes the balance for the provided address by iterating through the blockchain.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The address to get the balance for
+    ///
+    /// # Returns
+    ///  
+    /// The current balance of the address in smallest units
+    ///
+    /// # Functionality
+    ///
+    /// - Initializes the balance to the pruning checkpoint balance, or 0
+    /// - Iterates through each block in the chain after the pruning checkpoint
+    ///   - In each block, iterates through the transactions
+    ///     - Adds the amounts of the outputs to the address to the balance
+    ///     - If the address is the sender, subtract the total amount and fee from the balance
+    /// - Returns the calculated balance
+    pub fn compute_balance_of_address(&self, address: &Address) -> u64 {
+        let pruned_balance = self
+            .pruned_checkpoint
+            .as_ref()
+            .and_then(|cp| cp.balances.get(address).copied())
+            .unwrap_or(0);
+
+        let balance = self.blocks_from(self.first_unpruned_position()).fold(
+            pruned_balance,
+            |acc: u64, block| {
+                block.transactions.iter().fold(acc, |acc, transaction| {
+                    let received = acc.saturating_add(transaction.amount_to(address));
+
+                    if transaction.is_from(address) {
+                        received
+                            .saturating_sub(transaction.total_amount())
+                            .saturating_sub(transaction.fee)
+                    } else {
+                        received
+                    }
+                })
+            },
+        );
+        balance
+    }
+
+    /// Computes the balance for the provided address from the blockchain with checked
+    /// arithmetic.
+    ///
+    /// Unlike compute_balance_of_address(), which saturates, an amount that does not
+    /// fit in a u64 is reported instead of being silently capped.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The address to get the balance for
+    ///
+    /// # Returns
+    ///
+    /// `Result<u64, BlockchainError>`
+    ///
+    /// - `Ok(u64)` with the current balance of the address in smallest units
+    /// - `Err(BlockchainError::Overflow)` if the balance exceeds MAX_AMOUNT at any
+    ///   point, or a transaction spends more than the balance
+    ///
+    /// # Functionality
+    ///
+    /// - Initializes the balance to the pruning checkpoint balance, or 0
+    /// - Applies each transaction after the pruning checkpoint with checked_add() and
+    ///   checked_sub(), as compute_balance_of_address() does
+    pub fn checked_balance_of_address(&self, address: &Address) -> Result<u64, BlockchainError> {
+        let pruned_balance = self
+            .pruned_checkpoint
+            .as_ref()
+            .and_then(|cp| cp.balances.get(address).copied())
+            .unwrap_or(0);
+
+        self.unpruned_transactions()
+            .try_fold(pruned_balance, |balance, transaction| {
+                let received = transaction
+                    .outputs
+                    .iter()
+                    .filter(|output| output.to_address == *address)
+                    .try_fold(balance, |balance, output| {
+                        balance.checked_add(output.amount)
+                    })
+                    .ok_or(BlockchainError::Overflow)?;
+
+                if !transaction.is_from(address) {
+                    return Ok(received);
+                }
+
+                transaction
+                    .checked_total_amount()
+                    .and_then(|amount| amount.checked_add(transaction.fee))
+                    .and_then(|spent| received.checked_sub(spent))
+                    .ok_or(BlockchainError::Overflow)
+            })
+    }
+
+    /// Gets every mined transaction involving the provided address.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The address to get the history for
+    ///
+    /// # Returns
+    ///
+    /// The transactions sent or received by the address, including mining rewards,
+    /// each paired with the index of its block, in chain order
+    pub fn transaction_history(&self, address: &Address) -> Vec<(u64, &Transaction)> {
+        self.blocks()
+            .flat_map(|block| {
+                block
+                    .transactions
+                    .iter()
+                    .map(move |transaction| (block.index, transaction))
+            })
+            .filter(|(_, transaction)| transaction.pays_to(address) || transaction.is_from(address))
+            .collect()
+    }
+
+    /// Finds a mined transaction by its hash.
+    ///
+    /// # Parameters
+    ///
+    /// * `hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// The index of the block containing the transaction and the transaction,
+    /// or None if no mined transaction has the hash
+    ///
+    /// # Functionality
+    ///
+    /// - Iterates through the transactions of each block in chain order
+    /// - Recomputes the hash of each transaction, so mining rewards, which
+    ///   carry no stored hash, can be found too
+    /// - Returns the first transaction whose hash matches
+    pub fn find_transaction(&self, hash: &str) -> Option<(u64, &Transaction)> {
+        self.blocks().find_map(|block| {
+            block
+                .transactions
+                .iter()
+                .find(|transaction| transaction.compute_hash() == hash)
+                .map(|transaction| (block.index, transaction))
+        })
+    }
+
+    /// Checks if a transaction hash is mined.
+    ///
+    /// # Parameters
+    ///
+    /// * `hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// bool - True if a mined transaction has the hash, False otherwise
+    pub fn contains_transaction(&self, hash: &str) -> bool {
+        self.find_transaction(hash).is_some()
+    }
+
+    /// Gets the number of confirmations of a transaction.
+    ///
+    /// # Parameters
+    ///
+    /// * `tx_hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// The number of blocks from the block containing the transaction to the latest
+    /// block, both included, 0 for a pending transaction, or None for an unknown one
+    ///
+    /// # Functionality
+    ///
+    /// - Calls find_transaction() and returns the height minus the block index plus one
+    /// - Otherwise returns Some(0) if a pending transaction has the hash
+    pub fn confirmations(&self, tx_hash: &str) -> Option<u64> {
+        if let Some((index, _)) = self.find_transaction(tx_hash) {
+            return Some(self.height() - index + 1);
+        }
+
+        self.pending_transactions
+            .iter()
+            .any(|transaction| transaction.compute_hash() == tx_hash)
+            .then_some(0)
+    }
+
+    /// Checks if a transaction hash is already pending or mined.
+    ///
+    /// # Parameters
+    ///
+    /// * `hash` - The recomputed hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// bool - True if a pending or mined transaction has the same hash, False otherwise
+    fn is_known_transaction(&self, hash: &str) -> bool {
+        self.pending_transactions
+            .iter()
+            .chain(self.blocks().flat_map(|block| block.transactions.iter()))
+            .any(|transaction| transaction.compute_hash() == hash)
+    }
+
+    /// Gets the lowest nonce the provided address can use for its next transaction.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The sender address
+    ///
+    /// # Returns
+    ///
+    /// The next usable nonce as a u64
+    ///
+    /// # Functionality
+    ///
+    /// - Finds the highest nonce sent by the address across the chain and pending transactions
+    /// - Returns that nonce plus one, the next nonce recorded by the pruning checkpoint
+    ///   if higher, or 0 if the address has never sent a transaction
+    pub fn next_nonce_for(&self, address: &Address) -> u64 {
+        let pruned_nonce = self
+            .pruned_checkpoint
+            .as_ref()
+            .and_then(|cp| cp.next_nonces.get(address).copied())
+            .unwrap_or(0);
+
+        self.blocks()
+            .flat_map(|block| block.transactions.iter())
+            .chain(self.pending_transactions.iter())
+            .filter(|transaction| transaction.is_from(address))
+            .map(|transaction| transaction.nonce + 1)
+            .max()
+            .unwrap_or(0)
+            .max(pruned_nonce)
+    }
+
+    /// Gets the balance the provided address would have once its pending transactions are mined.
+    ///
+    /// Pending incoming amounts are not counted, as they are not guaranteed to be mined.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The address to get the projected balance for
+    ///
+    /// # Returns
+    ///
+    /// The mined balance minus the pending outgoing amounts and fees in smallest units, or 0 if they exceed it
+    pub fn projected_balance(&self, address: &Address) -> u64 {
+        self.get_balance_of_address(address)
+            .saturating_sub(self.get_pending_outgoing_of_address(address))
+    }
+
+    /// Gets the total amount the provided address is sending in pending transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The sender address
+    ///
+    /// # Returns
+    ///
+    /// The sum of the amounts and fees of the pending transactions sent from the address
+    pub fn get_pending_outgoing_of_address(&self, address: &Address) -> u64 {
+        self.pending_transactions
+            .iter()
+            .filter(|transaction| transaction.is_from(address))
+            .fold(0, |total: u64, transaction| {
+                total
+                    .saturating_add(transaction.total_amount())
+                    .saturating_add(transaction.fee)
+            })
+    }
+
+    /// Validates the blockchain by checking:
+    ///
+    /// - The index of each block is one more than the previous block
+    /// - The previous hash of each block matches the previous block hash
+    /// - The timestamp of each block is after the previous block and not too far
+    ///   in the future
+    /// - The Merkle root of each block matches its transactions
+    /// - The hash of each block matches the calculation
+    /// - Each block has valid transactions
+    /// - Each block after the genesis block has at most one reward transaction, not
+    ///   exceeding the reward plus fees
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the blockchain is valid
+    /// - `Err(ValidationError)` with the index of the first invalid block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Calls validate_blocks() with the chain
+    ///   - Checks the previous hash matches the previous block hash
+    ///   - Checks the timestamp is after the previous block timestamp
+    ///   - Checks the timestamp is at most max_future_block_time ahead of now
+    ///   - Checks Merkle root matches recalculation
+    ///   - Checks hash matches recalculation
+    ///   - Checks the reward transaction
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.validate_blocks_from(self.blocks(), 0).map(|_| ())
+    }
+
+    /// Validates a list of blocks with the rules of this blockchain.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocks` - The blocks to validate, starting with a genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<ValidationState, ValidationError>`
+    ///
+    /// - `Ok(ValidationState)` with the state after the last block if the blocks are valid
+    /// - `Err(ValidationError)` with the index of the first invalid block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Calls validate_blocks_from() from the genesis block
+    fn validate_blocks(&self, blocks: &[Block]) -> Result<ValidationState, ValidationError> {
+        self.validate_blocks_from(blocks.iter(), 0)
+    }
+
+    /// Validates the blocks of a list from a position onwards with the rules of this blockchain.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocks` - The blocks in chain order, starting with a genesis block
+    /// * `start` - The position of the first block to validate, the blocks before it are trusted
+    ///
+    /// # Returns
+    ///
+    /// `Result<ValidationState, ValidationError>`
+    ///
+    /// - `Ok(ValidationState)` with the state after the last block if the blocks from
+    ///   the position are valid
+    /// - `Err(ValidationError)` with the index of the first invalid block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Starts from initial_state()
+    /// - Calls validate_block() with each block from the position, the block before it
+    ///   and the validation state after it
+    /// - Advances the validation state past the blocks before the position with
+    ///   advance_state(), without checking them
+    /// - Returns the first failed check, or the final state if all checks pass
+    fn validate_blocks_from<'a>(
+        &self,
+        blocks: impl Iterator<Item = &'a Block>,
+        start: usize,
+    ) -> Result<ValidationState, ValidationError> {
+        let max_timestamp = self.max_block_timestamp();
+        let mut previous_block: Option<&Block> = None;
+        let mut state = self.initial_state();
+
+        for (position, block) in blocks.enumerate() {
+            if position >= start {
+                self.validate_block(
+                    block,
+                    previous_block,
+                    &mut state,
+                    position as u64,
+                    max_timestamp,
+                )?;
+            } else {
+                self.advance_state(&mut state, block, previous_block, position as u64);
+            }
+            previous_block = Some(block);
+        }
+
+        Ok(state)
+    }
+
+    /// Validates blocks one at a time as they are read, with the rules of this blockchain.
+    ///
+    /// Only the previous block is kept in memory, so a chain larger than the
+    /// available memory can be validated straight from its file.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocks` - The blocks, starting with a genesis block, or the errors reading them
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), BlockchainError>`
+    ///
+    /// - `Ok(())` if all the blocks are valid
+    /// - `Err(BlockchainError::InvalidChain)` with the index of the first invalid block
+    ///   and the failed check
+    /// - `Err(BlockchainError::Io)` if a block could not be read
+    ///
+    /// # Functionality
+    ///
+    /// - Reads each block, stopping at the first read error
+    /// - Calls validate_block() with the block, the previously read block and the
+    ///   validation state after it
+    /// - Returns the first failed check, or Ok(()) if all checks pass
+    pub fn validate_stream(
+        &self,
+        blocks: impl Iterator<Item = Result<Block, io::Error>>,
+    ) -> Result<(), BlockchainError> {
+        self.validate_read_blocks(blocks).map(|_| ())
+    }
+
+    /// Validates blocks one at a time as they are read, keeping the state after them.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocks` - The blocks, starting with a genesis block, or the errors reading them
+    ///
+    /// # Returns
+    ///
+    /// `Result<ValidationState, BlockchainError>`
+    ///
+    /// - `Ok(ValidationState)` with the state after the last block if all the blocks
+    ///   are valid
+    /// - `Err(BlockchainError)` as validate_stream() returns
+    fn validate_read_blocks(
+        &self,
+        blocks: impl Iterator<Item = Result<Block, io::Error>>,
+    ) -> Result<ValidationState, BlockchainError> {
+        let max_timestamp = self.max_block_timestamp();
+        let mut previous_block: Option<Block> = None;
+        let mut state = self.initial_state();
+
+        for (position, block) in blocks.enumerate() {
+            let block = block?;
+            self.validate_block(
+                &block,
+                previous_block.as_ref(),
+                &mut state,
+                position as u64,
+                max_timestamp,
+            )?;
+            previous_block = Some(block);
+        }
+
+        Ok(state)
+    }
+
+    /// Gets the latest block timestamp accepted by validation.
+    ///
+    /// # Returns
+    ///
+    /// The current time plus max_future_block_time, or None if it cannot be represented
+    fn max_block_timestamp(&self) -> Option<DateTime<Utc>> {
+        chrono::Duration::from_std(self.max_future_block_time)
+            .ok()
+            .and_then(|tolerance| Utc::now().checked_add_signed(tolerance))
+    }
+
+    /// Gets the validation state before the genesis block.
+    ///
+    /// # Returns
+    ///
+    /// The ValidationState the genesis block is checked against, expecting the
+    /// initial difficulty, or min_difficulty for a chain saved before the initial
+    /// difficulty was recorded
+    fn initial_state(&self) -> ValidationState {
+        let difficulty = match self.initial_difficulty {
+            0 => self.min_difficulty,
+            difficulty => difficulty,
+        };
+
+        ValidationState {
+            difficulty,
+            ..ValidationState::default()
+        }
+    }
+
+    /// Gets the validation state after the latest block.
+    ///
+    /// # Returns
+    ///
+    /// The ValidationState a block extending the latest block is checked against
+    ///
+    /// # Functionality
+    ///
+    /// - Expects the current difficulty of the latest block
+    /// - Records the timestamp of the block before it, if any
+    /// - Records total_issued() as the issued coins
+    /// - Copies the balance index as the balances
+    fn tip_state(&self) -> ValidationState {
+        ValidationState {
+            difficulty: self.difficulty,
+            parent_timestamp: self
+                .height()
+                .checked_sub(1)
+                .and_then(|height| self.block_at(height))
+                .map(|block| block.timestamp),
+            issued: self.total_issued(),
+            balances: self.balance_index.clone(),
+        }
+    }
+
+    /// Computes the difficulty expected of a block from the state before it.
+    ///
+    /// # Parameters
+    ///
+    /// * `state` - The validation state after the previous block
+    /// * `previous_block` - The block before it
+    ///
+    /// # Returns
+    ///
+    /// The difficulty adjust_difficulty() set before mining the block, the difficulty
+    /// of the state for the block after the genesis block
+    fn expected_difficulty(&self, state: &ValidationState, previous_block: &Block) -> u32 {
+        state
+            .parent_timestamp
+            .map_or(state.difficulty, |parent_timestamp| {
+                self.adjusted_difficulty(
+                    state.difficulty,
+                    parent_timestamp,
+                    previous_block.timestamp,
+                )
+            })
+    }
+
+    /// Advances a validation state past a block, without checking the block.
+    ///
+    /// # Parameters
+    ///
+    /// * `state` - The state after the block before it
+    /// * `block` - The block
+    /// * `previous_block` - The block before it, None for the genesis block
+    /// * `index` - The position of the block in its chain
+    ///
+    /// # Functionality
+    ///
+    /// - Sets the difficulty expected of the block with expected_difficulty() and
+    ///   records the timestamp of the block before it
+    /// - Replaces the issued coins and the balances with the ones of the pruning
+    ///   checkpoint at its height, as the transactions of the pruned blocks are gone
+    /// - Otherwise applies the issuance and the balance changes of the block transactions
+    fn advance_state(
+        &self,
+        state: &mut ValidationState,
+        block: &Block,
+        previous_block: Option<&Block>,
+        index: u64,
+    ) {
+        if let Some(previous_block) = previous_block {
+            state.difficulty = self.expected_difficulty(state, previous_block);
+            state.parent_timestamp = Some(previous_block.timestamp);
+        }
+
+        match self.pruned_checkpoint {
+            Some(ref cp) if index == cp.height => {
+                state.issued = self.pruned_issued();
+                state.balances = cp.balances.clone().into_iter().collect();
+            }
+            _ => {
+                state.issued = block
+                    .transactions
+                    .iter()
+                    .fold(state.issued, Self::apply_issuance);
+                for (address, change) in Self::balance_changes(&block.transactions) {
+                    let balance = state.balances.entry(address).or_insert(0);
+                    *balance = Self::apply_change(*balance, change);
+                }
+            }
+        }
+    }
+
+    /// Validates a block with the rules of this blockchain.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block to validate
+    /// * `previous_block` - The block before it, None for the genesis block
+    /// * `state` - The validation state after the previous block, advanced past the
+    ///   block if it is valid
+    /// * `index` - The position of the block in its chain
+    /// * `max_timestamp` - The latest accepted block timestamp, from max_block_timestamp()
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the block is valid
+    /// - `Err(ValidationError)` with the index of the block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Calls check_block()
+    /// - Calls advance_state() once the block passed the checks
+    fn validate_block(
+        &self,
+        block: &Block,
+        previous_block: Option<&Block>,
+        state: &mut ValidationState,
+        index: u64,
+        max_timestamp: Option<DateTime<Utc>>,
+    ) -> Result<(), ValidationError> {
+        self.check_block(block, previous_block, state, index, max_timestamp)?;
+        self.advance_state(state, block, previous_block, index);
+
+        Ok(())
+    }
+
+    /// Checks a block with the rules of this blockchain.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block to check
+    /// * `previous_block` - The block before it, None for the genesis block
+    /// * `state` - The validation state after the previous block
+    /// * `index` - The position of the block in its chain
+    /// * `max_timestamp` - The latest accepted block timestamp, from max_block_timestamp()
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the block is valid
+    /// - `Err(ValidationError)` with the index of the block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Checks the index follows the previous block index
+    /// - Checks the previous hash matches the previous block hash
+    /// - Checks the timestamp is after the previous block timestamp
+    /// - Checks the timestamp is at most max_future_block_time ahead of now
+    /// - Checks a pruned block is covered by the pruning checkpoint, and the block at
+    ///   the pruning checkpoint height has the checkpoint hash
+    /// - Checks Merkle root matches recalculation with the chain hash algorithm,
+    ///   unless the block is pruned
+    /// - Checks hash matches recalculation with the chain hash algorithm and proof of
+    ///   work function
+    /// - Checks hash meets the target of the difficulty recorded on the block
+    /// - Checks the difficulty recorded on the block is at least the difficulty
+    ///   expected_difficulty() replays, for a proof of work chain, except for the
+    ///   genesis block
+    /// - Checks block transaction signatures with Block::verify_signatures() and the
+    ///   transactions are not expired
+    /// - Skips the remaining checks for pruned blocks, whose transactions are gone
+    /// - Checks the block is sealed according to the consensus mode with the balances
+    ///   of the state, except for the genesis block
+    /// - Checks the block transactions are in canonical order if canonical_transaction_order
+    ///   is set, except for the genesis block
+    /// - Checks no transaction hash appears twice in the block, except for the genesis
+    ///   block, whose identical allocations are allowed
+    /// - Checks the block has at most one reward transaction, whose amount does
+    ///   not exceed capped_reward() for the block index and the coins issued before
+    ///   it plus the block fees, except for the genesis block, whose reward
+    ///   transactions are the premine
+    fn check_block(
+        &self,
+        block: &Block,
+        previous_block: Option<&Block>,
+        state: &ValidationState,
+        index: u64,
+        max_timestamp: Option<DateTime<Utc>>,
+    ) -> Result<(), ValidationError> {
+        if let Some(previous_block) = previous_block {
+            if block.index != previous_block.index + 1 {
+                return Err(ValidationError::IndexMismatch { index });
+            }
+
+            if block.previous_hash != previous_block.hash {
+                return Err(ValidationError::PreviousHashMismatch { index });
+            }
+
+            if block.timestamp <= previous_block.timestamp {
+                return Err(ValidationError::BadTimestamp { index });
+            }
+        }
+
+        if max_timestamp.is_some_and(|max_timestamp| block.timestamp > max_timestamp) {
+            return Err(ValidationError::BadTimestamp { index });
+        }
+
+        let pruned_height = self.pruned_checkpoint.as_ref().map(|cp| cp.height);
+
+        if block.pruned && pruned_height.is_none_or(|pruned_height| index > pruned_height) {
+            return Err(ValidationError::PrunedBlock { index });
+        }
+
+        if let Some(ref cp) = self.pruned_checkpoint {
+            if index == cp.height && block.hash != cp.block_hash {
+                return Err(ValidationError::CheckpointMismatch { index });
+            }
+        }
+
+        if !block.pruned
+            && block.merkle_root
+                != Block::compute_merkle_root(&self.hash_algorithm, &block.transactions)
+        {
+            return Err(ValidationError::MerkleRootMismatch { index });
+        }
+
+        let calculated_hash = Block::calculate_hash(
+            &self.block_hasher(),
+            block.index,
+            &block.timestamp,
+            &block.merkle_root,
+            &block.previous_hash,
+            block.nonce,
+        );
+        if block.hash != calculated_hash {
+            return Err(ValidationError::HashMismatch { index });
+        }
+
+        if !Block::meets_target(&block.hash, &Block::difficulty_to_target(block.difficulty)) {
+            return Err(ValidationError::InsufficientWork { index });
+        }
+
+        if let Some(previous_block) = previous_block {
+            if self.consensus_mode == ConsensusMode::ProofOfWork
+                && block.difficulty < self.expected_difficulty(state, previous_block)
+            {
+                return Err(ValidationError::InsufficientWork { index });
+            }
+        }
+
+        if let Err((position, err)) = block.verify_signatures() {
+            warn!(
+                "Block {} transaction {} is invalid: {}",
+                index, position, err
+            );
+            return Err(ValidationError::InvalidTransactions { index });
+        }
+
+        if block.has_expired_transactions() {
+            return Err(ValidationError::ExpiredTransaction { index });
+        }
+
+        if previous_block.is_none() || block.pruned {
+            return Ok(());
+        }
+
+        if !self.consensus_mode.verify_block(block, &state.balances) {
+            return Err(ValidationError::InvalidSeal { index });
+        }
+
+        if self.canonical_transaction_order && !block.has_canonical_order() {
+            return Err(ValidationError::TransactionOrder { index });
+        }
+
+        let mut hashes = HashSet::new();
+        if !block
+            .transactions
+            .iter()
+            .all(|transaction| hashes.insert(transaction.compute_hash()))
+        {
+            return Err(ValidationError::DuplicateTransaction { index });
+        }
+
+        let (coinbases, transfers): (Vec<&Transaction>, Vec<&Transaction>) = block
+            .transactions
+            .iter()
+            .partition(|transaction| transaction.from_address.is_none());
+
+        match coinbases.as_slice() {
+            [] => Ok(()),
+            [coinbase] => {
+                let total_fees = Self::total_fees(transfers.iter().copied());
+
+                let reward = self.capped_reward(block.index, state.issued);
+
+                if coinbase.total_amount() > reward.saturating_add(total_fees) {
+                    return Err(ValidationError::InvalidCoinbase { index });
+                }
+                Ok(())
+            }
+            _ => Err(ValidationError::InvalidCoinbase { index }),
+        }
+    }
+
+    /// Creates a checkpoint of the chain state at a height.
+    ///
+    /// The chain should be validated before, as the checkpoint is trusted by
+    /// validate_from_checkpoint().
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the last block to cover, clamped between the pruning
+    ///   checkpoint height and the chain height
+    ///
+    /// # Returns
+    ///
+    /// The Checkpoint with the hash of the block at the height and the balances and
+    /// next nonces up to it
+    ///
+    /// # Functionality
+    ///
+    /// - Starts from the pruning checkpoint state, or an empty state
+    /// - Applies the balance changes of the blocks up to the height
+    /// - Records the next nonce of each sender of the blocks up to the height
+    /// - Records the height and the hash of the block at the height
+    pub fn create_checkpoint(&self, height: u64) -> Checkpoint {
+        let first_position = self.first_unpruned_position();
+        let height = height
+            .min(self.height())
+            .max(first_position.saturating_sub(1) as u64);
+
+        let (mut balances, mut next_nonces) = match self.pruned_checkpoint {
+            Some(ref cp) => (cp.balances.clone(), cp.next_nonces.clone()),
+            None => (BTreeMap::new(), BTreeMap::new()),
+        };
+
+        for block in self
+            .blocks_from(first_position)
+            .take_while(|block| block.index <= height)
+        {
+            for (address, change) in Self::balance_changes(&block.transactions) {
+                let balance = balances.entry(address).or_insert(0);
+                *balance = Self::apply_change(*balance, change);
+            }
+
+            for transaction in &block.transactions {
+                if let Some(ref from_address) = transaction.from_address {
+                    let next_nonce = next_nonces.entry(*from_address).or_insert(0);
+                    *next_nonce = (*next_nonce).max(transaction.nonce + 1);
+                }
+            }
+        }
+
+        Checkpoint {
+            height,
+            block_hash: self
+                .block_at(height)
+                .map(|block| block.hash.clone())
+                .unwrap_or_default(),
+            balances,
+            next_nonces,
+        }
+    }
+
+    /// Discards the transactions of old blocks, keeping their headers.
+    ///
+    /// Pruned blocks still validate through their hash, links and Merkle root,
+    /// and balances and nonces are kept by the pruning checkpoint. Their
+    /// transactions no longer appear in transaction_history(), find_transaction()
+    /// or merkle_proof().
+    ///
+    /// # Parameters
+    ///
+    /// * `keep_last` - The number of most recent blocks whose transactions are kept
+    ///
+    /// # Functionality
+    ///
+    /// - Does nothing if no block is older than the chain height minus keep_last,
+    ///   or if those blocks are already pruned
+    /// - Calls create_checkpoint() with the last block to prune and stores it in
+    ///   the storage and as the pruning checkpoint, so a reopened storage keeps it
+    /// - Clears the transactions of the blocks up to it and marks them as pruned,
+    ///   keeping their Merkle root
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), io::Error>`
+    ///
+    /// - `Ok(())` if the blocks were pruned
+    /// - `Err(io::Error)` if the storage could not store the checkpoint, the chain
+    ///   being left unchanged, or a pruned block, the blocks after it keeping their
+    ///   transactions
+    pub fn prune(&mut self, keep_last: u64) -> Result<(), io::Error> {
+        let prune_count = self.height().saturating_sub(keep_last) as usize;
+
+        if prune_count <= self.first_unpruned_position() {
+            return Ok(());
+        }
+
+        let checkpoint = self.create_checkpoint(prune_count as u64 - 1);
+        self.storage.0.put_checkpoint(&checkpoint)?;
+        self.pruned_checkpoint = Some(checkpoint);
+
+        let pruned: Vec<Block> = self
+            .blocks()
+            .take(prune_count)
+            .map(|block| Block {
+                transactions: Vec::new(),
+                pruned: true,
+                ..block.clone()
+            })
+            .collect();
+
+        pruned
+            .into_iter()
+            .try_for_each(|block| self.storage.0.put_block(block))
+    }
+
+    /// Gets the position of the first block after the pruning checkpoint.
+    ///
+    /// # Returns
+    ///
+    /// The position as a usize, 0 if the chain was never pruned
+    fn first_unpruned_position(&self) -> usize {
+        self.pruned_checkpoint
+            .as_ref()
+            .map_or(0, |cp| cp.height as usize + 1)
+    }
+
+    /// Gets the balance changes caused by transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `transactions` - The transactions to get the changes of
+    ///
+    /// # Returns
+    ///
+    /// The address and signed amount in smallest units of each change, in transaction order
+    ///
+    /// # Functionality
+    ///
+    /// - Credits the amount of each output to its recipient
+    /// - Debits the total amount and fee of each transaction from its sender
+    fn balance_changes(transactions: &[Transaction]) -> impl Iterator<Item = (Address, i128)> + '_ {
+        transactions.iter().flat_map(|transaction| {
+            let credits = transaction
+                .outputs
+                .iter()
+                .map(|output| (output.to_address, output.amount as i128));
+            let debit = transaction.from_address.map(|from_address| {
+                (
+                    from_address,
+                    -(transaction.total_amount() as i128 + transaction.fee as i128),
+                )
+            });
+
+            credits.chain(debit)
+        })
+    }
+
+    /// Applies a signed balance change to a balance.
+    ///
+    /// # Parameters
+    ///
+    /// * `balance` - The balance in smallest units
+    /// * `change` - The signed change in smallest units
+    ///
+    /// # Returns
+    ///
+    /// The changed balance, saturating at 0 and u64::MAX
+    fn apply_change(balance: u64, change: i128) -> u64 {
+        (balance as i128 + change).clamp(0, u64::MAX as i128) as u64
+    }
+
+    /// Sums the fees of transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `transactions` - The transactions to sum the fees of
+    ///
+    /// # Returns
+    ///
+    /// The total fee in smallest units, saturating at u64::MAX
+    fn total_fees<'a>(transactions: impl Iterator<Item = &'a Transaction>) -> u64 {
+        transactions.fold(0, |total: u64, transaction| {
+            total.saturating_add(transaction.fee)
+        })
+    }
+
+    /// Validates only the blocks after a checkpoint.
+    ///
+    /// # Parameters
+    ///
+    /// * `cp` - The checkpoint, whose blocks are trusted
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ValidationError>`
+    ///
+    /// - `Ok(())` if the chain matches the checkpoint and the blocks after it are valid
+    /// - `Err(ValidationError)` with the index of the first invalid block and the failed check
+    ///
+    /// # Functionality
+    ///
+    /// - Checks the chain has a block at the checkpoint height, whose stored and
+    ///   recalculated hashes both match the checkpoint hash, so a chain rewritten
+    ///   before the checkpoint is rejected first
+    /// - Calls validate_blocks_from() with the blocks after the checkpoint
+    pub fn validate_from_checkpoint(&self, cp: &Checkpoint) -> Result<(), ValidationError> {
+        let mismatch = ValidationError::CheckpointMismatch { index: cp.height };
+
+        let block = self.block_at(cp.height).ok_or(mismatch.clone())?;
+
+        let calculated_hash = Block::calculate_hash(
+            &self.block_hasher(),
+            block.index,
+            &block.timestamp,
+            &block.merkle_root,
+            &block.previous_hash,
+            block.nonce,
+        );
+
+        if block.hash != cp.block_hash || calculated_hash != cp.block_hash {
+            return Err(mismatch);
+        }
+
+        self.validate_blocks_from(self.blocks(), cp.height as usize + 1)
+            .map(|_| ())
+    }
+
+    /// Gets the balance for the provided address, walking only the blocks after a checkpoint.
+    ///
+    /// # Parameters
+    ///
+    /// * `cp` - The checkpoint, whose balances are trusted
+    /// * `address` - The address to get the balance for
+    ///
+    /// # Returns
+    ///
+    /// The current balance of the address in smallest units
+    pub fn get_balance_from_checkpoint(&self, cp: &Checkpoint, address: &Address) -> u64 {
+        self.blocks()
+            .skip(cp.height as usize + 1)
+            .flat_map(|block| Self::balance_changes(&block.transactions))
+            .filter(|&(changed_address, _)| changed_address == *address)
+            .fold(
+                cp.balances.get(address).copied().unwrap_or(0),
+                |balance, (_, change)| Self::apply_change(balance, change),
+            )
+    }
+
+    /// Gets the cumulative work of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The sum of the expected work of each block, as a u128
+    pub fn total_work(&self) -> u128 {
+        Self::work_of(self.blocks())
+    }
+
+    /// Sums the expected work of blocks.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocks` - The blocks to sum the work of
+    ///
+    /// # Returns
+    ///
+    /// The sum of Block::work() of each block, saturated at u128::MAX
+    fn work_of<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> u128 {
+        blocks
+            .into_iter()
+            .fold(0, |work: u128, block| work.saturating_add(block.work()))
+    }
+
+    /// Gets the hash function blocks are hashed with.
+    ///
+    /// # Returns
+    ///
+    /// The BlockHasher combining the chain hash algorithm and proof of work function
+    fn block_hasher(&self) -> BlockHasher {
+        BlockHasher {
+            hash_algorithm: self.hash_algorithm,
+            pow_function: self.pow_function,
+        }
+    }
+
+    /// Checks if the blockchain is valid.
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the blockchain is valid, False otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Calls validate() and checks it succeeded
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Replaces the chain with a competing chain following the most cumulative work rule.
+    ///
+    /// # Parameters
+    ///
+    /// * `incoming` - The blocks of the competing chain, starting with its genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<bool, BlockchainError>`
+    ///
+    /// - `Ok(true)` if the chain was replaced
+    /// - `Ok(false)` if the incoming chain is empty or does not have more work, or as
+    ///   much work and a tip hash that is not smaller
+    /// - `Err(BlockchainError::InvalidChain)` with `GenesisMismatch` if the incoming
+    ///   chain starts with a different genesis block
+    /// - `Err(BlockchainError::InvalidChain)` if the incoming chain wins but is invalid
+    /// - `Err(BlockchainError::Io)` if the storage could not replace the blocks, the
+    ///   chain being left with the blocks stored before the error
+    ///
+    /// # Functionality
+    ///
+    /// - Rejects the incoming chain if its genesis block hash differs, before comparing
+    ///   the work of a chain of another network
+    /// - Keeps the current chain if the incoming chain has less total work, so a
+    ///   shorter chain of harder blocks beats a longer chain of easy ones
+    /// - Breaks a tie between chains with the same total work with wins_tie(), so every
+    ///   node settles on the same chain whichever one it received first
+    /// - Calls validate_blocks() on the incoming chain, replaying the difficulty
+    ///   adjustments from the initial difficulty of this chain
+    /// - Collects the non-reward transactions of the replaced blocks missing from the
+    ///   incoming chain
+    /// - Replaces the chain with the incoming chain, sets the difficulty expected of
+    ///   its latest block and rebuilds the balance index
+    /// - Returns the collected transactions to the pending transactions with
+    ///   restore_pending(), so the transactions already included in the incoming
+    ///   chain, and the ones whose nonce or balance no longer make sense, are dropped
+    /// - Rebuilds the balance index and restores the pending transactions as well if
+    ///   the storage fails, as the stored blocks are then still a valid chain
+    pub fn replace_chain(&mut self, incoming: Vec<Block>) -> Result<bool, BlockchainError> {
+        let Some(incoming_genesis) = incoming.first() else {
+            return Ok(false);
+        };
+
+        if incoming_genesis.hash != self.genesis_hash() {
+            return Err(ValidationError::GenesisMismatch.into());
+        }
+
+        let (incoming_work, work) = (Self::work_of(&incoming), self.total_work());
+        let incoming_tip = incoming.last().expect("The incoming chain is not empty");
+
+        if incoming_work < work
+            || (incoming_work == work && !Self::wins_tie(incoming_tip, self.get_latest_block()))
+        {
+            return Ok(false);
+        }
+
+        let state = self.validate_blocks(&incoming)?;
+
+        let fork_position = self
+            .blocks()
+            .zip(incoming.iter())
+            .take_while(|(block, incoming_block)| block.hash == incoming_block.hash)
+            .count();
+
+        let orphaned_transactions = Self::transfers_of(self.blocks_from(fork_position));
+
+        let stored = self
+            .storage
+            .0
+            .truncate(fork_position as u64)
+            .and_then(|()| {
+                incoming
+                    .into_iter()
+                    .skip(fork_position)
+                    .try_for_each(|block| self.storage.0.put_block(block))
+            });
+        if stored.is_ok() {
+            self.difficulty = state.difficulty;
+        }
+        self.rebuild_index();
+        self.restore_pending(orphaned_transactions);
+        stored?;
+
+        Ok(true)
+    }
+
+    /// Removes the latest blocks of the chain, e.g. to recover from a bad block.
+    ///
+    /// # Parameters
+    ///
+    /// * `n` - The number of blocks to remove
+    ///
+    /// # Returns
+    ///
+    /// `Result<Vec<Block>, BlockchainError>`
+    ///
+    /// - `Ok(Vec<Block>)` with the removed blocks, in chain order
+    /// - `Err(BlockchainError::Rollback)` if n is greater than the height, as the
+    ///   genesis block is never removed, or the rollback would remove pruned blocks
+    /// - `Err(BlockchainError::Io)` if the storage could not remove the blocks, the
+    ///   chain being left with the blocks it did not remove
+    ///
+    /// # Functionality
+    ///
+    /// - Removes the blocks above height `height - n`
+    /// - Rebuilds the balance index
+    /// - Restores the difficulty to the one the next block was mined at before the
+    ///   rollback, the difficulty of the latest remaining block unless only the
+    ///   genesis block remains
+    /// - Returns the non-reward transactions of the removed blocks to
+    ///   pending_transactions followed by the previously pending ones, as
+    ///   replace_chain() does
+    pub fn rollback(&mut self, n: u64) -> Result<Vec<Block>, BlockchainError> {
+        let height = self.height();
+        let Some(new_height) = height.checked_sub(n) else {
+            return Err(BlockchainError::Rollback(format!(
+                "cannot remove {} blocks from a chain of height {}",
+                n, height
+            )));
+        };
+
+        if let Some(cp) = self
+            .pruned_checkpoint
+            .as_ref()
+            .filter(|cp| new_height < cp.height)
+        {
+            return Err(BlockchainError::Rollback(format!(
+                "the blocks up to height {} are pruned",
+                cp.height
+            )));
+        }
+
+        let removed: Vec<Block> = self.blocks_from(new_height as usize + 1).cloned().collect();
+        let orphaned_transactions = Self::transfers_of(removed.iter());
+
+        let truncated = self.storage.0.truncate(new_height + 1);
+        self.rebuild_index();
+
+        if let Err(err) = truncated {
+            self.restore_pending(orphaned_transactions);
+            return Err(err.into());
+        }
+
+        if let Some(first_removed) = removed.first() {
+            self.difficulty = if new_height > 0 {
+                self.get_latest_block().difficulty
+            } else {
+                first_removed.difficulty
+            };
+        }
+
+        self.restore_pending(orphaned_transactions);
+
+        Ok(removed)
+    }
+
+    /// Collects the non-reward transactions of blocks.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocks` - The blocks
+    ///
+    /// # Returns
+    ///
+    /// The transactions with a sender, in chain order
+    fn transfers_of<'a>(blocks: impl Iterator<Item = &'a Block>) -> Vec<Transaction> {
+        blocks
+            .flat_map(|block| block.transactions.iter())
+            .filter(|transaction| transaction.from_address.is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the transactions of removed blocks to the pending transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `orphaned_transactions` - The non-reward transactions of the removed blocks
+    ///
+    /// # Functionality
+    ///
+    /// - Puts the orphaned transactions before the previously pending ones
+    /// - Calls check_transfer() on each against the current chain and keeps only the
+    ///   ones passing, so the transactions still mined, and the ones whose nonce or
+    ///   balance no longer make sense, are dropped
+    fn restore_pending(&mut self, orphaned_transactions: Vec<Transaction>) {
+        let candidates: Vec<Transaction> = orphaned_transactions
+            .into_iter()
+            .chain(std::mem::take(&mut self.pending_transactions))
+            .collect();
+
+        for transaction in candidates {
+            if self.check_transfer(&transaction).is_ok() {
+                self.pending_transactions.push(transaction);
+            }
+        }
+    }
+
+    /// Breaks the tie between the tips of two chains with the same total work.
+    ///
+    /// The rule is that the tip whose hash is numerically smaller wins, reading the
+    /// hashes as big-endian hexadecimal numbers. It depends only on the two tips, so
+    /// nodes applying it converge on the same chain regardless of arrival order.
+    ///
+    /// # Parameters
+    ///
+    /// * `challenger` - The tip of the incoming chain
+    /// * `tip` - The tip of the current chain
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the challenger hash is smaller than the tip hash, False if it is
+    /// equal or greater
+    fn wins_tie(challenger: &Block, tip: &Block) -> bool {
+        let numeric = |hash: &str| {
+            let digits = hash.trim_start_matches('0');
+            (digits.len(), digits.to_ascii_lowercase())
+        };
+
+        numeric(&challenger.hash) < numeric(&tip.hash)
+    }
+
+    /// Adds a block announced by a peer to the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block, extending the chain tip or competing with it
+    ///
+    /// # Returns
+    ///
+    /// `Result<bool, BlockchainError>`
+    ///
+    /// - `Ok(true)` if the block was added to the chain
+    /// - `Ok(false)` if the block neither extends the tip nor wins the tie-break
+    ///   against it, the chain being left unchanged
+    /// - `Err(BlockchainError::InvalidChain)` with the index of the block and the
+    ///   failed check
+    /// - `Err(BlockchainError::Io)` if the storage could not store the block
+    ///
+    /// # Functionality
+    ///
+    /// - Checks a block extending the tip with the rules of validate() against the
+    ///   latest block and the state after it only, the blocks before it were checked
+    ///   when they were added
+    /// - Adds it to the chain, applies its transactions to the balance index and
+    ///   notifies the subscribers, as mine_pending_transactions() does
+    /// - Sets the expected difficulty and drops the pending transactions the block
+    ///   included or made invalid, as restore_pending() does
+    /// - For a block with the same parent as the tip and a smaller hash, checks with
+    ///   verify() that it carries the expected proof of work before calling
+    ///   replace_chain() with it as the tip, and calls notify_subscribers() if the
+    ///   tip was swapped
+    pub fn accept_block(&mut self, block: Block) -> Result<bool, BlockchainError> {
+        let latest_block = self.get_latest_block();
+
+        if block.index == latest_block.index + 1 && block.previous_hash == latest_block.hash {
+            let mut state = self.tip_state();
+            self.validate_block(
+                &block,
+                Some(latest_block),
+                &mut state,
+                self.storage.0.len(),
+                self.max_block_timestamp(),
+            )?;
+
+            self.append_block(block)?;
+            self.difficulty = state.difficulty;
+            self.restore_pending(Vec::new());
+            return Ok(true);
+        }
+
+        let competes_with_tip = block.index == latest_block.index
+            && block.index > 0
+            && block.previous_hash == latest_block.previous_hash
+            && block.hash != latest_block.hash;
+
+        if !competes_with_tip || !Self::wins_tie(&block, latest_block) {
+            return Ok(false);
+        }
+
+        if self.consensus_mode == ConsensusMode::ProofOfWork {
+            let parent = self
+                .block_at(block.index - 1)
+                .expect("The tip is not the genesis block");
+            block.verify(parent, block.difficulty.max(self.difficulty))?;
+        }
+
+        let mut candidate_chain: Vec<Block> =
+            self.blocks().take(block.index as usize).cloned().collect();
+        candidate_chain.push(block);
+
+        if !self.replace_chain(candidate_chain)? {
+            return Ok(false);
+        }
+
+        self.notify_subscribers();
+
+        Ok(true)
+    }
+
+    /// Appends a sealed block to the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block, whose transactions are no longer pending
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), io::Error>`
+    ///
+    /// - `Ok(())` if the block was added to the chain
+    /// - `Err(io::Error)` if the storage could not store the block, the chain
+    ///   being left unchanged
+    ///
+    /// # Functionality
+    ///
+    /// - Clears the received_at time of the block transactions
+    /// - Adds the block to the storage
+    /// - Applies the block transactions to the balance index once the block is stored
+    /// - Calls notify_subscribers() with the block
+    fn append_block(&mut self, mut block: Block) -> Result<(), io::Error> {
+        for transaction in &mut block.transactions {
+            transaction.received_at = None;
+        }
+
+        let changes: Vec<(Address, i128)> = Self::balance_changes(&block.transactions).collect();
+        self.storage.0.put_block(block)?;
+
+        for (address, change) in changes {
+            let balance = self.balance_index.entry(address).or_insert(0);
+            *balance = Self::apply_change(*balance, change);
+        }
+
+        self.notify_subscribers();
+
+        Ok(())
+    }
+
+    /// Notifies the subscribers of the latest block.
+    ///
+    /// # Functionality
+    ///
+    /// - Calls each subscriber with the latest block, in registration order
+    fn notify_subscribers(&self) {
+        let block = self.get_latest_block();
+        for subscriber in &self.subscribers.0 {
+            subscriber(block);
+        }
+    }
+
+    /// Saves the blockchain to a JSON file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file to write
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<()>`
+    ///
+    /// - `Ok(())` if the file was written successfully
+    /// - `Err(io::Error)` if serialization or writing failed
+    ///
+    /// # Functionality
+    ///
+    /// - Serializes the whole blockchain, including difficulty, mining reward
+    ///   and pending transactions, to JSON
+    /// - Writes the JSON to the provided path
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Exports the blocks of the chain as newline-delimited JSON.
+    ///
+    /// # Parameters
+    ///
+    /// * `writer` - The destination, e.g. a file or the standard output
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<()>`
+    ///
+    /// - `Ok(())` if every block was written
+    /// - `Err(io::Error)` if serialization or writing failed
+    ///
+    /// # Functionality
+    ///
+    /// - Writes each block as a single line of JSON, from the genesis block to the
+    ///   latest block
+    /// - Flushes the writer after each block, so a reader following the output sees
+    ///   whole blocks
+    /// - The other chain state, e.g. the pending transactions, is not exported
+    pub fn export_ndjson(&self, mut writer: impl io::Write) -> io::Result<()> {
+        for block in self.blocks() {
+            serde_json::to_writer(&mut writer, block)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Imports a chain from newline-delimited JSON blocks, validating each block
+    /// as it is read.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `reader` - The source, one JSON block per line starting with the genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>` as import_ndjson_with_genesis() returns,
+    /// with `GenesisMismatch` if the first block is not the default genesis block
+    ///
+    /// # Functionality
+    ///
+    /// - Reads the blocks as import_ndjson_with_genesis() does, checking the first
+    ///   block with check_genesis() against the default genesis block
+    pub fn import_ndjson(
+        difficulty: u32,
+        mining_reward: u64,
+        reader: impl io::BufRead,
+    ) -> Result<Blockchain, BlockchainError> {
+        Self::read_ndjson(difficulty, mining_reward, reader, None)
+    }
+
+    /// Imports a chain of a network from newline-delimited JSON blocks, validating
+    /// each block as it is read.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `reader` - The source, one JSON block per line starting with the genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>`
+    ///
+    /// - `Ok(Blockchain)` with the read blocks as its chain
+    /// - `Err(BlockchainError)` if reading or parsing a line failed or a block is
+    ///   invalid, `InvalidChain` with `GenesisMismatch` if the first block does not
+    ///   have the genesis hash and with `IndexMismatch` at index 0 if there is no block
+    ///
+    /// # Functionality
+    ///
+    /// - Skips blank lines
+    /// - Starts the chain from the first block with from_genesis_block()
+    /// - Checks and appends each following block with append_untrusted_block()
+    ///   before reading the next line, as from_blocks() does
+    /// - Rebuilds the balance index
+    pub fn import_ndjson_with_genesis(
+        difficulty: u32,
+        mining_reward: u64,
+        reader: impl io::BufRead,
+        genesis_hash: &str,
+    ) -> Result<Blockchain, BlockchainError> {
+        Self::read_ndjson(difficulty, mining_reward, reader, Some(genesis_hash))
+    }
+
+    /// Reads a chain from newline-delimited JSON blocks, as import_ndjson_with_genesis()
+    /// does.
+    ///
+    /// # Parameters
+    ///
+    /// * `difficulty` - The mining difficulty the chain started with
+    /// * `mining_reward` - The mining reward amount, in smallest units
+    /// * `reader` - The source, one JSON block per line starting with the genesis block
+    /// * `genesis_hash` - The hash of the genesis block of the network, None for the
+    ///   default genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>` as import_ndjson_with_genesis() returns
+    fn read_ndjson(
+        difficulty: u32,
+        mining_reward: u64,
+        reader: impl io::BufRead,
+        genesis_hash: Option<&str>,
+    ) -> Result<Blockchain, BlockchainError> {
+        let mut blockchain: Option<(Blockchain, ValidationState)> = None;
+
+        for line in reader.lines() {
+            let line = line.map_err(BlockchainError::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let block: Block =
+                serde_json::from_str(&line).map_err(BlockchainError::Serialization)?;
+
+            match &mut blockchain {
+                Some((blockchain, state)) => blockchain
+                    .append_untrusted_block(state, block)
+                    .map_err(BlockchainError::InvalidChain)?,
+                None => {
+                    blockchain = Some(
+                        Self::from_genesis_block(difficulty, mining_reward, block, genesis_hash)
+                            .map_err(BlockchainError::InvalidChain)?,
+                    )
+                }
+            }
+        }
+
+        let (mut blockchain, _) = blockchain.ok_or(BlockchainError::InvalidChain(
+            ValidationError::IndexMismatch { index: 0 },
+        ))?;
+        blockchain.rebuild_index();
+
+        Ok(blockchain)
+    }
+
+    /// Loads a blockchain from a JSON file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file to read
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>`
+    ///
+    /// - `Ok(Blockchain)` if the file contains a valid blockchain starting with the
+    ///   default genesis block
+    /// - `Err(BlockchainError)` as load_from_file_with_genesis() returns otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Loads the file as load_from_file_with_genesis() does, checking the genesis
+    ///   block with check_genesis() against the default genesis block
+    pub fn load_from_file(path: &Path) -> Result<Blockchain, BlockchainError> {
+        Self::read_chain_file(path, None)
+    }
+
+    /// Loads a blockchain of a network from a JSON file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file to read
+    /// * `genesis_hash` - The hash of the genesis block of the network, e.g. the
+    ///   genesis_hash() of with_genesis()
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>`
+    ///
+    /// - `Ok(Blockchain)` if the file contains a valid blockchain of the network
+    /// - `Err(BlockchainError::InvalidChain)` with `ValidationError::GenesisMismatch` if
+    ///   the genesis block does not have the genesis hash
+    /// - `Err(BlockchainError)` if reading, parsing or validation failed otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Reads the file contents
+    /// - Deserializes the JSON into a Blockchain
+    /// - Compares the genesis hash before any other check
+    /// - Calls validate() to reject tampered chains
+    /// - Rebuilds the balance index
+    /// - Returns the loaded Blockchain
+    pub fn load_from_file_with_genesis(
+        path: &Path,
+        genesis_hash: &str,
+    ) -> Result<Blockchain, BlockchainError> {
+        Self::read_chain_file(path, Some(genesis_hash))
+    }
+
+    /// Loads a blockchain from a JSON file, as load_from_file_with_genesis() does.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file to read
+    /// * `genesis_hash` - The hash of the genesis block of the network, None for the
+    ///   default genesis block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Blockchain, BlockchainError>` as load_from_file_with_genesis() returns
+    fn read_chain_file(
+        path: &Path,
+        genesis_hash: Option<&str>,
+    ) -> Result<Blockchain, BlockchainError> {
+        let json = fs::read_to_string(path).map_err(BlockchainError::Io)?;
+        let mut blockchain: Blockchain =
+            serde_json::from_str(&json).map_err(BlockchainError::Serialization)?;
+
+        let genesis_block = blockchain
+            .block_at(0)
+            .ok_or(ValidationError::IndexMismatch { index: 0 })?;
+        Self::check_genesis(genesis_block, genesis_hash)?;
+
+        blockchain
+            .validate()
+            .map_err(BlockchainError::InvalidChain)?;
+
+        blockchain.rebuild_index();
+
+        Ok(blockchain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::keypair;
+    use crate::units::{parse_units, COIN};
+
+    use secp256k1::{Message, PublicKey, SecretKey};
+
+    /// Creates a chain crediting 1000 units to an address in its genesis block,
+    /// whose difficulty stays at its minimum.
+    fn funded_chain(address: Address) -> Blockchain {
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(address, 1000)]);
+        blockchain.target_block_time = Duration::ZERO;
+        blockchain
+    }
+
+    /// Mines a block holding transactions that skipped add_transaction().
+    fn mine_unchecked(blockchain: &mut Blockchain, transactions: Vec<Transaction>, miner: Address) {
+        blockchain.pending_transactions.extend(transactions);
+        blockchain
+            .mine_pending_transactions(miner)
+            .expect("Proof of work always seals");
+    }
+
+    #[test]
+    fn block_with_unsigned_transfer_is_invalid() {
+        let (_, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        let mut unsigned = Transaction::new(Some(sender), recipient, 500);
+        unsigned.hash = Some(unsigned.compute_hash());
+        mine_unchecked(&mut blockchain, vec![unsigned], recipient);
+
+        let block = blockchain.get_latest_block();
+        assert!(block.has_valid_transactions().is_err());
+        assert_eq!(
+            blockchain.validate_block(
+                block,
+                blockchain.block_at(0),
+                &mut ValidationState::default(),
+                1,
+                None
+            ),
+            Err(ValidationError::InvalidTransactions { index: 1 })
+        );
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::InvalidTransactions { index: 1 })
+        );
+    }
+
+    #[test]
+    fn block_with_mismatched_transaction_hash_is_invalid() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        let mut tampered = Transaction::new(Some(sender), recipient, 1);
+        tampered.sign_with(&key).unwrap();
+        tampered.outputs[0].amount = 900;
+        mine_unchecked(&mut blockchain, vec![tampered], recipient);
+
+        assert!(blockchain
+            .get_latest_block()
+            .has_valid_transactions()
+            .is_err());
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::InvalidTransactions { index: 1 })
+        );
+        assert_eq!(
+            Blockchain::from_blocks_with_genesis(
+                1,
+                100,
+                blockchain.blocks().cloned().collect(),
+                blockchain.genesis_hash()
+            )
+            .err(),
+            Some(ValidationError::InvalidTransactions { index: 1 })
+        );
+    }
+
+    #[test]
+    fn add_transaction_rejects_amount_changed_after_signing() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        let mut transaction = Transaction::new(Some(sender), recipient, 1);
+        transaction.sign_with(&key).unwrap();
+        transaction.outputs[0].amount = 900;
+
+        assert!(matches!(
+            blockchain.add_transaction(transaction),
+            Err(BlockchainError::Transaction(TransactionError::HashMismatch))
+        ));
+        assert!(blockchain.pending().is_empty());
+    }
+
+    #[test]
+    fn block_signatures_are_verified_together() {
+        let (key, sender) = keypair(1);
+        let (other_key, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        let valid: Vec<Transaction> = (0..3)
+            .map(|nonce| {
+                let mut transaction = Transaction::new(Some(sender), recipient, 10);
+                transaction.nonce = nonce;
+                transaction.sign_with(&key).unwrap();
+                transaction
+            })
+            .collect();
+        mine_unchecked(&mut blockchain, valid, recipient);
+        assert_eq!(
+            blockchain
+                .get_latest_block()
+                .verify_signatures()
+                .map_err(|(position, _)| position),
+            Ok(())
+        );
+        assert_eq!(blockchain.validate(), Ok(()));
+
+        let mut forged = Transaction::new(Some(sender), recipient, 10);
+        forged.nonce = 4;
+        forged.hash = Some(forged.compute_hash());
+        forged.signature = {
+            let mut signed = forged.clone();
+            signed.from_address = Some(recipient);
+            signed.sign_with(&other_key).unwrap();
+            signed.signature
+        };
+        let mut honest = Transaction::new(Some(sender), recipient, 10);
+        honest.nonce = 3;
+        honest.sign_with(&key).unwrap();
+        mine_unchecked(&mut blockchain, vec![honest, forged], recipient);
+
+        let block = blockchain.get_latest_block();
+        assert_eq!(
+            block.verify_signatures().map_err(|(position, _)| position),
+            Err(1)
+        );
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::InvalidTransactions { index: 2 })
+        );
+    }
+
+    #[test]
+    fn submit_block_rejects_a_pending_transfer_included_twice() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        let mut transaction = Transaction::new(Some(sender), recipient, 400);
+        transaction.sign_with(&key).unwrap();
+        blockchain.add_transaction(transaction.clone()).unwrap();
+
+        let mut template = blockchain.block_template(recipient);
+        template.transactions.insert(0, transaction);
+        template.merkle_root =
+            Block::compute_merkle_root(&template.hash_algorithm, &template.transactions);
+        let mut block = template.to_block(0);
+        block.mine_block(template.difficulty);
+
+        assert!(matches!(
+            blockchain.submit_block(block),
+            Err(BlockchainError::InvalidChain(
+                ValidationError::DuplicateTransaction { index: 1 }
+            ))
+        ));
+        assert_eq!(blockchain.height(), 0);
+        assert_eq!(blockchain.get_balance_of_address(&recipient), 0);
+    }
+
+    #[test]
+    fn from_blocks_checks_each_appended_block() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(miner, 1000)]);
+        for _ in 0..5 {
+            mine_unchecked(&mut blockchain, Vec::new(), miner);
+        }
+        let genesis_hash = blockchain.genesis_hash().to_string();
+        let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+        assert_eq!(
+            Blockchain::from_blocks_with_genesis(1, 100, blocks.clone(), &genesis_hash)
+                .map(|chain| chain.height()),
+            Ok(5)
+        );
+
+        let inflated = &mut blocks[3];
+        let coinbase = &mut inflated.transactions[0];
+        coinbase.outputs[0].amount = 1_000;
+        coinbase.hash = Some(coinbase.compute_hash());
+        inflated.merkle_root =
+            Block::compute_merkle_root(&inflated.hash_algorithm, &inflated.transactions);
+        inflated.hash = "f".repeat(64);
+        let difficulty = inflated.difficulty;
+        inflated.mine_block(difficulty);
+
+        assert_eq!(
+            Blockchain::from_blocks_with_genesis(1, 100, blocks, &genesis_hash).err(),
+            Some(ValidationError::InvalidCoinbase { index: 3 })
+        );
+    }
+
+    /// Creates a proof of stake chain with two stakers and the key of the
+    /// staker selected to produce the first block, then the other one.
+    fn staked_chain() -> (Blockchain, SecretKey, SecretKey) {
+        let (first_key, first) = keypair(1);
+        let (second_key, second) = keypair(2);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(first, 500), (second, 500)]);
+
+        let selected = ProofOfStake::new(7, first_key).select_producer(&blockchain);
+        let (producer_key, other_key) = if selected == Some(first.to_string()) {
+            (first_key, second_key)
+        } else {
+            (second_key, first_key)
+        };
+        blockchain.set_consensus(Box::new(ProofOfStake::new(7, producer_key)));
+
+        (blockchain, producer_key, other_key)
+    }
+
+    #[test]
+    fn block_signed_by_a_staker_not_selected_is_invalid() {
+        let (mut blockchain, _, other_key) = staked_chain();
+        let (_, miner) = keypair(3);
+        blockchain.mine_pending_transactions(miner).unwrap();
+        assert_eq!(blockchain.validate(), Ok(()));
+
+        let mut block = blockchain.get_latest_block().clone();
+        let message = Message::from_slice(&hex::decode(&block.hash).unwrap()).unwrap();
+        let signature = secp256k1_context().sign_ecdsa(&message, &other_key);
+        block.producer =
+            Some(PublicKey::from_secret_key(secp256k1_context(), &other_key).to_string());
+        block.signature = Some(hex::encode(signature.serialize_der()));
+        blockchain.storage.put_in_memory(block);
+
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::InvalidSeal { index: 1 })
+        );
+    }
+
+    #[test]
+    fn proof_of_stake_chain_reloads_with_its_consensus_mode() {
+        let (mut blockchain, _, _) = staked_chain();
+        let (_, miner) = keypair(3);
+        blockchain.mine_pending_transactions(miner).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("cryptobeca-stake-{}.json", std::process::id()));
+        blockchain.save_to_file(&path).unwrap();
+        let loaded = Blockchain::load_from_file_with_genesis(&path, blockchain.genesis_hash());
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(
+            loaded.consensus_mode(),
+            ConsensusMode::ProofOfStake { seed: 7 }
+        );
+        assert_eq!(loaded.height(), 1);
+    }
+
+    #[test]
+    fn replace_chain_replays_the_expected_difficulty() {
+        let (_, miner) = keypair(1);
+        let mut honest = Blockchain::new(1, 100);
+        for _ in 0..3 {
+            honest.mine_pending_transactions(miner).unwrap();
+        }
+        let forged: Vec<Block> = honest
+            .blocks()
+            .cloned()
+            .map(|mut block| {
+                block.difficulty = block.difficulty.min(1);
+                block
+            })
+            .collect();
+
+        // The genesis block is old, so the difficulty first rises at block 3
+        let mut node = Blockchain::new(1, 100);
+        assert!(matches!(
+            node.replace_chain(forged),
+            Err(BlockchainError::InvalidChain(
+                ValidationError::InsufficientWork { index: 3 }
+            ))
+        ));
+        assert!(matches!(
+            node.replace_chain(honest.blocks().cloned().collect()),
+            Ok(true)
+        ));
+        assert_eq!(node.difficulty, honest.difficulty);
+        assert_eq!(node.difficulty, 2);
+    }
+
+    #[test]
+    fn accept_block_checks_an_announced_block_against_the_tip() {
+        let (_, miner) = keypair(1);
+        let mut honest = Blockchain::new(1, 100);
+        for _ in 0..3 {
+            honest.mine_pending_transactions(miner).unwrap();
+        }
+        let blocks: Vec<Block> = honest.blocks().cloned().collect();
+        let mut forged = blocks[3].clone();
+        forged.difficulty = 1;
+
+        let mut node = Blockchain::new(1, 100);
+        assert!(matches!(node.accept_block(blocks[2].clone()), Ok(false)));
+        assert!(matches!(node.accept_block(blocks[1].clone()), Ok(true)));
+        assert!(matches!(node.accept_block(blocks[2].clone()), Ok(true)));
+        assert!(matches!(
+            node.accept_block(forged),
+            Err(BlockchainError::InvalidChain(
+                ValidationError::InsufficientWork { index: 3 }
+            ))
+        ));
+        assert!(matches!(node.accept_block(blocks[3].clone()), Ok(true)));
+        assert_eq!(node.difficulty, honest.difficulty);
+        assert_eq!(
+            node.get_balance_of_address(&miner),
+            honest.get_balance_of_address(&miner)
+        );
+    }
+
+    #[test]
+    fn block_below_the_expected_difficulty_is_invalid() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new(1, 100);
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(miner).unwrap();
+        }
+        assert_eq!(blockchain.validate(), Ok(()));
+
+        blockchain.difficulty = 1;
+        blockchain.mine_pending_transactions(miner).unwrap();
+
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::InsufficientWork { index: 4 })
+        );
+        assert_eq!(
+            Blockchain::from_blocks(1, 100, blockchain.blocks().cloned().collect()).err(),
+            Some(ValidationError::InsufficientWork { index: 4 })
+        );
+    }
+
+    #[test]
+    fn reward_past_the_max_supply_is_invalid() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new(1, 100);
+        blockchain.target_block_time = Duration::ZERO;
+        blockchain.max_supply = Some(250);
+        for _ in 0..2 {
+            blockchain.mine_pending_transactions(miner).unwrap();
+        }
+        assert_eq!(blockchain.current_reward(), 50);
+
+        blockchain.max_supply = None;
+        blockchain.mine_pending_transactions(miner).unwrap();
+        blockchain.max_supply = Some(250);
+
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::InvalidCoinbase { index: 3 })
+        );
+    }
+
+    /// Signs a transfer of 10 units with a fee and a nonce.
+    fn signed_transfer(key: &SecretKey, to: Address, fee: u64, nonce: u64) -> Transaction {
+        let from = PublicKey::from_secret_key(secp256k1_context(), key).into();
+        let mut transaction = Transaction::new(Some(from), to, 10);
+        transaction.fee = fee;
+        transaction.nonce = nonce;
+        transaction.sign_with(key).unwrap();
+        transaction
+    }
+
+    /// Signs a transfer of an amount without a fee.
+    fn signed_payment(key: &SecretKey, to: Address, amount: u64, nonce: u64) -> Transaction {
+        let from = PublicKey::from_secret_key(secp256k1_context(), key).into();
+        let mut transaction = Transaction::new(Some(from), to, amount);
+        transaction.nonce = nonce;
+        transaction.sign_with(key).unwrap();
+        transaction
+    }
+
+    /// A storage whose writes all fail, like a full disk.
+    struct ReadOnlyStorage(MemoryStorage);
+
+    impl Storage for ReadOnlyStorage {
+        fn put_block(&mut self, _block: Block) -> Result<(), io::Error> {
+            Err(io::Error::other("the disk is full"))
+        }
+
+        fn get_block(&self, height: u64) -> Result<Option<&Block>, io::Error> {
+            self.0.get_block(height)
+        }
+
+        fn tip(&self) -> Result<Option<&Block>, io::Error> {
+            self.0.tip()
+        }
+
+        fn len(&self) -> u64 {
+            self.0.len()
+        }
+
+        fn truncate(&mut self, _len: u64) -> Result<(), io::Error> {
+            Err(io::Error::other("the disk is full"))
+        }
+
+        fn put_checkpoint(&mut self, _checkpoint: &Checkpoint) -> Result<(), io::Error> {
+            Err(io::Error::other("the disk is full"))
+        }
+
+        fn checkpoint(&self) -> Result<Option<Checkpoint>, io::Error> {
+            self.0.checkpoint()
+        }
+    }
+
+    #[test]
+    fn storage_errors_are_returned_and_leave_the_chain_unchanged() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain.mine_pending_transactions(recipient).unwrap();
+
+        let blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+        blockchain.storage = ChainStorage(Box::new(ReadOnlyStorage(MemoryStorage::from(blocks))));
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 1, 0))
+            .unwrap();
+        let difficulty = blockchain.difficulty;
+
+        assert!(matches!(
+            blockchain.mine_pending_transactions(recipient),
+            Err(ConsensusError::Storage(_))
+        ));
+        assert_eq!(blockchain.height(), 1);
+        assert_eq!(blockchain.pending_transactions.len(), 1);
+        assert_eq!(blockchain.difficulty, difficulty);
+        assert_eq!(blockchain.get_balance_of_address(&sender), 1000);
+
+        assert!(matches!(
+            blockchain.rollback(1),
+            Err(BlockchainError::Io(_))
+        ));
+        assert_eq!(blockchain.height(), 1);
+        assert_eq!(blockchain.pending_transactions.len(), 1);
+    }
+
+    #[test]
+    fn estimate_fee_without_history_is_the_relay_fee() {
+        let (_, sender) = keypair(1);
+        let mut blockchain = funded_chain(sender);
+        blockchain.set_min_relay_fee(5);
+
+        assert_eq!(blockchain.estimate_fee(3), 5);
+    }
+
+    #[test]
+    fn estimate_fee_rises_with_a_full_mempool() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain.max_transactions_per_block = 2;
+
+        for nonce in 0..3 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 50, nonce))
+                .unwrap();
+        }
+
+        assert_eq!(blockchain.estimate_fee(1), 51);
+        assert_eq!(blockchain.estimate_fee(2), 0);
+    }
+
+    #[test]
+    fn estimate_fee_counts_pending_transactions_once() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        for (nonce, fee) in [10, 30, 20].into_iter().enumerate() {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, fee, nonce as u64))
+                .unwrap();
+        }
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 500, 3))
+            .unwrap();
+
+        assert_eq!(blockchain.estimate_fee(1), 20);
+    }
+
+    #[test]
+    fn chain_of_another_network_is_rejected_on_load() {
+        let (_, miner) = keypair(1);
+        let mut other = Blockchain::with_genesis(1, 100, vec![(miner, 1000)]);
+        other.target_block_time = Duration::ZERO;
+        other.mine_pending_transactions(miner).unwrap();
+        let blocks: Vec<Block> = other.blocks().cloned().collect();
+
+        let path =
+            std::env::temp_dir().join(format!("cryptobeca-network-{}.json", std::process::id()));
+        other.save_to_file(&path).unwrap();
+        let loaded = Blockchain::load_from_file(&path);
+        let loaded_with_genesis =
+            Blockchain::load_from_file_with_genesis(&path, other.genesis_hash());
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            loaded,
+            Err(BlockchainError::InvalidChain(
+                ValidationError::GenesisMismatch
+            ))
+        ));
+        assert_eq!(
+            loaded_with_genesis.map(|chain| chain.height()).ok(),
+            Some(1)
+        );
+        assert_eq!(
+            Blockchain::from_blocks(1, 100, blocks.clone()).err(),
+            Some(ValidationError::GenesisMismatch)
+        );
+        assert!(matches!(
+            Blockchain::with_storage(1, 100, Box::new(MemoryStorage::from(blocks))).err(),
+            Some(BlockchainError::InvalidChain(
+                ValidationError::GenesisMismatch
+            ))
+        ));
+        assert!(matches!(
+            Blockchain::with_storage_and_genesis(
+                1,
+                100,
+                Box::new(MemoryStorage::default()),
+                other.genesis_hash()
+            )
+            .err(),
+            Some(BlockchainError::InvalidChain(
+                ValidationError::GenesisMismatch
+            ))
+        ));
+    }
+
+    #[test]
+    fn serialized_chain_round_trips_and_stays_valid() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 1, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 1, 1))
+            .unwrap();
+
+        let json = serde_json::to_string(&blockchain).unwrap();
+        let restored: Blockchain = serde_json::from_str(&json).unwrap();
+
+        assert!(restored.is_valid());
+        assert_eq!(restored.height(), 1);
+        assert_eq!(restored.pending_transactions.len(), 1);
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+    }
+
+    #[test]
+    fn saved_chain_reloads_with_the_same_balances() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for nonce in 0..3 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 1, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(recipient).unwrap();
+        }
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 1, 3))
+            .unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("cryptobeca-save-{}.json", std::process::id()));
+        blockchain.save_to_file(&path).unwrap();
+        let loaded = Blockchain::load_from_file_with_genesis(&path, blockchain.genesis_hash());
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.height(), 3);
+        assert_eq!(loaded.difficulty, blockchain.difficulty);
+        assert_eq!(loaded.mining_reward, blockchain.mining_reward);
+        assert_eq!(loaded.pending_transactions.len(), 1);
+        for address in [sender, recipient] {
+            assert_eq!(
+                loaded.get_balance_of_address(&address),
+                blockchain.get_balance_of_address(&address)
+            );
+        }
+    }
+
+    #[test]
+    fn tampered_file_is_rejected_on_load() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 1, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+
+        let mut json = serde_json::to_value(&blockchain).unwrap();
+        json["chain"][1]["transactions"][0]["outputs"][0]["amount"] = 900.into();
+        let path =
+            std::env::temp_dir().join(format!("cryptobeca-tampered-{}.json", std::process::id()));
+        fs::write(&path, json.to_string()).unwrap();
+        let loaded = Blockchain::load_from_file_with_genesis(&path, blockchain.genesis_hash());
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(BlockchainError::InvalidChain(_))));
+    }
+
+    #[test]
+    fn difficulty_rises_when_blocks_come_fast() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new(1, 100);
+        blockchain.max_difficulty = 6;
+
+        for _ in 0..10 {
+            blockchain.mine_pending_transactions(miner).unwrap();
+        }
+
+        assert_eq!(blockchain.difficulty, 6);
+        assert_eq!(blockchain.get_latest_block().difficulty, 6);
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn difficulty_falls_when_blocks_come_slowly() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new(4, 100);
+        blockchain.min_difficulty = 2;
+        let start = Utc::now() - chrono::Duration::days(1);
+
+        let mut difficulties = Vec::new();
+        for hour in 0..5 {
+            blockchain
+                .mine_pending_transactions_at(miner, start + chrono::Duration::hours(hour))
+                .unwrap();
+            difficulties.push(blockchain.difficulty);
+        }
+
+        assert!(difficulties.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(blockchain.difficulty, 2);
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn transfer_of_the_whole_balance_is_accepted() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        assert!(blockchain
+            .add_transaction(signed_payment(&key, recipient, 1000, 0))
+            .is_ok());
+    }
+
+    #[test]
+    fn transfer_over_the_balance_is_rejected() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        assert!(matches!(
+            blockchain.add_transaction(signed_payment(&key, recipient, 1001, 0)),
+            Err(BlockchainError::Transaction(
+                TransactionError::InsufficientFunds
+            ))
+        ));
+        assert!(blockchain.pending_transactions.is_empty());
+    }
+
+    #[test]
+    fn pending_transfers_overdrawing_together_are_rejected() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 600, 0))
+            .unwrap();
+        assert!(matches!(
+            blockchain.add_transaction(signed_payment(&key, recipient, 500, 1)),
+            Err(BlockchainError::Transaction(
+                TransactionError::InsufficientFunds
+            ))
+        ));
+        assert_eq!(blockchain.pending_transactions.len(), 1);
+    }
+
+    #[test]
+    fn mined_transaction_added_again_is_a_duplicate() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        let transaction = signed_transfer(&key, recipient, 1, 0);
+
+        blockchain.add_transaction(transaction.clone()).unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+
+        assert!(matches!(
+            blockchain.add_transaction(transaction),
+            Err(BlockchainError::Transaction(
+                TransactionError::DuplicateTransaction
+            ))
+        ));
+        assert!(blockchain.pending_transactions.is_empty());
+    }
+
+    #[test]
+    fn pending_transaction_added_again_is_a_duplicate() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        let transaction = signed_transfer(&key, recipient, 1, 0);
+
+        blockchain.add_transaction(transaction.clone()).unwrap();
+
+        assert!(matches!(
+            blockchain.add_transaction(transaction),
+            Err(BlockchainError::Transaction(
+                TransactionError::DuplicateTransaction
+            ))
+        ));
+        assert_eq!(blockchain.pending_transactions.len(), 1);
+    }
+
+    #[test]
+    fn identical_transfers_with_increasing_nonces_are_distinct() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        assert_eq!(blockchain.next_nonce_for(&sender), 0);
+        let first = signed_transfer(&key, recipient, 0, 0);
+        blockchain.add_transaction(first.clone()).unwrap();
+        assert_eq!(blockchain.next_nonce_for(&sender), 1);
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        assert_eq!(blockchain.next_nonce_for(&sender), 1);
+
+        let second = signed_transfer(&key, recipient, 0, 1);
+        assert_ne!(second.compute_hash(), first.compute_hash());
+        assert_ne!(second.signature, first.signature);
+        blockchain.add_transaction(second).unwrap();
+        assert_eq!(blockchain.next_nonce_for(&sender), 2);
+    }
+
+    #[test]
+    fn transfer_reusing_a_nonce_is_rejected() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 10, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 10, 1))
+            .unwrap();
+
+        for nonce in [0, 1] {
+            assert!(matches!(
+                blockchain.add_transaction(signed_payment(&key, recipient, 20, nonce)),
+                Err(BlockchainError::Transaction(TransactionError::InvalidNonce))
+            ));
+        }
+    }
+
+    #[test]
+    fn miner_collects_the_reward_and_the_fees() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let (_, miner) = keypair(3);
+        let mut blockchain = funded_chain(sender);
+
+        for (nonce, fee) in [(0, 1), (1, 2), (2, 3)] {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, fee, nonce))
+                .unwrap();
+        }
+        blockchain.mine_pending_transactions(miner).unwrap();
+
+        assert_eq!(
+            blockchain.get_balance_of_address(&miner),
+            blockchain.mining_reward + 6
+        );
+        assert_eq!(
+            blockchain.get_balance_of_address(&sender),
+            1000 - 3 * 10 - 6
+        );
+    }
+
+    #[test]
+    fn fee_is_covered_by_the_balance_check() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(sender, 10)]);
+
+        assert!(matches!(
+            blockchain.add_transaction(signed_transfer(&key, recipient, 1, 0)),
+            Err(BlockchainError::Transaction(
+                TransactionError::InsufficientFunds
+            ))
+        ));
+        assert!(blockchain
+            .add_transaction(signed_transfer(&key, recipient, 0, 0))
+            .is_ok());
+    }
+
+    #[test]
+    fn mined_blocks_are_numbered_by_height() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = funded_chain(miner);
+        assert_eq!(blockchain.height(), 0);
+
+        for height in 1..=5 {
+            mine_unchecked(&mut blockchain, Vec::new(), miner);
+            assert_eq!(blockchain.height(), height);
+            assert_eq!(blockchain.get_latest_block().index, height);
+        }
+        let indexes: Vec<u64> = blockchain.blocks().map(|block| block.index).collect();
+        assert_eq!(indexes, (0..=5).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn block_with_a_skipped_index_is_invalid() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = funded_chain(miner);
+        for _ in 0..3 {
+            mine_unchecked(&mut blockchain, Vec::new(), miner);
+        }
+        let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+
+        let skipped = &mut blocks[2];
+        skipped.index = 3;
+        skipped.hash = "f".repeat(64);
+        let difficulty = skipped.difficulty;
+        skipped.mine_block(difficulty);
+
+        assert_eq!(
+            blockchain.validate_blocks(&blocks).err(),
+            Some(ValidationError::IndexMismatch { index: 2 })
+        );
+    }
+
+    #[test]
+    fn validate_points_at_the_tampered_block_and_check() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        mine_unchecked(&mut blockchain, Vec::new(), sender);
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 10, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(sender).unwrap();
+        mine_unchecked(&mut blockchain, Vec::new(), sender);
+        assert_eq!(blockchain.validate(), Ok(()));
+        let blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+
+        let mut renonced = blocks.clone();
+        renonced[1].nonce += 1;
+        assert_eq!(
+            blockchain.validate_blocks(&renonced).err(),
+            Some(ValidationError::HashMismatch { index: 1 })
+        );
+
+        let mut inflated = blocks.clone();
+        let block = &mut inflated[2];
+        let transfer = block
+            .transactions
+            .iter_mut()
+            .find(|transaction| transaction.from_address.is_some())
+            .unwrap();
+        transfer.outputs[0].amount = 500;
+        block.merkle_root = Block::compute_merkle_root(&block.hash_algorithm, &block.transactions);
+        block.hash = "f".repeat(64);
+        let difficulty = block.difficulty;
+        block.mine_block(difficulty);
+        assert_eq!(
+            blockchain.validate_blocks(&inflated).err(),
+            Some(ValidationError::InvalidTransactions { index: 2 })
+        );
+
+        let mut relinked = blocks;
+        relinked[3].previous_hash = relinked[1].hash.clone();
+        assert_eq!(
+            blockchain.validate_blocks(&relinked).err(),
+            Some(ValidationError::PreviousHashMismatch { index: 3 })
+        );
+    }
+
+    /// Mines a block with a reward to an address on top of the latest block of a chain.
+    fn block_on_tip(blockchain: &Blockchain, miner: Address, timestamp: DateTime<Utc>) -> Block {
+        let tip = blockchain.get_latest_block();
+        let mut block = Block::new_at(
+            tip.index + 1,
+            vec![Transaction::coinbase(miner, blockchain.current_reward())],
+            tip.hash.clone(),
+            timestamp,
+        );
+        block.mine_block(blockchain.difficulty);
+        block
+    }
+
+    #[test]
+    fn block_older_than_its_parent_is_invalid() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = funded_chain(miner);
+        mine_unchecked(&mut blockchain, Vec::new(), miner);
+        let parent_time = blockchain.get_latest_block().timestamp;
+        let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+
+        let block = block_on_tip(
+            &blockchain,
+            miner,
+            parent_time + chrono::Duration::seconds(1),
+        );
+        blocks.push(block);
+        assert_eq!(blockchain.validate_blocks(&blocks).map(|_| ()), Ok(()));
+
+        let block = block_on_tip(
+            &blockchain,
+            miner,
+            parent_time - chrono::Duration::minutes(1),
+        );
+        *blocks.last_mut().unwrap() = block;
+        assert_eq!(
+            blockchain.validate_blocks(&blocks).err(),
+            Some(ValidationError::BadTimestamp { index: 2 })
+        );
+    }
+
+    #[test]
+    fn block_far_in_the_future_is_invalid() {
+        let (_, miner) = keypair(1);
+        let blockchain = funded_chain(miner);
+        let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+
+        let tolerance = chrono::Duration::from_std(blockchain.max_future_block_time).unwrap();
+        let block = block_on_tip(
+            &blockchain,
+            miner,
+            Utc::now() + tolerance + chrono::Duration::minutes(10),
+        );
+        blocks.push(block);
+        assert_eq!(
+            blockchain.validate_blocks(&blocks).err(),
+            Some(ValidationError::BadTimestamp { index: 1 })
+        );
+    }
+
+    #[test]
+    fn pending_transactions_beyond_the_block_limit_wait_for_the_next_block() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain.max_transactions_per_block = 2;
+
+        for nonce in 0..5 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, nonce + 1, nonce))
+                .unwrap();
+        }
+
+        let mut rounds = 0;
+        while !blockchain.pending_transactions.is_empty() {
+            blockchain.mine_pending_transactions(sender).unwrap();
+            let block = blockchain.get_latest_block();
+            assert!(block.transactions.len() <= 3);
+            assert!(block
+                .transactions
+                .iter()
+                .any(|transaction| transaction.from_address.is_none()));
+            rounds += 1;
+        }
+
+        assert_eq!(rounds, 3);
+        assert_eq!(blockchain.get_balance_of_address(&recipient), 5 * 10);
+    }
+
+    #[test]
+    fn highest_fee_transactions_are_mined_first() {
+        let (low_key, low_sender) = keypair(1);
+        let (high_key, high_sender) = keypair(2);
+        let (_, recipient) = keypair(3);
+        let mut blockchain =
+            Blockchain::with_genesis(1, 100, vec![(low_sender, 1000), (high_sender, 1000)]);
+        blockchain.max_transactions_per_block = 1;
+
+        let low = signed_transfer(&low_key, recipient, 1, 0);
+        let high = signed_transfer(&high_key, recipient, 5, 0);
+        blockchain.add_transaction(low.clone()).unwrap();
+        blockchain.add_transaction(high.clone()).unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+
+        let mined = &blockchain.get_latest_block().transactions;
+        assert!(mined
+            .iter()
+            .any(|transaction| transaction.hash == high.hash));
+        assert!(mined.iter().all(|transaction| transaction.hash != low.hash));
+        assert_eq!(blockchain.pending_transactions.len(), 1);
+    }
+
+    #[test]
+    fn reward_halves_at_each_interval() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = funded_chain(miner);
+        blockchain.halving_interval = 3;
+
+        let mut rewards = Vec::new();
+        for _ in 0..7 {
+            let expected = blockchain.current_reward();
+            mine_unchecked(&mut blockchain, Vec::new(), miner);
+            let block = blockchain.get_latest_block();
+            assert_eq!(block.transactions[0].outputs[0].amount, expected);
+            rewards.push(expected);
+        }
+
+        assert_eq!(rewards, [100, 100, 50, 50, 50, 25, 25]);
+        assert_eq!(blockchain.reward_at(64 * 3), 0);
+    }
+
+    #[test]
+    fn projected_balance_includes_pending_outflows() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(sender, 10)]);
+
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 8, 0))
+            .unwrap();
+        assert_eq!(blockchain.get_balance_of_address(&sender), 10);
+        assert_eq!(blockchain.projected_balance(&sender), 2);
+
+        assert!(matches!(
+            blockchain.add_transaction(signed_payment(&key, recipient, 8, 1)),
+            Err(BlockchainError::Transaction(
+                TransactionError::InsufficientFunds
+            ))
+        ));
+        assert_eq!(blockchain.projected_balance(&sender), 2);
+        assert_eq!(blockchain.projected_balance(&recipient), 0);
+
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        assert_eq!(blockchain.projected_balance(&sender), 2);
+    }
+
+    #[test]
+    fn longer_valid_chain_replaces_the_chain_and_returns_its_transactions() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut node = funded_chain(sender);
+        let transfer = signed_payment(&key, recipient, 10, 0);
+        node.add_transaction(transfer.clone()).unwrap();
+        node.mine_pending_transactions(sender).unwrap();
+
+        let mut competitor = funded_chain(sender);
+        for _ in 0..3 {
+            mine_unchecked(&mut competitor, Vec::new(), recipient);
+        }
+        let shorter: Vec<Block> = competitor.blocks().take(1).cloned().collect();
+        assert!(matches!(node.replace_chain(shorter), Ok(false)));
+
+        assert!(matches!(
+            node.replace_chain(competitor.blocks().cloned().collect()),
+            Ok(true)
+        ));
+        assert_eq!(node.height(), 3);
+        assert_eq!(
+            node.get_latest_block().hash,
+            competitor.get_latest_block().hash
+        );
+        assert_eq!(node.get_balance_of_address(&recipient), 300);
+        assert_eq!(node.pending_transactions.len(), 1);
+        assert_eq!(node.pending_transactions[0].hash, transfer.hash);
+    }
+
+    #[test]
+    fn longer_invalid_chain_is_rejected() {
+        let (_, miner) = keypair(1);
+        let mut node = funded_chain(miner);
+        mine_unchecked(&mut node, Vec::new(), miner);
+        let tip = node.get_latest_block().hash.clone();
+
+        let mut competitor = funded_chain(miner);
+        for _ in 0..3 {
+            mine_unchecked(&mut competitor, Vec::new(), miner);
+        }
+        let mut blocks: Vec<Block> = competitor.blocks().cloned().collect();
+        blocks[2].nonce += 1;
+
+        assert!(matches!(
+            node.replace_chain(blocks),
+            Err(BlockchainError::InvalidChain(
+                ValidationError::HashMismatch { index: 2 }
+            ))
+        ));
+        assert_eq!(node.height(), 1);
+        assert_eq!(node.get_latest_block().hash, tip);
+
+        let other_network = Blockchain::new(1, 100).blocks().cloned().collect();
+        assert!(matches!(
+            node.replace_chain(other_network),
+            Err(BlockchainError::InvalidChain(
+                ValidationError::GenesisMismatch
+            ))
+        ));
+    }
+
+    #[test]
+    fn chain_checked_with_another_hasher_is_invalid() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new_with_algorithm(1, 100, HashAlgorithm::Blake3);
+        for _ in 0..2 {
+            mine_unchecked(&mut blockchain, Vec::new(), miner);
+        }
+        assert!(blockchain.is_valid());
+
+        blockchain.hash_algorithm = HashAlgorithm::Sha3_256;
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::HashMismatch { index: 0 })
+        );
+    }
+
+    /// A logger recording the warnings logged by each test thread.
+    struct CapturingLogger;
+
+    static CAPTURED_LOGS: std::sync::Mutex<Vec<(std::thread::ThreadId, log::Level, String)>> =
+        std::sync::Mutex::new(Vec::new());
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                CAPTURED_LOGS.lock().unwrap().push((
+                    std::thread::current().id(),
+                    record.level(),
+                    record.args().to_string(),
+                ));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Installs the capturing logger once, and gets the messages logged so far
+    /// by the current thread.
+    fn logs_of_this_thread() -> Vec<(log::Level, String)> {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&CapturingLogger).expect("No other logger is installed by the tests");
+            log::set_max_level(log::LevelFilter::Warn);
+        });
+
+        let thread = std::thread::current().id();
+        CAPTURED_LOGS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(logged_by, _, _)| *logged_by == thread)
+            .map(|(_, level, message)| (*level, message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn transaction_failing_validation_logs_a_warning() {
+        let (_, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        assert!(logs_of_this_thread().is_empty());
+
+        let mut unsigned = Transaction::new(Some(sender), recipient, 10);
+        unsigned.hash = Some(unsigned.compute_hash());
+        assert!(matches!(
+            blockchain.add_transaction(unsigned),
+            Err(BlockchainError::Transaction(
+                TransactionError::InvalidTransaction
+            ))
+        ));
+
+        assert_eq!(
+            logs_of_this_thread(),
+            [(
+                log::Level::Warn,
+                "Error validating transaction: Verification failed: No signature in this transaction"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn transaction_history_lists_transfers_and_rewards_in_chain_order() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let (_, miner) = keypair(3);
+        let mut blockchain = funded_chain(sender);
+
+        let first = signed_payment(&key, recipient, 10, 0);
+        blockchain.add_transaction(first.clone()).unwrap();
+        blockchain.mine_pending_transactions(miner).unwrap();
+        let second = signed_payment(&key, recipient, 20, 1);
+        blockchain.add_transaction(second.clone()).unwrap();
+        blockchain.mine_pending_transactions(sender).unwrap();
+
+        let received: Vec<(u64, Option<String>)> = blockchain
+            .transaction_history(&recipient)
+            .into_iter()
+            .map(|(height, transaction)| (height, transaction.hash.clone()))
+            .collect();
+        assert_eq!(
+            received,
+            [(1, first.hash.clone()), (2, second.hash.clone())]
+        );
+
+        let sent = blockchain.transaction_history(&sender);
+        let heights: Vec<u64> = sent.iter().map(|(height, _)| *height).collect();
+        assert_eq!(heights, [0, 1, 2, 2]);
+        assert!(sent[0].1.from_address.is_none());
+        assert_eq!(sent[1].1.hash, first.hash);
+        assert_eq!(
+            sent.iter()
+                .filter(|(height, transaction)| *height == 2 && transaction.from_address.is_none())
+                .count(),
+            1
+        );
+
+        assert!(blockchain.transaction_history(&keypair(4).1).is_empty());
+    }
+
+    #[test]
+    fn mined_transaction_is_found_by_its_hash() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        mine_unchecked(&mut blockchain, Vec::new(), sender);
+
+        let transfer = signed_payment(&key, recipient, 10, 0);
+        let hash = transfer.hash.clone().unwrap();
+        blockchain.add_transaction(transfer).unwrap();
+        assert!(!blockchain.contains_transaction(&hash));
+
+        blockchain.mine_pending_transactions(sender).unwrap();
+        let (height, found) = blockchain.find_transaction(&hash).unwrap();
+        assert_eq!(height, 2);
+        assert_eq!(found.hash.as_deref(), Some(hash.as_str()));
+        assert!(blockchain.contains_transaction(&hash));
+
+        assert!(blockchain.find_transaction(&"ab".repeat(32)).is_none());
+        assert!(!blockchain.contains_transaction(&"ab".repeat(32)));
+    }
+
+    #[test]
+    fn subscribers_are_notified_of_each_mined_block_in_order() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = funded_chain(miner);
+        let notified = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        for subscriber in ["first", "second"] {
+            let notified = std::sync::Arc::clone(&notified);
+            blockchain.subscribe(Box::new(move |block: &Block| {
+                notified.lock().unwrap().push((subscriber, block.index));
+            }));
+        }
+        mine_unchecked(&mut blockchain, Vec::new(), miner);
+        mine_unchecked(&mut blockchain, Vec::new(), miner);
+
+        assert_eq!(
+            *notified.lock().unwrap(),
+            [("first", 1), ("second", 1), ("first", 2), ("second", 2)]
+        );
+    }
+
+    #[test]
+    fn subscribers_are_notified_of_accepted_blocks_but_not_of_a_replaced_chain() {
+        let (_, miner) = keypair(1);
+        let mut honest = Blockchain::new(1, 100);
+        for _ in 0..3 {
+            honest.mine_pending_transactions(miner).unwrap();
+        }
+        let blocks: Vec<Block> = honest.blocks().cloned().collect();
+
+        let mut node = Blockchain::new(1, 100);
+        let notified = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let subscriber_notified = std::sync::Arc::clone(&notified);
+        node.subscribe(Box::new(move |block: &Block| {
+            subscriber_notified.lock().unwrap().push(block.index);
+        }));
+
+        assert!(matches!(node.accept_block(blocks[1].clone()), Ok(true)));
+        assert!(matches!(node.replace_chain(blocks), Ok(true)));
+
+        assert_eq!(node.height(), 3);
+        assert_eq!(*notified.lock().unwrap(), [1]);
+    }
+
+    #[test]
+    fn high_fee_transaction_waits_for_the_lower_nonce_of_its_sender() {
+        let keys: Vec<(SecretKey, Address)> = (1..=3).map(keypair).collect();
+        let (_, recipient) = keypair(4);
+        let mut blockchain = Blockchain::with_genesis(
+            1,
+            100,
+            keys.iter().map(|&(_, address)| (address, 1000)).collect(),
+        );
+        blockchain.max_transactions_per_block = 2;
+
+        let low_nonce = signed_transfer(&keys[0].0, recipient, 1, 0);
+        let high_fee = signed_transfer(&keys[0].0, recipient, 9, 1);
+        let second_best = signed_transfer(&keys[1].0, recipient, 5, 0);
+        let third_best = signed_transfer(&keys[2].0, recipient, 3, 0);
+        for transaction in [&low_nonce, &high_fee, &second_best, &third_best] {
+            blockchain.add_transaction(transaction.clone()).unwrap();
+        }
+
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        let mined_hashes = |blockchain: &Blockchain| -> Vec<Option<String>> {
+            blockchain
+                .get_latest_block()
+                .transactions
+                .iter()
+                .filter(|transaction| transaction.from_address.is_some())
+                .map(|transaction| transaction.hash.clone())
+                .collect()
+        };
+        assert_eq!(
+            mined_hashes(&blockchain),
+            [second_best.hash, third_best.hash]
+        );
+
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        assert_eq!(mined_hashes(&blockchain), [low_nonce.hash, high_fee.hash]);
+        assert!(blockchain.pending_transactions.is_empty());
+    }
+
+    #[test]
+    fn block_rewarding_more_than_the_protocol_allows_is_invalid() {
+        let (_, miner) = keypair(1);
+        let blockchain = funded_chain(miner);
+        let blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+        let tip = blockchain.get_latest_block();
+        let reward = blockchain.current_reward();
+
+        let block_with = |transactions: Vec<Transaction>| {
+            let mut block = Block::new(1, transactions, tip.hash.clone());
+            block.mine_block(blockchain.difficulty);
+            block
+        };
+        let checked = |block: Block| {
+            let mut blocks = blocks.clone();
+            blocks.push(block);
+            blockchain.validate_blocks(&blocks).map(|_| ())
+        };
+
+        assert_eq!(
+            checked(block_with(vec![Transaction::coinbase(miner, reward)])),
+            Ok(())
+        );
+        assert_eq!(
+            checked(block_with(vec![Transaction::coinbase(miner, reward + 1)])),
+            Err(ValidationError::InvalidCoinbase { index: 1 })
+        );
+        assert_eq!(
+            checked(block_with(vec![
+                Transaction::coinbase(miner, reward / 2),
+                Transaction::coinbase(keypair(2).1, reward / 2),
+            ])),
+            Err(ValidationError::InvalidCoinbase { index: 1 })
+        );
+    }
+
+    #[test]
+    fn payout_to_three_recipients_debits_the_sender_once() {
+        let (key, sender) = keypair(1);
+        let recipients: Vec<Address> = (2..=4).map(|seed| keypair(seed).1).collect();
+        let mut blockchain = funded_chain(sender);
+
+        let outputs = recipients
+            .iter()
+            .zip([100, 200, 300])
+            .map(|(&to_address, amount)| TransactionOutput { to_address, amount })
+            .collect();
+        let mut payout = Transaction::with_outputs(Some(sender), outputs);
+        payout.fee = 5;
+        payout.sign_with(&key).unwrap();
+        assert!(payout.is_valid().unwrap());
+
+        blockchain.add_transaction(payout).unwrap();
+        blockchain.mine_pending_transactions(recipients[0]).unwrap();
+
+        let balance = |address: &Address| blockchain.get_balance_of_address(address);
+        assert_eq!(balance(&sender), 1000 - 600 - 5);
+        assert_eq!(balance(&recipients[0]), 100 + blockchain.mining_reward + 5);
+        assert_eq!(balance(&recipients[1]), 200);
+        assert_eq!(balance(&recipients[2]), 300);
+    }
+
+    #[test]
+    fn payout_over_the_balance_in_total_is_rejected() {
+        let (key, sender) = keypair(1);
+        let recipients: Vec<Address> = (2..=3).map(|seed| keypair(seed).1).collect();
+        let mut blockchain = funded_chain(sender);
+
+        let outputs = recipients
+            .iter()
+            .map(|&to_address| TransactionOutput {
+                to_address,
+                amount: 600,
+            })
+            .collect();
+        let mut payout = Transaction::with_outputs(Some(sender), outputs);
+        payout.sign_with(&key).unwrap();
+
+        assert!(matches!(
+            blockchain.add_transaction(payout),
+            Err(BlockchainError::Transaction(
+                TransactionError::InsufficientFunds
+            ))
+        ));
+    }
+
+    /// Replaces the stored blocks of a chain without validating them, like a
+    /// chain file edited on disk.
+    fn overwrite_blocks(blockchain: &mut Blockchain, blocks: Vec<Block>) {
+        blockchain.storage.0.truncate(0).unwrap();
+        for block in blocks {
+            blockchain.storage.put_in_memory(block);
+        }
+        blockchain.rebuild_index();
+    }
+
+    #[test]
+    fn chain_rewritten_before_the_checkpoint_is_rejected() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for nonce in 0..5 {
+            blockchain
+                .add_transaction(signed_payment(&key, recipient, 10, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(sender).unwrap();
+        }
+
+        let cp = blockchain.create_checkpoint(3);
+        assert_eq!(cp.height, 3);
+        assert_eq!(cp.balances[&recipient], 30);
+        assert_eq!(blockchain.validate_from_checkpoint(&cp), Ok(()));
+        assert_eq!(blockchain.get_balance_from_checkpoint(&cp, &recipient), 50);
+
+        let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+        blocks[2].timestamp += chrono::Duration::milliseconds(1);
+        for index in 2..blocks.len() {
+            blocks[index].previous_hash = blocks[index - 1].hash.clone();
+            blocks[index].hash = "f".repeat(64);
+            let difficulty = blocks[index].difficulty;
+            blocks[index].mine_block(difficulty);
+        }
+        overwrite_blocks(&mut blockchain, blocks);
+
+        assert_eq!(
+            blockchain.validate_from_checkpoint(&cp),
+            Err(ValidationError::CheckpointMismatch { index: 3 })
+        );
+    }
+
+    #[test]
+    fn balance_index_matches_a_fold_over_the_chain() {
+        use rand::{Rng, SeedableRng};
+
+        let keys: Vec<(SecretKey, Address)> = (1..=4).map(keypair).collect();
+        let mut blockchain = Blockchain::with_genesis(
+            1,
+            100,
+            keys.iter().map(|&(_, address)| (address, 1000)).collect(),
+        );
+        blockchain.target_block_time = Duration::ZERO;
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(7);
+
+        for _ in 0..40 {
+            let (key, sender) = &keys[rng.gen_range(0..keys.len())];
+            let (_, recipient) = keys[rng.gen_range(0..keys.len())];
+            let affordable = blockchain.projected_balance(sender) / 2;
+            let mut transaction =
+                Transaction::new(Some(*sender), recipient, rng.gen_range(0..=affordable));
+            transaction.fee = rng.gen_range(0..=3);
+            transaction.nonce = blockchain.next_nonce_for(sender);
+            transaction.sign_with(key).unwrap();
+            blockchain.add_transaction(transaction).unwrap();
+
+            if rng.gen_bool(0.3) {
+                let (_, miner) = keys[rng.gen_range(0..keys.len())];
+                blockchain.mine_pending_transactions(miner).unwrap();
+            }
+        }
+        blockchain.mine_pending_transactions(keys[0].1).unwrap();
+
+        let folded = |address: &Address| {
+            blockchain
+                .blocks()
+                .flat_map(|block| &block.transactions)
+                .fold(0i128, |balance, transaction| {
+                    let sent = if transaction.is_from(address) {
+                        transaction.total_amount() as i128 + transaction.fee as i128
+                    } else {
+                        0
+                    };
+                    balance + transaction.amount_to(address) as i128 - sent
+                })
+        };
+        for (_, address) in &keys {
+            assert_eq!(
+                blockchain.get_balance_of_address(address) as i128,
+                folded(address)
+            );
+        }
+
+        let indexed: Vec<u64> = keys
+            .iter()
+            .map(|(_, address)| blockchain.get_balance_of_address(address))
+            .collect();
+        blockchain.balance_index.clear();
+        blockchain.rebuild_index();
+        let rebuilt: Vec<u64> = keys
+            .iter()
+            .map(|(_, address)| blockchain.get_balance_of_address(address))
+            .collect();
+        assert_eq!(rebuilt, indexed);
+    }
+
+    #[test]
+    fn many_small_transfers_credit_an_exact_total() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(sender, 10 * COIN)]);
+        blockchain.target_block_time = Duration::ZERO;
+        let tenth = parse_units("0.1").unwrap();
+
+        for nonce in 0..30 {
+            blockchain
+                .add_transaction(signed_payment(&key, recipient, tenth, nonce))
+                .unwrap();
+        }
+        while !blockchain.pending_transactions.is_empty() {
+            blockchain.mine_pending_transactions(recipient).unwrap();
+        }
+
+        let mined_rewards = blockchain.height() * blockchain.mining_reward;
+        assert_eq!(
+            blockchain.get_balance_of_address(&recipient) - mined_rewards,
+            3 * COIN
+        );
+        assert_eq!(blockchain.get_balance_of_address(&sender), 7 * COIN);
+    }
+
+    #[test]
+    fn getters_expose_the_blocks_and_pending_transactions() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for _ in 0..3 {
+            mine_unchecked(&mut blockchain, Vec::new(), sender);
+        }
+
+        assert_eq!(blockchain.blocks().count() as u64, blockchain.height() + 1);
+        for (height, block) in blockchain.blocks().enumerate() {
+            assert_eq!(
+                blockchain.block_at(height as u64).map(|block| &block.hash),
+                Some(&block.hash)
+            );
+        }
+        assert!(blockchain.block_at(blockchain.height() + 1).is_none());
+
+        assert!(blockchain.pending().is_empty());
+        let transfer = signed_payment(&key, recipient, 10, 0);
+        blockchain.add_transaction(transfer.clone()).unwrap();
+        assert_eq!(blockchain.pending().len(), 1);
+        assert_eq!(blockchain.pending()[0].hash, transfer.hash);
+    }
+
+    #[test]
+    fn premined_genesis_credits_its_allocations_at_once() {
+        let (_, founder) = keypair(1);
+        let (_, other_founder) = keypair(2);
+        let blockchain = Blockchain::with_genesis(
+            1,
+            100,
+            vec![(founder, 50 * COIN), (other_founder, 25 * COIN)],
+        );
+
+        assert_eq!(blockchain.height(), 0);
+        assert_eq!(blockchain.get_balance_of_address(&founder), 50 * COIN);
+        assert_eq!(blockchain.get_balance_of_address(&other_founder), 25 * COIN);
+        assert!(blockchain.is_valid());
+
+        let without_premine = Blockchain::with_genesis(1, 100, Vec::new());
+        assert_ne!(blockchain.genesis_hash(), without_premine.genesis_hash());
+        assert_eq!(without_premine.get_balance_of_address(&founder), 0);
+    }
+
+    #[test]
+    fn from_blocks_rebuilds_a_clean_chain_and_rejects_a_tampered_one() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(sender, 1000)]);
+        for nonce in 0..4 {
+            blockchain
+                .add_transaction(signed_payment(&key, recipient, 10, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(sender).unwrap();
+        }
+        let genesis_hash = blockchain.genesis_hash().to_string();
+        let blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+
+        let rebuilt =
+            Blockchain::from_blocks_with_genesis(1, 100, blocks.clone(), &genesis_hash).unwrap();
+        assert_eq!(rebuilt.height(), 4);
+        assert_eq!(
+            rebuilt.get_latest_block().hash,
+            blockchain.get_latest_block().hash
+        );
+        assert_eq!(rebuilt.get_balance_of_address(&recipient), 40);
+        assert_eq!(rebuilt.next_nonce_for(&sender), 4);
+
+        let mut tampered = blocks.clone();
+        tampered[2].nonce += 1;
+        assert_eq!(
+            Blockchain::from_blocks_with_genesis(1, 100, tampered, &genesis_hash).err(),
+            Some(ValidationError::HashMismatch { index: 2 })
+        );
+        assert_eq!(
+            Blockchain::from_blocks(1, 100, blocks).err(),
+            Some(ValidationError::GenesisMismatch)
+        );
+        assert_eq!(
+            Blockchain::from_blocks_with_genesis(1, 100, Vec::new(), &genesis_hash).err(),
+            Some(ValidationError::IndexMismatch { index: 0 })
+        );
+    }
+
+    #[test]
+    fn pending_transactions_can_be_removed_and_cleared() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        let transfers: Vec<Transaction> = (0..3)
+            .map(|nonce| signed_payment(&key, recipient, 10, nonce))
+            .collect();
+        for transfer in &transfers {
+            blockchain.add_transaction(transfer.clone()).unwrap();
+        }
+        assert_eq!(blockchain.pending_count(), 3);
+
+        let removed = transfers[1].compute_hash();
+        assert!(blockchain.remove_pending(&removed));
+        assert_eq!(blockchain.pending_count(), 2);
+        assert!(!blockchain.remove_pending(&removed));
+        assert_eq!(blockchain.pending_count(), 2);
+
+        blockchain.clear_pending();
+        assert_eq!(blockchain.pending_count(), 0);
+        assert_eq!(blockchain.projected_balance(&sender), 1000);
+    }
+
+    #[test]
+    fn estimated_mining_time_doubles_with_each_unit_of_difficulty() {
+        let mut blockchain = Blockchain::new(10, 100);
+        let estimate = blockchain.estimate_mine_seconds(1_000.0);
+        assert!((estimate - 1024.0 / 1_000.0).abs() < 1e-6);
+
+        blockchain.difficulty = 11;
+        let harder = blockchain.estimate_mine_seconds(1_000.0);
+        assert!((harder / estimate - 2.0).abs() < 1e-6);
+        assert!((blockchain.estimate_mine_seconds(2_000.0) - harder / 2.0).abs() < 1e-9);
+
+        assert!(Block::benchmark_hashrate(1_000) > 0.0);
+    }
+
+    #[test]
+    fn streamed_chain_file_is_validated_block_by_block() {
+        use std::io::BufRead;
+
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for nonce in 0..3 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 1, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(recipient).unwrap();
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("cryptobeca-stream-{}.jsonl", std::process::id()));
+        let stream = |lines: &[String]| {
+            fs::write(&path, lines.join("\n")).unwrap();
+            let reader = io::BufReader::new(fs::File::open(&path).unwrap());
+            blockchain.validate_stream(
+                reader
+                    .lines()
+                    .map(|line| serde_json::from_str::<Block>(&line?).map_err(io::Error::from)),
+            )
+        };
+
+        let mut lines: Vec<String> = blockchain
+            .blocks()
+            .map(|block| serde_json::to_string(block).unwrap())
+            .collect();
+        let clean = stream(&lines);
+
+        let mut tampered: serde_json::Value = serde_json::from_str(&lines[2]).unwrap();
+        tampered["transactions"][0]["outputs"][0]["amount"] = 900.into();
+        lines[2] = tampered.to_string();
+        let streamed = stream(&lines);
+        fs::remove_file(&path).unwrap();
+
+        assert!(clean.is_ok());
+        assert!(matches!(
+            streamed,
+            Err(BlockchainError::InvalidChain(error)) if error.index() == 2
+        ));
+    }
+
+    #[test]
+    fn block_mined_at_a_fixed_timestamp_keeps_it() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new(1, 100);
+        let timestamp = blockchain.get_latest_block().timestamp + chrono::Duration::seconds(30);
+
+        blockchain
+            .mine_pending_transactions_at(miner, timestamp)
+            .unwrap();
+
+        assert_eq!(blockchain.get_latest_block().timestamp, timestamp);
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn batch_reports_only_the_overdrawing_transaction() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        let outcomes = blockchain.add_transactions(vec![
+            signed_payment(&key, recipient, 600, 0),
+            signed_payment(&key, recipient, 600, 1),
+            signed_payment(&key, recipient, 300, 1),
+        ]);
+
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes[0].is_ok());
+        assert!(matches!(
+            outcomes[1],
+            Err(BlockchainError::Transaction(
+                TransactionError::InsufficientFunds
+            ))
+        ));
+        assert!(outcomes[2].is_ok());
+        assert_eq!(blockchain.pending_count(), 2);
+    }
+
+    #[test]
+    fn all_balances_match_the_balance_of_each_address() {
+        let (first_key, first) = keypair(1);
+        let (second_key, second) = keypair(2);
+        let (_, third) = keypair(3);
+        let mut blockchain = funded_chain(first);
+        blockchain
+            .add_transaction(signed_payment(&first_key, second, 300, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(third).unwrap();
+        blockchain
+            .add_transaction(signed_payment(&second_key, third, 100, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(first).unwrap();
+
+        let balances = blockchain.all_balances();
+
+        assert_eq!(balances.len(), 3);
+        for address in [first, second, third] {
+            assert_eq!(
+                balances[&address],
+                blockchain.get_balance_of_address(&address)
+            );
+        }
+        assert!(!balances.contains_key(&keypair(4).1));
+    }
+
+    #[test]
+    fn pruned_chain_stays_valid_with_the_same_balances() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for nonce in 0..4 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 1, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(recipient).unwrap();
+        }
+        let balances = blockchain.all_balances();
+
+        blockchain.prune(2).unwrap();
+
+        let pruned: Vec<bool> = blockchain.blocks().map(|block| block.pruned).collect();
+        assert_eq!(pruned, [true, true, false, false, false]);
+        assert!(blockchain
+            .blocks()
+            .take(2)
+            .all(|block| block.transactions.is_empty()));
+        assert!(blockchain.is_valid());
+        assert_eq!(blockchain.all_balances(), balances);
+        assert_eq!(
+            blockchain.get_balance_of_address(&sender),
+            balances[&sender]
+        );
+    }
+
+    #[test]
+    fn shorter_chain_of_harder_blocks_wins_over_a_longer_easy_one() {
+        let (_, miner) = keypair(1);
+        let mut easy = Blockchain::new(1, 100);
+        let start = Utc::now() - chrono::Duration::days(1);
+        for hour in 0..6 {
+            easy.mine_pending_transactions_at(miner, start + chrono::Duration::hours(hour))
+                .unwrap();
+        }
+        let mut hard = Blockchain::new(1, 100);
+        for _ in 0..4 {
+            hard.mine_pending_transactions(miner).unwrap();
+        }
+        assert!(hard.height() < easy.height());
+        assert!(hard.total_work() > easy.total_work());
+
+        let mut node = Blockchain::new(1, 100);
+        assert!(matches!(
+            node.replace_chain(easy.blocks().cloned().collect()),
+            Ok(true)
+        ));
+        assert!(matches!(
+            node.replace_chain(hard.blocks().cloned().collect()),
+            Ok(true)
+        ));
+        assert!(matches!(
+            node.replace_chain(easy.blocks().cloned().collect()),
+            Ok(false)
+        ));
+        assert_eq!(node.get_latest_block().hash, hard.get_latest_block().hash);
+    }
+
+    #[test]
+    fn memory_hard_chain_validates_only_with_the_same_pow_function() {
+        let (_, miner) = keypair(1);
+        let mut blockchain =
+            Blockchain::new_with_pow_function(1, 100, HashAlgorithm::Sha3_256, PowFunction::Scrypt);
+        blockchain.mine_pending_transactions(miner).unwrap();
+        assert_eq!(
+            blockchain.get_latest_block().pow_function,
+            PowFunction::Scrypt
+        );
+        assert!(blockchain.is_valid());
+
+        blockchain.pow_function = PowFunction::HashAlgorithm;
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::HashMismatch { index: 0 })
+        );
+
+        let mut plain = Blockchain::new(1, 100);
+        plain.mine_pending_transactions(miner).unwrap();
+        plain.pow_function = PowFunction::Scrypt;
+        assert_eq!(
+            plain.validate(),
+            Err(ValidationError::HashMismatch { index: 0 })
+        );
+    }
+
+    #[test]
+    fn simulation_reports_the_outcome_without_adding_the_transaction() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let blockchain = funded_chain(sender);
+
+        assert_eq!(
+            blockchain
+                .simulate(&signed_transfer(&key, recipient, 2, 0))
+                .unwrap(),
+            SimResult {
+                valid: true,
+                balance_after: 988,
+                fee: 2,
+            }
+        );
+        assert!(matches!(
+            blockchain.simulate(&signed_payment(&key, recipient, 2_000, 0)),
+            Err(TransactionError::InsufficientFunds)
+        ));
+
+        let unsigned = Transaction::new(Some(sender), recipient, 10);
+        assert!(!blockchain.simulate(&unsigned).unwrap().valid);
+        assert!(blockchain.pending().is_empty());
+    }
+
+    #[test]
+    fn block_is_filled_up_to_its_byte_budget() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        let transfers: Vec<Transaction> = (0..4)
+            .map(|nonce| signed_transfer(&key, recipient, 1, nonce))
+            .collect();
+        let reward_size = Transaction::coinbase(recipient, 0).serialized_size();
+        let transfer_size = transfers[0].serialized_size();
+
+        blockchain.max_block_bytes = reward_size + transfer_size - 1;
+        assert!(matches!(
+            blockchain.add_transaction(transfers[0].clone()),
+            Err(BlockchainError::Transaction(TransactionError::TooLarge))
+        ));
+
+        blockchain.max_block_bytes = reward_size + 2 * transfer_size + transfer_size / 2;
+        for transfer in transfers {
+            blockchain.add_transaction(transfer).unwrap();
+        }
+        blockchain.mine_pending_transactions(recipient).unwrap();
+
+        let block = blockchain.get_latest_block();
+        assert_eq!(block.transactions.len(), 3);
+        assert!(
+            block
+                .transactions
+                .iter()
+                .map(Transaction::serialized_size)
+                .sum::<usize>()
+                <= blockchain.max_block_bytes
+        );
+        assert_eq!(blockchain.pending_count(), 2);
+    }
+
+    #[test]
+    fn stats_count_the_coins_mined_by_each_block() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 300, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 300, 1))
+            .unwrap();
+
+        let stats = blockchain.stats();
+
+        assert_eq!(stats.height, 2);
+        assert_eq!(stats.total_transactions, 4);
+        assert_eq!(stats.total_coins, 1_000 + 2 * 100);
+        assert_eq!(stats.total_coins, blockchain.total_issued());
+        assert_eq!(stats.difficulty, blockchain.difficulty);
+        assert_eq!(stats.current_reward, 100);
+        assert_eq!(stats.mempool_size, 1);
+        assert!(stats
+            .to_string()
+            .starts_with("Height: 2\nTransactions: 4\n"));
+    }
+
+    #[test]
+    fn difficulty_out_of_bounds_is_clamped() {
+        assert_eq!(Blockchain::new(0, 100).difficulty, MIN_DIFFICULTY);
+        assert_eq!(Blockchain::new(1_000, 100).difficulty, MAX_DIFFICULTY);
+
+        let (_, miner) = keypair(1);
+        for reward in 0..8 {
+            let coinbase = Transaction::coinbase(miner, reward);
+            let mut block = Block::new(1, vec![coinbase], "0".repeat(64));
+            block.mine_block(0);
+            assert_eq!(block.difficulty, MIN_DIFFICULTY);
+            assert!(
+                block.hash.as_str() < "8",
+                "{} has no leading zero bit",
+                block.hash
+            );
+        }
+    }
+
+    #[test]
+    fn reorg_reinjects_only_the_orphaned_transactions_missing_from_the_new_chain() {
+        let (first_key, first) = keypair(1);
+        let (second_key, second) = keypair(2);
+        let (_, recipient) = keypair(3);
+        let two_senders = || {
+            let mut blockchain =
+                Blockchain::with_genesis(1, 100, vec![(first, 1000), (second, 1000)]);
+            blockchain.target_block_time = Duration::ZERO;
+            blockchain
+        };
+        let orphaned = signed_payment(&first_key, recipient, 10, 0);
+        let confirmed = signed_payment(&second_key, recipient, 20, 0);
+
+        let mut node = two_senders();
+        mine_unchecked(&mut node, vec![orphaned.clone(), confirmed.clone()], first);
+        mine_unchecked(&mut node, Vec::new(), first);
+
+        let mut competitor = two_senders();
+        mine_unchecked(&mut competitor, vec![confirmed], second);
+        for _ in 0..2 {
+            mine_unchecked(&mut competitor, Vec::new(), second);
+        }
+
+        assert!(matches!(
+            node.replace_chain(competitor.blocks().cloned().collect()),
+            Ok(true)
+        ));
+        let pending: Vec<&Option<String>> = node
+            .pending()
+            .iter()
+            .map(|transaction| &transaction.hash)
+            .collect();
+        assert_eq!(pending, [&orphaned.hash]);
+        assert_eq!(node.get_balance_of_address(&recipient), 20);
+    }
+
+    #[test]
+    fn reward_is_reduced_then_zeroed_at_the_max_supply() {
+        let (_, miner) = keypair(1);
+        let mut blockchain = Blockchain::new(1, 100);
+        blockchain.target_block_time = Duration::ZERO;
+        blockchain.max_supply = Some(250);
+
+        let mut rewards = Vec::new();
+        for _ in 0..4 {
+            blockchain.mine_pending_transactions(miner).unwrap();
+            rewards.push(blockchain.get_latest_block().transactions[0].total_amount());
+            assert!(blockchain.total_issued() <= 250);
+        }
+
+        assert_eq!(rewards, [100, 100, 50, 0]);
+        assert_eq!(blockchain.total_issued(), 250);
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn stale_pending_transaction_is_evicted_and_a_recent_one_kept() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for nonce in 0..2 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 1, nonce))
+                .unwrap();
+        }
+        assert!(blockchain.pending()[0].received_at.is_some());
+        blockchain.pending_transactions[0].received_at =
+            Some(Utc::now() - chrono::Duration::hours(2));
+
+        let evicted = blockchain.evict_stale(Duration::from_secs(3600));
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].nonce, 0);
+        assert_eq!(blockchain.pending_count(), 1);
+        assert_eq!(blockchain.pending()[0].nonce, 1);
+    }
+
+    #[test]
+    fn headers_recompute_the_block_hashes_and_link_up() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        for nonce in 0..3 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 1, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(recipient).unwrap();
+        }
+
+        let headers = blockchain.headers();
+
+        assert_eq!(headers.len(), 4);
+        for (header, block) in headers.iter().zip(blockchain.blocks()) {
+            assert_eq!(header.calculate_hash(), block.hash);
+            assert_eq!(header.merkle_root, block.merkle_root);
+        }
+        for pair in headers.windows(2) {
+            assert_eq!(pair[1].previous_hash, pair[0].hash);
+            assert_eq!(pair[1].verify(&pair[0]), Ok(()));
+        }
+
+        let mut forged = headers[2].clone();
+        forged.merkle_root = headers[1].merkle_root.clone();
+        assert_eq!(
+            forged.verify(&headers[1]),
+            Err(ValidationError::HashMismatch { index: 2 })
+        );
+    }
+
+    #[test]
+    fn independently_created_chains_share_their_genesis_block() {
+        let first = Blockchain::new(1, 100);
+        std::thread::sleep(Duration::from_millis(5));
+        let second = Blockchain::new(4, 50);
+
+        assert_eq!(first.genesis_hash(), second.genesis_hash());
+        assert_eq!(
+            serde_json::to_vec(first.get_latest_block()).unwrap(),
+            serde_json::to_vec(second.get_latest_block()).unwrap()
+        );
+        assert_eq!(
+            first.get_latest_block().timestamp.timestamp(),
+            GENESIS_TIMESTAMP
+        );
+
+        let timestamp = first.get_latest_block().timestamp + chrono::Duration::days(1);
+        let custom = || Blockchain::with_genesis_time(1, 100, timestamp, "1".repeat(64));
+        assert_eq!(custom().genesis_hash(), custom().genesis_hash());
+        assert_ne!(custom().genesis_hash(), first.genesis_hash());
+    }
+
+    #[test]
+    fn balance_past_the_max_amount_is_an_overflow() {
+        let (_, holder) = keypair(1);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(holder, MAX_AMOUNT - 10)]);
+        assert_eq!(
+            blockchain.checked_balance_of_address(&holder).ok(),
+            Some(MAX_AMOUNT - 10)
+        );
+
+        mine_unchecked(&mut blockchain, Vec::new(), holder);
+
+        assert!(matches!(
+            blockchain.checked_balance_of_address(&holder),
+            Err(BlockchainError::Overflow)
+        ));
+        assert_eq!(blockchain.compute_balance_of_address(&holder), MAX_AMOUNT);
+
+        let (_, recipient) = keypair(2);
+        let outputs = vec![
+            TransactionOutput {
+                to_address: recipient,
+                amount: MAX_AMOUNT,
+            },
+            TransactionOutput {
+                to_address: recipient,
+                amount: 1,
+            },
+        ];
+        assert_eq!(
+            Transaction::with_outputs(Some(holder), outputs).checked_total_amount(),
+            None
+        );
+    }
+
+    #[test]
+    fn transaction_past_its_deadline_is_rejected_and_dropped() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        let with_deadline = |valid_until: u64, nonce: u64| {
+            let mut transaction = Transaction::new(Some(sender), recipient, 10);
+            transaction.valid_until = Some(valid_until);
+            transaction.nonce = nonce;
+            transaction.sign_with(&key).unwrap();
+            transaction
+        };
+
+        assert!(matches!(
+            blockchain.add_transaction(with_deadline(1, 0)),
+            Err(BlockchainError::Transaction(TransactionError::Expired))
+        ));
+        let accepted = with_deadline(2, 0);
+        blockchain.add_transaction(accepted.clone()).unwrap();
+        blockchain.add_transaction(with_deadline(2, 1)).unwrap();
+
+        blockchain.max_transactions_per_block = 1;
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        assert_eq!(blockchain.pending_count(), 1);
+        blockchain.mine_pending_transactions(recipient).unwrap();
+
+        assert!(blockchain.pending().is_empty());
+        assert_eq!(blockchain.get_latest_block().transactions.len(), 1);
+        assert!(blockchain.is_valid());
+        assert!(blockchain
+            .find_transaction(accepted.hash.as_ref().unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn template_solved_outside_the_node_is_accepted() {
+        let (key, sender) = keypair(1);
+        let (_, miner) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain
+            .add_transaction(signed_transfer(&key, miner, 1, 0))
+            .unwrap();
+        blockchain.difficulty = 6;
+
+        let template = blockchain.block_template(miner);
+        assert_eq!(template.transactions.len(), 2);
+        let target = Block::difficulty_to_target(template.difficulty);
+        let hasher = BlockHasher {
+            hash_algorithm: template.hash_algorithm,
+            pow_function: template.pow_function,
+        };
+        let meets_target = |nonce: u64| {
+            let mut header = template.header.clone();
+            header.extend_from_slice(nonce.to_string().as_bytes());
+            hasher.hash(&header) <= target.to_vec()
+        };
+        let nonce = (0..).find(|&nonce| meets_target(nonce)).unwrap();
+        let unsolved = (0..).find(|&nonce| !meets_target(nonce)).unwrap();
+
+        assert!(matches!(
+            blockchain.submit_block(template.to_block(unsolved)),
+            Err(BlockchainError::InvalidChain(_))
+        ));
+        blockchain.submit_block(template.to_block(nonce)).unwrap();
+
+        assert_eq!(blockchain.height(), 1);
+        assert!(blockchain.pending().is_empty());
+        assert_eq!(blockchain.get_balance_of_address(&miner), 111);
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn transaction_with_a_corrupt_signature_is_not_added() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+
+        for signature in ["not hex", "00", &"zz".repeat(65)] {
+            let mut transaction = signed_transfer(&key, recipient, 1, 0);
+            transaction.signature = Some(signature.to_string());
+            assert!(matches!(
+                blockchain.add_transaction(transaction),
+                Err(BlockchainError::Transaction(
+                    TransactionError::InvalidTransaction
+                ))
+            ));
+        }
+
+        assert!(blockchain.pending().is_empty());
+    }
+
+    #[test]
+    fn transaction_below_the_min_relay_fee_is_rejected() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain.set_min_relay_fee(5);
+        assert_eq!(blockchain.min_relay_fee(), 5);
+
+        assert!(matches!(
+            blockchain.add_transaction(signed_transfer(&key, recipient, 4, 0)),
+            Err(BlockchainError::Transaction(TransactionError::FeeTooLow))
+        ));
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 5, 0))
+            .unwrap();
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 6, 1))
+            .unwrap();
+        assert_eq!(blockchain.pending_count(), 2);
+
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn exported_ndjson_imports_to_an_identical_chain() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(sender, 1000)]);
+        for nonce in 0..2 {
+            blockchain
+                .add_transaction(signed_transfer(&key, recipient, 1, nonce))
+                .unwrap();
+            blockchain.mine_pending_transactions(recipient).unwrap();
+        }
+
+        let mut exported = Vec::new();
+        blockchain.export_ndjson(&mut exported).unwrap();
+        assert_eq!(exported.iter().filter(|&&byte| byte == b'\n').count(), 3);
+
+        let imported = Blockchain::import_ndjson_with_genesis(
+            1,
+            100,
+            &exported[..],
+            blockchain.genesis_hash(),
+        )
+        .unwrap();
+
+        let mut reexported = Vec::new();
+        imported.export_ndjson(&mut reexported).unwrap();
+        assert_eq!(reexported, exported);
+        assert!(imported.is_valid());
+        assert_eq!(imported.all_balances(), blockchain.all_balances());
+    }
+
+    #[test]
+    fn nothing_is_mined_without_transactions_when_they_are_required() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain.require_transactions = true;
+        let difficulty = blockchain.difficulty;
+
+        assert!(matches!(
+            blockchain.mine_pending_transactions(recipient),
+            Err(ConsensusError::NoTransactions)
+        ));
+        assert_eq!(blockchain.height(), 0);
+        assert_eq!(blockchain.difficulty, difficulty);
+
+        blockchain
+            .add_transaction(signed_transfer(&key, recipient, 1, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(recipient).unwrap();
+        assert_eq!(blockchain.height(), 1);
+    }
+
+    #[test]
+    fn equal_chains_settle_on_the_smaller_tip_hash_in_any_order() {
+        let (_, sender) = keypair(1);
+        let fork = |seed: u8| {
+            let mut blockchain = funded_chain(sender);
+            for _ in 0..2 {
+                mine_unchecked(&mut blockchain, Vec::new(), keypair(seed).1);
+            }
+            blockchain.blocks().cloned().collect::<Vec<Block>>()
+        };
+        let (first, second) = (fork(2), fork(3));
+        assert_eq!(Blockchain::work_of(&first), Blockchain::work_of(&second));
+        let smaller_tip = first[2]
+            .hash
+            .as_str()
+            .min(second[2].hash.as_str())
+            .to_string();
+
+        for arrivals in [[&first, &second], [&second, &first]] {
+            let mut node = funded_chain(sender);
+            for chain in arrivals {
+                node.replace_chain(chain.clone()).unwrap();
+            }
+            assert_eq!(node.get_latest_block().hash, smaller_tip);
+        }
+    }
+
+    #[test]
+    fn block_with_shuffled_transactions_breaks_the_canonical_order() {
+        let senders: Vec<(SecretKey, Address)> = (1..=3).map(keypair).collect();
+        let (_, miner) = keypair(4);
+        let mut blockchain = Blockchain::with_genesis(
+            1,
+            100,
+            senders
+                .iter()
+                .map(|&(_, address)| (address, 1000))
+                .collect(),
+        );
+        blockchain.target_block_time = Duration::ZERO;
+        blockchain.canonical_transaction_order = true;
+        for ((key, _), fee) in senders.iter().zip([1, 3, 2]) {
+            blockchain
+                .add_transaction(signed_transfer(key, miner, fee, 0))
+                .unwrap();
+        }
+        blockchain.mine_pending_transactions(miner).unwrap();
+
+        let block = blockchain.get_latest_block();
+        let fees: Vec<u64> = block
+            .transactions
+            .iter()
+            .map(|transaction| transaction.fee)
+            .collect();
+        assert_eq!(fees, [3, 2, 1, 0]);
+        assert!(block.transactions[3].from_address.is_none());
+        assert_eq!(blockchain.validate(), Ok(()));
+
+        let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+        let shuffled = &mut blocks[1];
+        shuffled.transactions.swap(0, 2);
+        shuffled.merkle_root =
+            Block::compute_merkle_root(&shuffled.hash_algorithm, &shuffled.transactions);
+        shuffled.hash = "f".repeat(64);
+        let difficulty = shuffled.difficulty;
+        shuffled.mine_block(difficulty);
+        overwrite_blocks(&mut blockchain, blocks);
+
+        assert_eq!(
+            blockchain.validate(),
+            Err(ValidationError::TransactionOrder { index: 1 })
+        );
+    }
+
+    #[test]
+    fn mined_transaction_gains_a_confirmation_per_block() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        let transfer = signed_transfer(&key, recipient, 1, 0);
+        let hash = transfer.compute_hash();
+        assert_eq!(blockchain.confirmations(&hash), None);
+
+        blockchain.add_transaction(transfer).unwrap();
+        assert_eq!(blockchain.confirmations(&hash), Some(0));
+
+        for confirmations in 1..=3 {
+            blockchain.mine_pending_transactions(recipient).unwrap();
+            assert_eq!(blockchain.confirmations(&hash), Some(confirmations));
+        }
+    }
+
+    #[test]
+    fn rolling_back_two_blocks_restores_the_earlier_state() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut blockchain = funded_chain(sender);
+        blockchain
+            .add_transaction(signed_payment(&key, recipient, 100, 0))
+            .unwrap();
+        blockchain.mine_pending_transactions(sender).unwrap();
+        let balances = blockchain.all_balances();
+        let tip = blockchain.get_latest_block().hash.clone();
+
+        let rolled_back: Vec<Transaction> = (1..=2)
+            .map(|nonce| signed_payment(&key, recipient, 100, nonce))
+            .collect();
+        for transfer in &rolled_back {
+            blockchain.add_transaction(transfer.clone()).unwrap();
+            blockchain.mine_pending_transactions(sender).unwrap();
+        }
+        let pending = signed_payment(&key, recipient, 100, 3);
+        blockchain.add_transaction(pending.clone()).unwrap();
+
+        let removed = blockchain.rollback(2).unwrap();
+
+        assert_eq!(
+            removed.iter().map(|block| block.index).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(blockchain.height(), 1);
+        assert_eq!(blockchain.get_latest_block().hash, tip);
+        assert_eq!(blockchain.all_balances(), balances);
+        let pending_hashes: Vec<&Option<String>> = blockchain
+            .pending()
+            .iter()
+            .map(|transaction| &transaction.hash)
+            .collect();
+        assert_eq!(
+            pending_hashes,
+            [&rolled_back[0].hash, &rolled_back[1].hash, &pending.hash]
+        );
+        assert!(matches!(
+            blockchain.rollback(2),
+            Err(BlockchainError::Rollback(_))
+        ));
+        assert_eq!(blockchain.height(), 1);
+    }
+
+    #[test]
+    fn miner_distribution_counts_the_blocks_of_each_reward_address() {
+        let (sender_key, sender) = keypair(1);
+        let (_, first_miner) = keypair(2);
+        let (_, second_miner) = keypair(3);
+        let mut blockchain = funded_chain(sender);
+
+        mine_unchecked(
+            &mut blockchain,
+            vec![signed_transfer(&sender_key, second_miner, 1, 0)],
+            first_miner,
+        );
+        for _ in 0..2 {
+            mine_unchecked(&mut blockchain, Vec::new(), first_miner);
+        }
+        mine_unchecked(&mut blockchain, Vec::new(), second_miner);
+
+        assert_eq!(
+            blockchain.miner_distribution(),
+            HashMap::from([(first_miner.to_string(), 3), (second_miner.to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn oversized_memo_is_rejected_and_a_memo_leaves_balances_alone() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let (_, miner) = keypair(3);
+        let mut blockchain = funded_chain(sender);
+
+        let mut oversized = Transaction::new(Some(sender), recipient, 10);
+        oversized.memo = Some(vec![0; MAX_MEMO_BYTES + 1]);
+        oversized.sign_with(&key).unwrap();
+        assert!(matches!(
+            blockchain.add_transaction(oversized),
+            Err(BlockchainError::Transaction(TransactionError::MemoTooLarge))
+        ));
+        assert!(blockchain.pending().is_empty());
+
+        let mut with_memo = Transaction::new(Some(sender), recipient, 10);
+        with_memo.memo = Some(vec![0; MAX_MEMO_BYTES]);
+        with_memo.sign_with(&key).unwrap();
+        blockchain.add_transaction(with_memo).unwrap();
+        blockchain.mine_pending_transactions(miner).unwrap();
+
+        assert_eq!(blockchain.get_balance_of_address(&sender), 990);
+        assert_eq!(blockchain.get_balance_of_address(&recipient), 10);
     }
 }
@@ -0,0 +1,438 @@
+use crate::address::*;
+use crate::block::*;
+use crate::blockchain::*;
+use crate::transaction::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use secp256k1::{ecdsa::Signature, Message, PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Consensus trait.
+///
+/// Represents the rules deciding who may append a block to the chain and how
+/// the block proves it.
+pub trait Consensus {
+    /// Seals a freshly assembled block so it can be appended to the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `blockchain` - The chain the block extends
+    /// * `block` - The block to seal, with its transactions and previous hash set
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), ConsensusError>`
+    ///
+    /// - `Ok(())` if the block was sealed
+    /// - `Err(ConsensusError)` if this node may not produce the block
+    fn produce_block(
+        &self,
+        blockchain: &Blockchain,
+        block: &mut Block,
+    ) -> Result<(), ConsensusError>;
+
+    /// Checks a block carries a valid seal.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block to check
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the block is sealed according to the consensus, False otherwise
+    fn verify_block(&self, block: &Block) -> bool;
+
+    /// Gets the mode of the consensus, saved with the chain.
+    ///
+    /// # Returns
+    ///
+    /// The ConsensusMode the blocks sealed by this consensus are verified with
+    fn mode(&self) -> ConsensusMode;
+}
+
+/// ConsensusMode enum.
+///
+/// Represents the consensus a chain is sealed with. It is saved with the chain,
+/// unlike the Consensus holding the keys of a node, so a reloaded chain is
+/// verified with the rules its blocks were sealed with.
+///
+/// # Variants
+///
+/// * `ProofOfWork` - The blocks are mined, the default.
+/// * `ProofOfStake` - The blocks are signed by the producer select_staker() draws with the seed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsensusMode {
+    #[default]
+    ProofOfWork,
+    ProofOfStake {
+        seed: u64,
+    },
+}
+
+impl ConsensusMode {
+    /// Checks a block carries a valid seal for the chain it extends.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block to check
+    /// * `balances` - The balance of every address before the block
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the block is sealed according to the mode, False otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Calls ProofOfWork::verify_block() for proof of work
+    /// - For proof of stake, checks the producer of the block is the address
+    ///   select_staker() draws from the balances and the previous hash, and the
+    ///   block is signed by the producer
+    pub fn verify_block(&self, block: &Block, balances: &HashMap<Address, u64>) -> bool {
+        match *self {
+            ConsensusMode::ProofOfWork => ProofOfWork.verify_block(block),
+            ConsensusMode::ProofOfStake { seed } => {
+                block.producer.is_some()
+                    && select_staker(seed, balances, &block.previous_hash) == block.producer
+                    && is_signed_by_producer(block)
+            }
+        }
+    }
+}
+
+/// ConsensusError enum.
+///
+/// Represents the reasons a block could not be produced or added to the chain.
+///
+/// # Variants
+///
+/// * `NoStake` - Returned when no address holds coins to stake.
+/// * `NotSelected` - Returned when another staker was selected to produce the block.
+/// * `Signing` - Returned when the block could not be signed.
+/// * `NoTransactions` - Returned when the chain requires transactions and none is pending.
+/// * `Storage` - Returned when the sealed block could not be stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusError {
+    NoStake,
+    NotSelected { producer: String },
+    Signing(String),
+    NoTransactions,
+    Storage(String),
+}
+
+impl fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusError::NoStake => write!(f, "No address holds coins to stake"),
+            ConsensusError::NotSelected { producer } => {
+                write!(f, "The block must be produced by {}", producer)
+            }
+            ConsensusError::Signing(msg) => write!(f, "Signing failed: {}", msg),
+            ConsensusError::NoTransactions => write!(f, "No pending transaction to mine"),
+            ConsensusError::Storage(msg) => write!(f, "Storing the block failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConsensusError {}
+
+/// ProofOfWork struct.
+///
+/// The default consensus, sealing blocks by grinding a nonce until the block
+/// hash meets the chain difficulty.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProofOfWork;
+
+impl Consensus for ProofOfWork {
+    fn produce_block(
+        &self,
+        blockchain: &Blockchain,
+        block: &mut Block,
+    ) -> Result<(), ConsensusError> {
+        block.mine_block(blockchain.difficulty);
+        Ok(())
+    }
+
+    /// Checks the block was mined at a difficulty of at least MIN_DIFFICULTY, the hash
+    /// meeting the target of the difficulty is checked by the blockchain.
+    fn verify_block(&self, block: &Block) -> bool {
+        block.difficulty >= MIN_DIFFICULTY
+    }
+
+    fn mode(&self) -> ConsensusMode {
+        ConsensusMode::ProofOfWork
+    }
+}
+
+/// ProofOfStake struct.
+///
+/// A consensus selecting the producer of each block among the addresses
+/// holding coins, weighted by their balance. The producer signs the block
+/// hash instead of grinding a nonce.
+///
+/// # Fields
+///
+/// * `seed` - The seed of the producer selection, shared by all the nodes of the chain
+/// * `signing_key` - The private key this node signs its blocks with
+/// * `address` - The address staked by this node, derived from the signing key
+pub struct ProofOfStake {
+    pub seed: u64,
+    signing_key: SecretKey,
+    address: String,
+}
+
+impl fmt::Debug for ProofOfStake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofOfStake")
+            .field("seed", &self.seed)
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProofOfStake {
+    /// Creates a new ProofOfStake instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `seed` - The seed of the producer selection
+    /// * `signing_key` - The private key this node signs its blocks with
+    ///
+    /// # Returns
+    ///
+    /// A new ProofOfStake instance staking the compressed public key of the signing key
+    pub fn new(seed: u64, signing_key: SecretKey) -> Self {
+        let address = PublicKey::from_secret_key(secp256k1_context(), &signing_key).to_string();
+
+        Self {
+            seed,
+            signing_key,
+            address,
+        }
+    }
+
+    /// Gets the address staked by this node.
+    ///
+    /// # Returns
+    ///
+    /// The hex encoded compressed public key of the signing key
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Selects the producer of the next block.
+    ///
+    /// # Parameters
+    ///
+    /// * `blockchain` - The chain the next block extends
+    ///
+    /// # Returns
+    ///
+    /// The address selected to produce the next block, or None if no address holds coins
+    ///
+    /// # Functionality
+    ///
+    /// - Calls select_staker() with the seed, the balances of the chain and the
+    ///   latest block hash
+    pub fn select_producer(&self, blockchain: &Blockchain) -> Option<String> {
+        select_staker(
+            self.seed,
+            &blockchain.all_balances(),
+            &blockchain.get_latest_block().hash,
+        )
+    }
+}
+
+impl Consensus for ProofOfStake {
+    /// Seals the block with the signature of this node.
+    ///
+    /// # Functionality
+    ///
+    /// - Calls select_producer() and checks this node was selected
+    /// - Signs the block hash with the signing key
+    /// - Sets the producer and the hex encoded DER signature on the block
+    fn produce_block(
+        &self,
+        blockchain: &Blockchain,
+        block: &mut Block,
+    ) -> Result<(), ConsensusError> {
+        let producer = self
+            .select_producer(blockchain)
+            .ok_or(ConsensusError::NoStake)?;
+
+        if producer != self.address {
+            return Err(ConsensusError::NotSelected { producer });
+        }
+
+        let message = block_message(block)
+            .ok_or_else(|| ConsensusError::Signing("Invalid block hash".to_string()))?;
+        let signature = secp256k1_context().sign_ecdsa(&message, &self.signing_key);
+
+        block.producer = Some(producer);
+        block.signature = Some(hex::encode(signature.serialize_der()));
+
+        Ok(())
+    }
+
+    /// Checks the block is signed by its producer.
+    ///
+    /// Whether the producer was selected depends on the chain, the blockchain
+    /// checks it with ConsensusMode::verify_block().
+    ///
+    /// # Functionality
+    ///
+    /// - Calls is_signed_by_producer()
+    fn verify_block(&self, block: &Block) -> bool {
+        is_signed_by_producer(block)
+    }
+
+    fn mode(&self) -> ConsensusMode {
+        ConsensusMode::ProofOfStake { seed: self.seed }
+    }
+}
+
+/// Selects the producer of a block among the addresses holding coins.
+///
+/// # Parameters
+///
+/// * `seed` - The seed of the producer selection, shared by all the nodes of the chain
+/// * `balances` - The balance of every address before the block
+/// * `previous_hash` - The hash of the block the block extends
+///
+/// # Returns
+///
+/// The address selected to produce the block, or None if no address holds coins
+///
+/// # Functionality
+///
+/// - Sorts the non-zero balances by address, so every node draws from the same list
+/// - Seeds a RNG with the seed and the previous hash, so the draw changes
+///   with each block but is the same on every node
+/// - Draws a number below the total stake and returns the address whose
+///   balance range contains it
+pub fn select_staker(
+    seed: u64,
+    balances: &HashMap<Address, u64>,
+    previous_hash: &str,
+) -> Option<String> {
+    let mut stakes: Vec<(&Address, u64)> = balances
+        .iter()
+        .filter(|(_, &stake)| stake > 0)
+        .map(|(address, &stake)| (address, stake))
+        .collect();
+    stakes.sort();
+
+    let total_stake: u128 = stakes.iter().map(|&(_, stake)| stake as u128).sum();
+    if total_stake == 0 {
+        return None;
+    }
+
+    let previous_hash = hex::decode(previous_hash).unwrap_or_default();
+    let mut hash_seed = [0u8; 8];
+    for (byte, hash_byte) in hash_seed.iter_mut().zip(previous_hash) {
+        *byte = hash_byte;
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed ^ u64::from_be_bytes(hash_seed));
+    let mut draw = rng.gen_range(0..total_stake);
+
+    stakes.into_iter().find_map(|(address, stake)| {
+        if draw < stake as u128 {
+            Some(address.to_string())
+        } else {
+            draw -= stake as u128;
+            None
+        }
+    })
+}
+
+/// Checks a block is signed by its producer.
+///
+/// # Parameters
+///
+/// * `block` - The block to check
+///
+/// # Returns
+///
+/// bool - True if the block has a producer and a signature of its hash by the
+/// producer key, False otherwise
+///
+/// # Functionality
+///
+/// - Decodes the producer public key and the signature
+/// - Verifies the signature of the block hash against the producer key
+fn is_signed_by_producer(block: &Block) -> bool {
+    let (Some(producer), Some(signature)) = (&block.producer, &block.signature) else {
+        return false;
+    };
+
+    let Ok(public_key) = PublicKey::from_str(producer) else {
+        return false;
+    };
+
+    let Some(signature) = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_der(&bytes).ok())
+    else {
+        return false;
+    };
+
+    block_message(block).is_some_and(|message| {
+        secp256k1_context()
+            .verify_ecdsa(&message, &signature, &public_key)
+            .is_ok()
+    })
+}
+
+/// Builds the message a block producer signs.
+///
+/// # Parameters
+///
+/// * `block` - The block whose hash is signed
+///
+/// # Returns
+///
+/// The secp256k1 message of the block hash, or None if the hash is not 32 hex encoded bytes
+fn block_message(block: &Block) -> Option<Message> {
+    hex::decode(&block.hash)
+        .ok()
+        .and_then(|hash| Message::from_slice(&hash).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::{Hasher, Sha3_256Hasher};
+    use crate::test_utils::address;
+
+    #[test]
+    fn higher_stake_is_selected_more_often() {
+        let (heavy, light, empty) = (address(1), address(2), address(3));
+        let balances = HashMap::from([(heavy, 900), (light, 100), (empty, 0)]);
+
+        let mut selections = HashMap::new();
+        for round in 0..1_000u32 {
+            let previous_hash = Sha3_256Hasher.hash_hex(&round.to_be_bytes());
+            let selected = select_staker(7, &balances, &previous_hash).unwrap();
+            *selections.entry(selected).or_insert(0) += 1;
+        }
+
+        assert!(!selections.contains_key(&empty.to_string()));
+        assert!(selections[&heavy.to_string()] > 800);
+        assert!(selections[&light.to_string()] > 50);
+    }
+
+    #[test]
+    fn selection_is_the_same_for_the_same_seed_and_block() {
+        let balances = HashMap::from([(address(1), 500), (address(2), 500)]);
+        let previous_hash = Sha3_256Hasher.hash_hex(b"block");
+
+        assert_eq!(
+            select_staker(7, &balances, &previous_hash),
+            select_staker(7, &balances, &previous_hash)
+        );
+        assert_eq!(select_staker(7, &HashMap::new(), &previous_hash), None);
+    }
+}
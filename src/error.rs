@@ -0,0 +1,139 @@
+use crate::blockchain::ValidationError;
+use crate::transaction::TransactionError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// BlockchainError enum.
+///
+/// Represents any error raised by the blockchain, so errors from the
+/// different modules can be propagated with `?` and collected together.
+///
+/// # Variants
+///
+/// * `Io` - Returned when a file could not be read or written.
+/// * `Serialization` - Returned when the data is not a valid blockchain.
+/// * `InvalidChain` - Returned when a chain fails validation.
+/// * `Transaction` - Returned when a transaction is rejected by the blockchain.
+/// * `Signing` - Returned when a transaction could not be signed.
+/// * `Verification` - Returned when a transaction signature could not be verified.
+/// * `Overflow` - Returned when an amount computation goes past MAX_AMOUNT or below 0.
+/// * `Rollback` - Returned when the requested blocks cannot be rolled back.
+#[derive(Debug)]
+pub enum BlockchainError {
+    Io(io::Error),
+    Serialization(serde_json::Error),
+    InvalidChain(ValidationError),
+    Transaction(TransactionError),
+    Signing(String),
+    Verification(String),
+    Overflow,
+    Rollback(String),
+}
+
+impl fmt::Display for BlockchainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockchainError::Io(err) => write!(f, "I/O error: {}", err),
+            BlockchainError::Serialization(err) => write!(f, "Serialization error: {}", err),
+            BlockchainError::InvalidChain(err) => write!(f, "Invalid chain: {}", err),
+            BlockchainError::Transaction(err) => write!(f, "Transaction rejected: {}", err),
+            BlockchainError::Signing(msg) => write!(f, "Signing failed: {}", msg),
+            BlockchainError::Verification(msg) => write!(f, "Verification failed: {}", msg),
+            BlockchainError::Overflow => {
+                write!(f, "Amount overflow: the amount does not fit in a u64")
+            }
+            BlockchainError::Rollback(msg) => write!(f, "Rollback refused: {}", msg),
+        }
+    }
+}
+
+impl Error for BlockchainError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BlockchainError::Io(err) => Some(err),
+            BlockchainError::Serialization(err) => Some(err),
+            BlockchainError::InvalidChain(err) => Some(err),
+            BlockchainError::Transaction(err) => Some(err),
+            BlockchainError::Signing(_)
+            | BlockchainError::Verification(_)
+            | BlockchainError::Overflow
+            | BlockchainError::Rollback(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for BlockchainError {
+    fn from(err: io::Error) -> Self {
+        BlockchainError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for BlockchainError {
+    fn from(err: serde_json::Error) -> Self {
+        BlockchainError::Serialization(err)
+    }
+}
+
+impl From<ValidationError> for BlockchainError {
+    fn from(err: ValidationError) -> Self {
+        BlockchainError::InvalidChain(err)
+    }
+}
+
+impl From<TransactionError> for BlockchainError {
+    fn from(err: TransactionError) -> Self {
+        BlockchainError::Transaction(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::test_utils::keypair;
+    use crate::transaction::Transaction;
+
+    #[test]
+    fn signing_and_balance_failures_are_collected_together() {
+        let (key, sender) = keypair(1);
+        let mut blockchain = Blockchain::with_genesis(1, 100, vec![(sender, 5)]);
+        let mut errors: Vec<BlockchainError> = Vec::new();
+
+        let mut badly_signed = Transaction::new(Some(sender), sender, 1);
+        errors.extend(badly_signed.sign("not a key").err());
+
+        let mut overdrawing = Transaction::new(Some(sender), sender, 10);
+        overdrawing.sign_with(&key).unwrap();
+        errors.extend(blockchain.add_transaction(overdrawing).err());
+
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                BlockchainError::Signing(_),
+                BlockchainError::Transaction(TransactionError::InsufficientFunds)
+            ]
+        ));
+        assert!(errors[0].source().is_none());
+        assert_eq!(
+            errors[1].source().unwrap().to_string(),
+            "insufficient funds"
+        );
+    }
+
+    #[test]
+    fn errors_convert_with_the_question_mark_operator() {
+        fn load(json: &str) -> Result<Blockchain, BlockchainError> {
+            Ok(serde_json::from_str(json)?)
+        }
+
+        assert!(matches!(
+            load("not json"),
+            Err(BlockchainError::Serialization(_))
+        ));
+        assert_eq!(
+            BlockchainError::from(ValidationError::GenesisMismatch).to_string(),
+            format!("Invalid chain: {}", ValidationError::GenesisMismatch)
+        );
+    }
+}
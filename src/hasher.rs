@@ -0,0 +1,291 @@
+pub use crate::light::{HashAlgorithm, PowFunction};
+use serde::Serialize;
+use serde_json::Value;
+use sha3::Digest;
+
+/// Hasher trait.
+///
+/// Represents a hash function used to hash blocks and Merkle trees.
+pub trait Hasher {
+    /// Hashes the provided data.
+    ///
+    /// # Parameters
+    ///
+    /// * `data` - The bytes to hash
+    ///
+    /// # Returns
+    ///
+    /// The raw hash bytes.
+    fn hash(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Hashes the provided data into a hex encoded string.
+    ///
+    /// # Parameters
+    ///
+    /// * `data` - The bytes to hash
+    ///
+    /// # Returns
+    ///
+    /// The hash as a hex encoded string.
+    fn hash_hex(&self, data: &[u8]) -> String {
+        hex::encode(self.hash(data))
+    }
+}
+
+/// SHA3-256 hasher, the default hash function of the blockchain.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha3_256Hasher;
+
+impl Hasher for Sha3_256Hasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        sha3::Sha3_256::digest(data).to_vec()
+    }
+}
+
+/// SHA-256 hasher.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        sha2::Sha256::digest(data).to_vec()
+    }
+}
+
+/// BLAKE3 hasher.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+impl Hasher for Blake3Hasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+}
+
+impl Hasher for HashAlgorithm {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha3_256 => Sha3_256Hasher.hash(data),
+            HashAlgorithm::Sha256 => Sha256Hasher.hash(data),
+            HashAlgorithm::Blake3 => Blake3Hasher.hash(data),
+        }
+    }
+}
+
+/// The scrypt cost parameter of the memory-hard proof of work, as the base 2 logarithm of N.
+pub const POW_SCRYPT_LOG_N: u8 = 10;
+
+/// The scrypt block size parameter of the memory-hard proof of work.
+pub const POW_SCRYPT_R: u32 = 8;
+
+/// The scrypt parallelization parameter of the memory-hard proof of work.
+pub const POW_SCRYPT_P: u32 = 1;
+
+/// The fixed salt fed into scrypt by the memory-hard proof of work.
+const POW_SCRYPT_SALT: &[u8] = b"cryptobeca-pow";
+
+/// Scrypt hasher.
+///
+/// A memory-hard hash function, each hash needs 128 * POW_SCRYPT_R * 2^POW_SCRYPT_LOG_N
+/// bytes of memory (1 MiB), so mining it gains far less from dedicated hardware than SHA3.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScryptHasher;
+
+impl Hasher for ScryptHasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        let params = scrypt::Params::new(POW_SCRYPT_LOG_N, POW_SCRYPT_R, POW_SCRYPT_P, 32)
+            .expect("The proof of work scrypt parameters are valid");
+
+        let mut hash = vec![0u8; 32];
+        scrypt::scrypt(data, POW_SCRYPT_SALT, &params, &mut hash)
+            .expect("A 32-byte scrypt output is valid");
+        hash
+    }
+}
+
+/// BlockHasher struct.
+///
+/// The hash function of block hashes, combining the hash algorithm of a
+/// blockchain with its proof of work function.
+///
+/// # Fields
+///
+/// * `hash_algorithm` - The hash algorithm of the blockchain
+/// * `pow_function` - The proof of work function of the blockchain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockHasher {
+    pub hash_algorithm: HashAlgorithm,
+    pub pow_function: PowFunction,
+}
+
+impl Hasher for BlockHasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self.pow_function {
+            PowFunction::HashAlgorithm => self.hash_algorithm.hash(data),
+            PowFunction::Scrypt => ScryptHasher.hash(data),
+        }
+    }
+}
+
+/// Serializes a value into canonical JSON bytes.
+///
+/// Consensus-critical: any hash computed over serialized data must hash these
+/// bytes, never the output of serde_json::to_vec(), whose key order follows the
+/// field declaration order and can change with the serde_json features enabled.
+/// Block and transaction hashes do not depend on JSON today, they hash the fixed
+/// binary layouts of Block::hash_prefix() and Transaction::canonical_bytes().
+///
+/// # Parameters
+///
+/// * `value` - The value to serialize, e.g. a Block
+///
+/// # Returns
+///
+/// `Result<Vec<u8>, serde_json::Error>`
+///
+/// - `Ok(Vec<u8>)` with the canonical JSON bytes
+/// - `Err(serde_json::Error)` if the value cannot be serialized or holds a
+///   floating point number
+///
+/// # Functionality
+///
+/// - Converts the value to a JSON value
+/// - Writes it without whitespace, with the keys of each object sorted by their
+///   UTF-8 bytes
+/// - Writes integers in plain decimal and rejects floating point numbers, whose
+///   formatting is not fixed across implementations
+/// - Writes strings with the escaping of serde_json
+pub fn canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    let mut bytes = Vec::new();
+    write_canonical_json(&serde_json::to_value(value)?, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Writes a JSON value in the canonical form of canonical_json().
+///
+/// # Parameters
+///
+/// * `value` - The JSON value
+/// * `bytes` - The buffer the JSON is appended to
+///
+/// # Returns
+///
+/// `Result<(), serde_json::Error>`
+///
+/// - `Ok(())` if the value was written
+/// - `Err(serde_json::Error)` if the value holds a floating point number
+fn write_canonical_json(value: &Value, bytes: &mut Vec<u8>) -> Result<(), serde_json::Error> {
+    match value {
+        Value::Number(number) if number.is_f64() => Err(serde::ser::Error::custom(
+            "floating point numbers have no canonical JSON form",
+        )),
+        Value::Array(values) => {
+            bytes.push(b'[');
+            for (position, value) in values.iter().enumerate() {
+                if position > 0 {
+                    bytes.push(b',');
+                }
+                write_canonical_json(value, bytes)?;
+            }
+            bytes.push(b']');
+            Ok(())
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+            bytes.push(b'{');
+            for (position, (key, value)) in entries.into_iter().enumerate() {
+                if position > 0 {
+                    bytes.push(b',');
+                }
+                serde_json::to_writer(&mut *bytes, key)?;
+                bytes.push(b':');
+                write_canonical_json(value, bytes)?;
+            }
+            bytes.push(b'}');
+            Ok(())
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+            serde_json::to_writer(bytes, value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashers_match_their_reference_digests() {
+        assert_eq!(
+            Sha3_256Hasher.hash_hex(b"abc"),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            Sha256Hasher.hash_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            Blake3Hasher.hash_hex(b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn hash_algorithm_defaults_to_sha3_256() {
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::Sha3_256);
+        assert_eq!(
+            BlockHasher::default().hash(b"abc"),
+            Sha3_256Hasher.hash(b"abc")
+        );
+    }
+
+    #[test]
+    fn canonical_json_ignores_the_field_declaration_order() {
+        #[derive(Serialize)]
+        struct Declared {
+            nonce: u64,
+            hash: &'static str,
+            inner: Vec<(u8, bool)>,
+        }
+
+        #[derive(Serialize)]
+        struct Reordered {
+            inner: Vec<(u8, bool)>,
+            hash: &'static str,
+            nonce: u64,
+        }
+
+        let declared = canonical_json(&Declared {
+            nonce: 7,
+            hash: "00ff",
+            inner: vec![(1, true)],
+        })
+        .unwrap();
+        let reordered = canonical_json(&Reordered {
+            inner: vec![(1, true)],
+            hash: "00ff",
+            nonce: 7,
+        })
+        .unwrap();
+
+        assert_eq!(declared, reordered);
+        assert_eq!(declared, br#"{"hash":"00ff","inner":[[1,true]],"nonce":7}"#);
+        assert!(canonical_json(&serde_json::json!({ "amount": 0.1 })).is_err());
+    }
+
+    #[test]
+    fn canonical_bytes_of_a_block_are_stable() {
+        let timestamp = chrono::DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+        let block = crate::block::Block::new_at(1, Vec::new(), "0".repeat(64), timestamp);
+
+        let bytes = canonical_json(&block).unwrap();
+        let reparsed: Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(canonical_json(&reparsed).unwrap(), bytes);
+        assert_eq!(canonical_json(&block.clone()).unwrap(), bytes);
+        assert!(!bytes.contains(&b' '));
+        assert!(bytes.starts_with(b"{\"difficulty\":"));
+    }
+}
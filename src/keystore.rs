@@ -0,0 +1,320 @@
+use crate::transaction::secp256k1_context;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::Rng;
+use secp256k1::{PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The default scrypt cost parameter, as the base 2 logarithm of N.
+pub const DEFAULT_SCRYPT_LOG_N: u8 = 15;
+
+/// The default scrypt block size parameter.
+pub const DEFAULT_SCRYPT_R: u32 = 8;
+
+/// The default scrypt parallelization parameter.
+pub const DEFAULT_SCRYPT_P: u32 = 1;
+
+/// The length of the random salt fed into the key derivation function.
+const SALT_LEN: usize = 16;
+
+/// The length of the AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// Keystore struct.
+///
+/// Represents a private key encrypted with a password, as stored on disk.
+/// The plaintext private key is never part of a keystore.
+///
+/// # Fields
+///
+/// * `public_key` - The hex encoded public key of the encrypted private key
+/// * `scrypt_log_n` - The scrypt cost parameter used to derive the encryption key
+/// * `scrypt_r` - The scrypt block size parameter
+/// * `scrypt_p` - The scrypt parallelization parameter
+/// * `salt` - The hex encoded random salt fed into scrypt
+/// * `nonce` - The hex encoded AES-GCM nonce
+/// * `ciphertext` - The hex encoded AES-256-GCM encrypted private key, with its tag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub public_key: String,
+    pub scrypt_log_n: u8,
+    pub scrypt_r: u32,
+    pub scrypt_p: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// KeystoreError enum.
+///
+/// Represents the possible errors when reading or decrypting a keystore.
+///
+/// # Variants
+///
+/// * `Io` - Returned when the keystore file could not be read or written.
+/// * `Serialization` - Returned when the file contents are not a valid keystore.
+/// * `InvalidParameters` - Returned when the stored KDF parameters, salt or nonce are invalid.
+/// * `Decryption` - Returned when the password is wrong or the keystore was tampered with.
+/// * `KeyMismatch` - Returned when the decrypted private key does not match the stored public key.
+#[derive(Debug)]
+pub enum KeystoreError {
+    Io(io::Error),
+    Serialization(serde_json::Error),
+    InvalidParameters,
+    Decryption,
+    KeyMismatch,
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeystoreError::Io(err) => write!(f, "I/O error: {}", err),
+            KeystoreError::Serialization(err) => write!(f, "Serialization error: {}", err),
+            KeystoreError::InvalidParameters => write!(f, "Invalid keystore parameters"),
+            KeystoreError::Decryption => write!(f, "Wrong password or corrupted keystore"),
+            KeystoreError::KeyMismatch => {
+                write!(f, "The private key does not correspond to the public key")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl Keystore {
+    /// Encrypts a private key with a password.
+    ///
+    /// # Parameters
+    ///
+    /// * `secret_key` - The private key to encrypt
+    /// * `password` - The password the encryption key is derived from
+    /// * `compressed` - Whether to store the public key in compressed format
+    ///
+    /// # Returns
+    ///
+    /// The new Keystore instance.
+    ///
+    /// # Functionality
+    ///
+    /// - Generates a random salt and nonce
+    /// - Derives a 256-bit encryption key from the password with scrypt
+    /// - Encrypts the private key bytes with AES-256-GCM
+    /// - Stores the public key, KDF parameters, salt, nonce and ciphertext as hex
+    pub fn encrypt(secret_key: &SecretKey, password: &str, compressed: bool) -> Keystore {
+        let mut rng = rand::thread_rng();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt);
+        rng.fill(&mut nonce);
+
+        let key = Self::derive_key(
+            password,
+            &salt,
+            DEFAULT_SCRYPT_LOG_N,
+            DEFAULT_SCRYPT_R,
+            DEFAULT_SCRYPT_P,
+        )
+        .expect("Default scrypt parameters are valid");
+
+        let ciphertext = Aes256Gcm::new(&key.into())
+            .encrypt(Nonce::from_slice(&nonce), &secret_key[..])
+            .expect("Encrypting a private key cannot fail");
+
+        let public_key = PublicKey::from_secret_key(secp256k1_context(), secret_key);
+        let public_key = if compressed {
+            hex::encode(public_key.serialize())
+        } else {
+            hex::encode(public_key.serialize_uncompressed())
+        };
+
+        Keystore {
+            public_key,
+            scrypt_log_n: DEFAULT_SCRYPT_LOG_N,
+            scrypt_r: DEFAULT_SCRYPT_R,
+            scrypt_p: DEFAULT_SCRYPT_P,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        }
+    }
+
+    /// Decrypts the private key with a password.
+    ///
+    /// # Parameters
+    ///
+    /// * `password` - The password the keystore was encrypted with
+    ///
+    /// # Returns
+    ///
+    /// `Result<SecretKey, KeystoreError>`
+    ///
+    /// - `Ok(SecretKey)` with the decrypted private key
+    /// - `Err(KeystoreError)` if the password is wrong or the keystore is invalid
+    ///
+    /// # Functionality
+    ///
+    /// - Decodes the salt, nonce and ciphertext from hex
+    /// - Derives the encryption key from the password with the stored scrypt parameters
+    /// - Decrypts and authenticates the ciphertext with AES-256-GCM
+    /// - Checks the decrypted private key corresponds to the stored public key
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey, KeystoreError> {
+        let salt = hex::decode(&self.salt).map_err(|_| KeystoreError::InvalidParameters)?;
+        let nonce = hex::decode(&self.nonce).map_err(|_| KeystoreError::InvalidParameters)?;
+        let ciphertext = hex::decode(&self.ciphertext).map_err(|_| KeystoreError::Decryption)?;
+
+        if nonce.len() != NONCE_LEN {
+            return Err(KeystoreError::InvalidParameters);
+        }
+
+        let key = Self::derive_key(
+            password,
+            &salt,
+            self.scrypt_log_n,
+            self.scrypt_r,
+            self.scrypt_p,
+        )?;
+
+        let plaintext = Aes256Gcm::new(&key.into())
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| KeystoreError::Decryption)?;
+
+        let secret_key =
+            SecretKey::from_slice(&plaintext).map_err(|_| KeystoreError::Decryption)?;
+
+        let public_key =
+            PublicKey::from_str(&self.public_key).map_err(|_| KeystoreError::KeyMismatch)?;
+
+        if PublicKey::from_secret_key(secp256k1_context(), &secret_key) != public_key {
+            return Err(KeystoreError::KeyMismatch);
+        }
+
+        Ok(secret_key)
+    }
+
+    /// Derives an AES-256 key from a password with scrypt.
+    ///
+    /// # Parameters
+    ///
+    /// * `password` - The password to derive the key from
+    /// * `salt` - The random salt
+    /// * `log_n` - The scrypt cost parameter
+    /// * `r` - The scrypt block size parameter
+    /// * `p` - The scrypt parallelization parameter
+    ///
+    /// # Returns
+    ///
+    /// `Result<[u8; 32], KeystoreError>`
+    ///
+    /// - `Ok([u8; 32])` with the derived key
+    /// - `Err(KeystoreError::InvalidParameters)` if the scrypt parameters are invalid
+    fn derive_key(
+        password: &str,
+        salt: &[u8],
+        log_n: u8,
+        r: u32,
+        p: u32,
+    ) -> Result<[u8; 32], KeystoreError> {
+        let params =
+            scrypt::Params::new(log_n, r, p, 32).map_err(|_| KeystoreError::InvalidParameters)?;
+
+        let mut key = [0u8; 32];
+        scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+            .map_err(|_| KeystoreError::InvalidParameters)?;
+
+        Ok(key)
+    }
+
+    /// Saves the keystore to a JSON file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file to write
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<()>`
+    ///
+    /// - `Ok(())` if the file was written
+    /// - `Err(io::Error)` if serialization or writing failed
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Loads a keystore from a JSON file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file to read
+    ///
+    /// # Returns
+    ///
+    /// `Result<Keystore, KeystoreError>`
+    ///
+    /// - `Ok(Keystore)` if the file contains a keystore
+    /// - `Err(KeystoreError)` if reading or parsing failed
+    pub fn load_from_file(path: &Path) -> Result<Keystore, KeystoreError> {
+        let json = fs::read_to_string(path).map_err(KeystoreError::Io)?;
+        serde_json::from_str(&json).map_err(KeystoreError::Serialization)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{address, keypair};
+
+    #[test]
+    fn encrypted_key_decrypts_only_with_its_password() {
+        let (secret_key, _) = keypair(1);
+        let keystore = Keystore::encrypt(&secret_key, "correct horse", true);
+
+        assert_eq!(keystore.decrypt("correct horse").unwrap(), secret_key);
+        assert!(matches!(
+            keystore.decrypt("wrong horse"),
+            Err(KeystoreError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn keystore_file_never_holds_the_plaintext_key() {
+        let (secret_key, _) = keypair(2);
+        let keystore = Keystore::encrypt(&secret_key, "password", false);
+        let path =
+            std::env::temp_dir().join(format!("cryptobeca-keystore-{}.json", std::process::id()));
+
+        keystore.save_to_file(&path).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        let loaded = Keystore::load_from_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(!json.contains(&hex::encode(secret_key.secret_bytes())));
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.public_key, keystore.public_key);
+        assert_eq!(loaded.public_key.len(), 130);
+        assert_eq!(loaded.decrypt("password").unwrap(), secret_key);
+    }
+
+    #[test]
+    fn keystore_with_a_mismatched_public_key_is_rejected() {
+        let (secret_key, _) = keypair(1);
+        let mut keystore = Keystore::encrypt(&secret_key, "password", true);
+        keystore.public_key = hex::encode(address(2).public_key().serialize());
+
+        assert!(matches!(
+            keystore.decrypt("password"),
+            Err(KeystoreError::KeyMismatch)
+        ));
+
+        keystore.nonce = "00".to_string();
+        assert!(matches!(
+            keystore.decrypt("password"),
+            Err(KeystoreError::InvalidParameters)
+        ));
+    }
+}
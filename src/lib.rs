@@ -0,0 +1,29 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod address;
+#[cfg(feature = "std")]
+pub mod block;
+#[cfg(feature = "std")]
+pub mod blockchain;
+#[cfg(feature = "std")]
+pub mod consensus;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod hasher;
+#[cfg(feature = "std")]
+pub mod keystore;
+pub mod light;
+#[cfg(feature = "std")]
+pub mod p2p;
+#[cfg(feature = "std")]
+pub mod rpc;
+#[cfg(feature = "std")]
+pub mod storage;
+#[cfg(all(test, feature = "std"))]
+mod test_utils;
+#[cfg(feature = "std")]
+pub mod transaction;
+#[cfg(feature = "std")]
+pub mod units;
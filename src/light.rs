@@ -0,0 +1,753 @@
+use core::fmt::{self, Write};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sha3::Digest;
+
+/// HashAlgorithm enum.
+///
+/// Represents the hash function a blockchain is configured with. All
+/// variants produce 32-byte hashes.
+///
+/// # Variants
+///
+/// * `Sha3_256` - Hashes with SHA3-256, the default.
+/// * `Sha256` - Hashes with SHA-256.
+/// * `Blake3` - Hashes with BLAKE3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum HashAlgorithm {
+    #[default]
+    Sha3_256,
+    Sha256,
+    Blake3,
+}
+
+/// PowFunction enum.
+///
+/// Represents the hash function block hashes are mined with. The Merkle root
+/// and transaction hashes always use the hash algorithm of the blockchain.
+///
+/// # Variants
+///
+/// * `HashAlgorithm` - Mines with the hash algorithm of the blockchain, the default.
+/// * `Scrypt` - Mines with the memory-hard ScryptHasher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum PowFunction {
+    #[default]
+    HashAlgorithm,
+    Scrypt,
+}
+
+/// HeaderError enum.
+///
+/// Represents the reason a block header failed light verification.
+///
+/// # Variants
+///
+/// * `IndexMismatch` - Returned when the header index does not follow the parent index.
+/// * `PreviousHashMismatch` - Returned when the header previous hash is not the parent hash.
+/// * `HashMismatch` - Returned when the header hash does not match its recalculated hash.
+/// * `InsufficientWork` - Returned when the header hash does not meet its difficulty.
+/// * `UnsupportedPowFunction` - Returned when the header is mined with scrypt, which
+///   needs the `std` feature.
+/// * `TimestampOutOfRange` - Returned when the timestamp is not between the years 0 and 9999.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderError {
+    IndexMismatch,
+    PreviousHashMismatch,
+    HashMismatch,
+    InsufficientWork,
+    UnsupportedPowFunction,
+    TimestampOutOfRange,
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            HeaderError::IndexMismatch => "index does not follow the parent header",
+            HeaderError::PreviousHashMismatch => "previous hash does not match the parent hash",
+            HeaderError::HashMismatch => "hash does not match the recalculated hash",
+            HeaderError::InsufficientWork => "hash does not meet the difficulty",
+            HeaderError::UnsupportedPowFunction => "proof of work function needs std",
+            HeaderError::TimestampOutOfRange => "timestamp is out of range",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HeaderError {}
+
+/// RawHeader struct.
+///
+/// The fields of a block header, borrowed and with an integer timestamp, so a
+/// header can be verified without std, chrono or allocation.
+///
+/// # Fields
+///
+/// * `index` - The height of the block
+/// * `timestamp` - The timestamp of the block, as seconds since the Unix epoch
+/// * `timestamp_nanos` - The nanoseconds of the timestamp past its second
+/// * `merkle_root` - The hex encoded Merkle root of the block transactions
+/// * `previous_hash` - The hex encoded hash of the previous block
+/// * `nonce` - The nonce the block was mined with
+/// * `hash` - The hex encoded hash of the block
+/// * `difficulty` - The difficulty the block was mined at
+/// * `hash_algorithm` - The hash function of the block
+/// * `pow_function` - The function the block hash is mined with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawHeader<'a> {
+    pub index: u64,
+    pub timestamp: i64,
+    pub timestamp_nanos: u32,
+    pub merkle_root: &'a str,
+    pub previous_hash: &'a str,
+    pub nonce: u64,
+    pub hash: &'a str,
+    pub difficulty: u32,
+    pub hash_algorithm: HashAlgorithm,
+    pub pow_function: PowFunction,
+}
+
+/// Computes the hash of a block header.
+///
+/// Consensus-critical: the hashed bytes are the ones of Block::calculate_hash(),
+/// the index, Merkle root, RFC 3339 timestamp, previous hash and nonce written
+/// one after the other.
+///
+/// # Parameters
+///
+/// * `header` - The header to hash
+///
+/// # Returns
+///
+/// `Result<[u8; 32], HeaderError>`
+///
+/// - `Ok([u8; 32])` with the hash of the header
+/// - `Err(HeaderError)` if the header is mined with scrypt or its timestamp is out of range
+///
+/// # Functionality
+///
+/// - Feeds the fields to the hash function as they are formatted, without a buffer
+pub fn block_hash(header: &RawHeader<'_>) -> Result<[u8; 32], HeaderError> {
+    if header.pow_function != PowFunction::HashAlgorithm {
+        return Err(HeaderError::UnsupportedPowFunction);
+    }
+
+    let timestamp = Rfc3339::new(header.timestamp, header.timestamp_nanos)
+        .ok_or(HeaderError::TimestampOutOfRange)?;
+
+    Ok(hash_formatted(header.hash_algorithm, |writer| {
+        write!(
+            writer,
+            "{}{}{}{}{}",
+            header.index, header.merkle_root, timestamp, header.previous_hash, header.nonce
+        )
+    }))
+}
+
+/// Checks if a hash meets a difficulty.
+///
+/// # Parameters
+///
+/// * `hash` - The block hash
+/// * `difficulty` - The difficulty, as the number of leading zero bits
+///
+/// # Returns
+///
+/// bool - True if the hash has at least `difficulty` leading zero bits, as
+/// Block::meets_target() checks against the target of the difficulty, False otherwise
+pub fn meets_difficulty(hash: &[u8; 32], difficulty: u32) -> bool {
+    let mut zero_bits = 0;
+    for byte in hash {
+        zero_bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+
+    zero_bits >= difficulty.min(256)
+}
+
+/// Verifies a header extends a parent header, as BlockHeader::verify() does.
+///
+/// # Parameters
+///
+/// * `header` - The header to verify
+/// * `parent` - The header of the block it claims to follow
+///
+/// # Returns
+///
+/// `Result<(), HeaderError>`
+///
+/// - `Ok(())` if the header follows the parent and carries valid proof of work
+/// - `Err(HeaderError)` with the failed check
+///
+/// # Functionality
+///
+/// - Checks the index follows the parent index
+/// - Checks the previous hash matches the parent hash
+/// - Checks the hash matches block_hash()
+/// - Checks the hash meets the difficulty recorded on the header
+pub fn verify_header(header: &RawHeader<'_>, parent: &RawHeader<'_>) -> Result<(), HeaderError> {
+    if Some(header.index) != parent.index.checked_add(1) {
+        return Err(HeaderError::IndexMismatch);
+    }
+
+    if header.previous_hash != parent.hash {
+        return Err(HeaderError::PreviousHashMismatch);
+    }
+
+    let hash = block_hash(header)?;
+    if !is_hex_of(header.hash, &hash) {
+        return Err(HeaderError::HashMismatch);
+    }
+
+    if !meets_difficulty(&hash, header.difficulty) {
+        return Err(HeaderError::InsufficientWork);
+    }
+
+    Ok(())
+}
+
+/// Verifies a Merkle proof that a transaction is included in a block, as
+/// block::verify_merkle_proof() does.
+///
+/// # Parameters
+///
+/// * `hash_algorithm` - The hash function of the block
+/// * `tx_hash` - The hex encoded hash of the transaction
+/// * `proof` - The hex encoded sibling hashes and whether each is on the left, as
+///   returned by Block::merkle_proof()
+/// * `root` - The hex encoded Merkle root of the block
+///
+/// # Returns
+///
+/// bool - True if the proof leads from the transaction hash to the root, False otherwise
+///
+/// # Functionality
+///
+/// - Starts from the transaction hash
+/// - Hashes the hex encoded current node with each sibling, on the side given by the proof
+/// - Compares the resulting hash with the root
+pub fn verify_merkle_proof<'p>(
+    hash_algorithm: HashAlgorithm,
+    tx_hash: &str,
+    proof: impl IntoIterator<Item = (&'p str, bool)>,
+    root: &str,
+) -> bool {
+    let mut node: Option<[u8; 32]> = None;
+
+    for (sibling, is_left) in proof {
+        let mut node_hex = [0u8; 64];
+        let node_str = match node {
+            Some(hash) => hex_str(&hash, &mut node_hex),
+            None => tx_hash,
+        };
+
+        node = Some(hash_formatted(hash_algorithm, |writer| {
+            if is_left {
+                write!(writer, "{}{}", sibling, node_str)
+            } else {
+                write!(writer, "{}{}", node_str, sibling)
+            }
+        }));
+    }
+
+    match node {
+        Some(hash) => is_hex_of(root, &hash),
+        None => tx_hash == root,
+    }
+}
+
+/// Hashes formatted data with a hash algorithm.
+///
+/// # Parameters
+///
+/// * `hash_algorithm` - The hash function
+/// * `write` - Writes the data to hash to the provided writer
+///
+/// # Returns
+///
+/// The hash of the written data
+fn hash_formatted(
+    hash_algorithm: HashAlgorithm,
+    write: impl FnOnce(&mut dyn Write) -> fmt::Result,
+) -> [u8; 32] {
+    match hash_algorithm {
+        HashAlgorithm::Sha3_256 => hash_with::<sha3::Sha3_256>(write),
+        HashAlgorithm::Sha256 => hash_with::<sha2::Sha256>(write),
+        HashAlgorithm::Blake3 => hash_with::<blake3::Hasher>(write),
+    }
+}
+
+/// Hashes formatted data with a hash function.
+///
+/// # Parameters
+///
+/// * `write` - Writes the data to hash to the provided writer
+///
+/// # Returns
+///
+/// The hash of the written data
+fn hash_with<H: IncrementalHash>(write: impl FnOnce(&mut dyn Write) -> fmt::Result) -> [u8; 32] {
+    let mut writer = HashWriter(H::default());
+    write(&mut writer).expect("Writing to a hash cannot fail");
+    writer.0.finish()
+}
+
+/// IncrementalHash trait.
+///
+/// A 32-byte hash function fed its data in pieces.
+trait IncrementalHash: Default {
+    /// Feeds data to the hash.
+    ///
+    /// # Parameters
+    ///
+    /// * `data` - The bytes to hash
+    fn feed(&mut self, data: &[u8]);
+
+    /// Finishes the hash.
+    ///
+    /// # Returns
+    ///
+    /// The hash of the data fed so far
+    fn finish(self) -> [u8; 32];
+}
+
+impl IncrementalHash for sha3::Sha3_256 {
+    fn feed(&mut self, data: &[u8]) {
+        self.update(data);
+    }
+
+    fn finish(self) -> [u8; 32] {
+        self.finalize().into()
+    }
+}
+
+impl IncrementalHash for sha2::Sha256 {
+    fn feed(&mut self, data: &[u8]) {
+        self.update(data);
+    }
+
+    fn finish(self) -> [u8; 32] {
+        self.finalize().into()
+    }
+}
+
+impl IncrementalHash for blake3::Hasher {
+    fn feed(&mut self, data: &[u8]) {
+        self.update(data);
+    }
+
+    fn finish(self) -> [u8; 32] {
+        self.finalize().into()
+    }
+}
+
+/// HashWriter struct.
+///
+/// Feeds the formatted text written to it to a hash function.
+struct HashWriter<H>(H);
+
+impl<H: IncrementalHash> Write for HashWriter<H> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.feed(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Rfc3339 struct.
+///
+/// A UTC timestamp formatted as chrono's DateTime::to_rfc3339() formats it, e.g.
+/// "2024-01-01T00:00:00.250+00:00", the fraction of a second being written with
+/// 3, 6 or 9 digits and left out if zero.
+///
+/// # Fields
+///
+/// * `year`, `month`, `day`, `hour`, `minute`, `second` - The date and time
+/// * `nanos` - The nanoseconds past the second
+struct Rfc3339 {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    nanos: u32,
+}
+
+impl Rfc3339 {
+    /// Creates a new Rfc3339 instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `timestamp` - The seconds since the Unix epoch
+    /// * `nanos` - The nanoseconds past the second
+    ///
+    /// # Returns
+    ///
+    /// The timestamp, or None if it is not between the years 0 and 9999 or the
+    /// nanoseconds are a second or more
+    ///
+    /// # Functionality
+    ///
+    /// - Splits the timestamp into days since the epoch and seconds of the day
+    /// - Converts the days into a proleptic Gregorian date
+    fn new(timestamp: i64, nanos: u32) -> Option<Self> {
+        if nanos >= 1_000_000_000 {
+            return None;
+        }
+
+        let days = timestamp.div_euclid(86_400);
+        let seconds = timestamp.rem_euclid(86_400) as u32;
+
+        // Converts days since 1970-01-01 into a date, counting in 400-year eras
+        // starting on March 1st so leap days fall at the end of each year.
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        if !(0..=9999).contains(&year) {
+            return None;
+        }
+
+        Some(Self {
+            year,
+            month,
+            day,
+            hour: seconds / 3_600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+            nanos,
+        })
+    }
+}
+
+impl fmt::Display for Rfc3339 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+
+        match self.nanos {
+            0 => {}
+            nanos if nanos.is_multiple_of(1_000_000) => write!(f, ".{:03}", nanos / 1_000_000)?,
+            nanos if nanos.is_multiple_of(1_000) => write!(f, ".{:06}", nanos / 1_000)?,
+            nanos => write!(f, ".{:09}", nanos)?,
+        }
+
+        write!(f, "+00:00")
+    }
+}
+
+/// Hex encodes a hash into a buffer.
+///
+/// # Parameters
+///
+/// * `hash` - The hash
+/// * `buffer` - The buffer holding the encoding
+///
+/// # Returns
+///
+/// The lowercase hex encoding of the hash, borrowed from the buffer
+fn hex_str<'b>(hash: &[u8; 32], buffer: &'b mut [u8; 64]) -> &'b str {
+    hex::encode_to_slice(hash, buffer).expect("A 32-byte hash fits 64 hex digits");
+    core::str::from_utf8(buffer).expect("Hex digits are ASCII")
+}
+
+/// Checks if a string is the hex encoding of a hash.
+///
+/// # Parameters
+///
+/// * `hex_hash` - The hex encoded hash
+/// * `hash` - The hash
+///
+/// # Returns
+///
+/// bool - True if the string is the lowercase hex encoding of the hash, False otherwise
+fn is_hex_of(hex_hash: &str, hash: &[u8; 32]) -> bool {
+    let mut buffer = [0u8; 64];
+    hex_str(hash, &mut buffer) == hex_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MERKLE_ROOT: &str = "7d1a54127b222502f5b79b5fb0803061152a44f92b37e23c6527baf665d4da9a";
+
+    const PARENT_HASH: &str = "0f9c2e3a8f50b9b86e1c2f6477706cb1b0d6b81a1ac7a73f1c5a9437c60fb4e2";
+
+    /// A fixed buffer formatted text is written to, as no allocation is available
+    /// without std.
+    struct Buffer {
+        bytes: [u8; 64],
+        len: usize,
+    }
+
+    impl Buffer {
+        fn new() -> Self {
+            Self {
+                bytes: [0; 64],
+                len: 0,
+            }
+        }
+
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    /// Formats a timestamp as block_hash() hashes it.
+    fn rfc3339(timestamp: i64, nanos: u32) -> Buffer {
+        let mut buffer = Buffer::new();
+        write!(buffer, "{}", Rfc3339::new(timestamp, nanos).unwrap()).unwrap();
+        buffer
+    }
+
+    /// Mines the header following PARENT_HASH at a difficulty, writing its hash
+    /// to the buffer.
+    fn mined_header(difficulty: u32, hash_buffer: &mut [u8; 64]) -> RawHeader<'_> {
+        let mut header = RawHeader {
+            index: 1,
+            timestamp: 1_704_067_200,
+            timestamp_nanos: 250_000_000,
+            merkle_root: MERKLE_ROOT,
+            previous_hash: PARENT_HASH,
+            nonce: 0,
+            hash: "",
+            difficulty,
+            hash_algorithm: HashAlgorithm::Sha3_256,
+            pow_function: PowFunction::HashAlgorithm,
+        };
+
+        let hash = loop {
+            let hash = block_hash(&header).unwrap();
+            if meets_difficulty(&hash, difficulty) {
+                break hash;
+            }
+            header.nonce += 1;
+        };
+
+        header.hash = hex_str(&hash, hash_buffer);
+        header
+    }
+
+    /// Gets the header the mined header follows.
+    fn parent_header() -> RawHeader<'static> {
+        RawHeader {
+            index: 0,
+            timestamp: 1_704_067_000,
+            timestamp_nanos: 0,
+            merkle_root: MERKLE_ROOT,
+            previous_hash: "0",
+            nonce: 0,
+            hash: PARENT_HASH,
+            difficulty: 0,
+            hash_algorithm: HashAlgorithm::Sha3_256,
+            pow_function: PowFunction::HashAlgorithm,
+        }
+    }
+
+    #[test]
+    fn timestamps_are_formatted_as_chrono_formats_them() {
+        assert_eq!(rfc3339(0, 0).as_str(), "1970-01-01T00:00:00+00:00");
+        assert_eq!(
+            rfc3339(1_709_164_799, 250_000_000).as_str(),
+            "2024-02-28T23:59:59.250+00:00"
+        );
+        assert_eq!(
+            rfc3339(1_709_164_800, 1_000).as_str(),
+            "2024-02-29T00:00:00.000001+00:00"
+        );
+        assert_eq!(
+            rfc3339(-1, 1).as_str(),
+            "1969-12-31T23:59:59.000000001+00:00"
+        );
+        assert!(Rfc3339::new(253_402_300_800, 0).is_none());
+        assert!(Rfc3339::new(0, 1_000_000_000).is_none());
+    }
+
+    #[test]
+    fn mined_header_verifies_against_its_parent() {
+        let mut hash_buffer = [0u8; 64];
+        let header = mined_header(8, &mut hash_buffer);
+
+        assert_eq!(verify_header(&header, &parent_header()), Ok(()));
+    }
+
+    #[test]
+    fn tampered_headers_are_rejected() {
+        let mut hash_buffer = [0u8; 64];
+        let header = mined_header(8, &mut hash_buffer);
+        let parent = parent_header();
+
+        let checks = [
+            (RawHeader { index: 2, ..header }, HeaderError::IndexMismatch),
+            (
+                RawHeader {
+                    previous_hash: MERKLE_ROOT,
+                    ..header
+                },
+                HeaderError::PreviousHashMismatch,
+            ),
+            (
+                RawHeader {
+                    nonce: header.nonce + 1,
+                    ..header
+                },
+                HeaderError::HashMismatch,
+            ),
+            (
+                RawHeader {
+                    timestamp_nanos: 0,
+                    ..header
+                },
+                HeaderError::HashMismatch,
+            ),
+            (
+                RawHeader {
+                    difficulty: 256,
+                    ..header
+                },
+                HeaderError::InsufficientWork,
+            ),
+            (
+                RawHeader {
+                    pow_function: PowFunction::Scrypt,
+                    ..header
+                },
+                HeaderError::UnsupportedPowFunction,
+            ),
+            (
+                RawHeader {
+                    timestamp: i64::MAX,
+                    ..header
+                },
+                HeaderError::TimestampOutOfRange,
+            ),
+        ];
+
+        for (tampered, err) in checks {
+            assert_eq!(verify_header(&tampered, &parent), Err(err));
+        }
+    }
+
+    #[test]
+    fn difficulty_counts_leading_zero_bits() {
+        let mut hash = [0xffu8; 32];
+        hash[0] = 0;
+        hash[1] = 0x1f;
+
+        assert!(meets_difficulty(&hash, 11));
+        assert!(!meets_difficulty(&hash, 12));
+        assert!(meets_difficulty(&[0; 32], 300));
+    }
+
+    #[test]
+    fn merkle_proof_leads_to_the_root() {
+        let sibling = PARENT_HASH;
+        let root = hash_formatted(HashAlgorithm::Blake3, |writer| {
+            write!(writer, "{}{}", MERKLE_ROOT, sibling)
+        });
+        let mut root_buffer = [0u8; 64];
+        let root = hex_str(&root, &mut root_buffer);
+
+        assert!(verify_merkle_proof(
+            HashAlgorithm::Blake3,
+            MERKLE_ROOT,
+            [(sibling, false)],
+            root
+        ));
+        assert!(!verify_merkle_proof(
+            HashAlgorithm::Blake3,
+            MERKLE_ROOT,
+            [(sibling, true)],
+            root
+        ));
+        assert!(!verify_merkle_proof(
+            HashAlgorithm::Sha256,
+            MERKLE_ROOT,
+            [(sibling, false)],
+            root
+        ));
+        assert!(verify_merkle_proof(
+            HashAlgorithm::Sha3_256,
+            MERKLE_ROOT,
+            [],
+            MERKLE_ROOT
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn light_verification_agrees_with_the_full_node() {
+        use crate::block::Block;
+        use crate::blockchain::Blockchain;
+        use crate::test_utils::address;
+
+        for hash_algorithm in [
+            HashAlgorithm::Sha3_256,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+        ] {
+            let mut blockchain = Blockchain::new_with_algorithm(1, 100, hash_algorithm);
+            for _ in 0..3 {
+                blockchain.mine_pending_transactions(address(1)).unwrap();
+            }
+
+            let headers: Vec<_> = blockchain.blocks().map(Block::header).collect();
+            for pair in headers.windows(2) {
+                assert_eq!(pair[1].verify(&pair[0]), Ok(()));
+                assert_eq!(verify_header(&pair[1].as_raw(), &pair[0].as_raw()), Ok(()));
+            }
+        }
+
+        let blockchain = Blockchain::with_genesis(
+            1,
+            100,
+            vec![(address(1), 10), (address(2), 20), (address(3), 30)],
+        );
+        let genesis_block = blockchain.get_latest_block();
+        for transaction in &genesis_block.transactions {
+            let tx_hash = transaction.compute_hash();
+            let proof = genesis_block.merkle_proof(&tx_hash).unwrap();
+
+            assert!(verify_merkle_proof(
+                genesis_block.hash_algorithm,
+                &tx_hash,
+                proof
+                    .iter()
+                    .map(|(sibling, is_left)| (sibling.as_str(), *is_left)),
+                &genesis_block.merkle_root
+            ));
+        }
+    }
+}
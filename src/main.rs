@@ -1,61 +1,208 @@
-use blockchain::*;
-use transaction::*;
+use clap::{Parser, Subcommand};
+use cryptobeca::address::*;
+use cryptobeca::blockchain::*;
+use cryptobeca::transaction::*;
+use cryptobeca::units::*;
+use log::{error, info};
 use std::env;
-mod block;
-mod blockchain;
-mod transaction;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-fn main() {
-    dotenv::dotenv().ok();
+/// The difficulty of a newly created chain.
+const DEFAULT_DIFFICULTY: u32 = 8;
+
+/// The mining reward of a newly created chain, in smallest units.
+const DEFAULT_MINING_REWARD: u64 = 100 * COIN;
+
+/// Cli struct.
+///
+/// Represents the command line wallet options.
+///
+/// # Fields
+///
+/// * `chain` - The file the chain is loaded from and saved to
+/// * `command` - The subcommand to run
+#[derive(Parser)]
+#[command(version, about = "A command line wallet for the cryptobeca blockchain")]
+struct Cli {
+    #[arg(long, default_value = "blockchain.json")]
+    chain: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
 
-    let my_key: &str = &env::var("PRIVATE_KEY").unwrap_or("Invalid PRIVATE_KEY".to_string());
-    let my_wallet_address: &str =
-        &env::var("PUBLIC_KEY").unwrap_or("Invalid PUBLIC_KEY".to_string());
+/// Command enum.
+///
+/// Represents the subcommands of the command line wallet.
+///
+/// # Variants
+///
+/// * `Send` - Signs a transfer with the PRIVATE_KEY and PUBLIC_KEY environment
+///   variables and adds it to the pending transactions. `--to` and `--amount`
+///   can be repeated to pay several recipients in a single transaction. Amounts
+///   and fees are decimal numbers of coins. `--memo` attaches a text note.
+/// * `Mine` - Mines the pending transactions into a new block.
+/// * `Balance` - Prints the balance of an address.
+/// * `Validate` - Validates the chain.
+/// * `Status` - Prints a summary of the chain.
+#[derive(Subcommand)]
+enum Command {
+    Send {
+        #[arg(long, required = true)]
+        to: Vec<Address>,
+        #[arg(long, required = true, value_parser = parse_units)]
+        amount: Vec<u64>,
+        #[arg(long, default_value = "0", value_parser = parse_units)]
+        fee: u64,
+        #[arg(long)]
+        memo: Option<String>,
+    },
+    Mine {
+        #[arg(long)]
+        reward_to: Address,
+    },
+    Balance {
+        address: Address,
+    },
+    Validate,
+    Status,
+}
+
+/// Runs the command line wallet.
+///
+/// # Returns
+///
+/// `ExitCode::SUCCESS` if the subcommand succeeded, `ExitCode::FAILURE` otherwise.
+///
+/// # Functionality
+///
+/// - Loads the environment variables and initializes logging
+/// - Parses the command line options
+/// - Loads the chain file, or creates a new chain if it does not exist, rejecting a
+///   chain file whose genesis block differs from the one of a new chain
+/// - Runs the subcommand, printing its result
+/// - Saves the chain back to the file for subcommands that modify it
+fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    let mut blockchain = Blockchain::new(2, 100.0);
+    let cli = Cli::parse();
 
-    let mut transaction = Transaction {
-        from_address: Some(my_wallet_address.to_string()),
-        to_address: "public key of someone's address".to_string(),
-        amount: 10.0,
-        signature: None,
-        hash: None,
+    let mut blockchain = if cli.chain.exists() {
+        match Blockchain::load_from_file(&cli.chain) {
+            Ok(blockchain) => blockchain,
+            Err(err) => {
+                error!("Could not load {}: {}", cli.chain.display(), err);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        info!("Creating a new chain at {}", cli.chain.display());
+        Blockchain::new(DEFAULT_DIFFICULTY, DEFAULT_MINING_REWARD)
     };
 
-    match transaction.sign(my_key) {
-        Ok(()) => {
-            println!("Transaction signed successfully!");
-            println!("Transaction with signature: {:#?}", transaction);
+    match cli.command {
+        Command::Send {
+            to,
+            amount,
+            fee,
+            memo,
+        } => {
+            let (Ok(private_key), Ok(public_key)) =
+                (env::var("PRIVATE_KEY"), env::var("PUBLIC_KEY"))
+            else {
+                error!("The PRIVATE_KEY and PUBLIC_KEY environment variables must be set");
+                return ExitCode::FAILURE;
+            };
+
+            let public_key = match public_key.parse::<Address>() {
+                Ok(public_key) => public_key,
+                Err(err) => {
+                    error!("Invalid PUBLIC_KEY: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            if to.len() != amount.len() {
+                error!("Each --to must be paired with an --amount");
+                return ExitCode::FAILURE;
+            }
+
+            let outputs = to
+                .into_iter()
+                .zip(amount)
+                .map(|(to_address, amount)| TransactionOutput { to_address, amount })
+                .collect();
+
+            let mut transaction = Transaction::with_outputs(Some(public_key), outputs);
+            transaction.fee = fee;
+            transaction.memo = memo.map(String::into_bytes);
+            transaction.nonce = blockchain.next_nonce_for(&public_key);
+
+            if let Err(err) = transaction.sign(&private_key) {
+                error!("Error signing transaction: {}", err);
+                return ExitCode::FAILURE;
+            }
+
+            let hash = transaction.compute_hash();
+
+            if let Err(err) = blockchain.add_transaction(transaction) {
+                error!("Error adding transaction: {}", err);
+                return ExitCode::FAILURE;
+            }
+
+            println!("Transaction {} added to the pending transactions", hash);
+            save(&blockchain, &cli.chain)
         }
-        Err(err) => println!("Error signing transaction: {}", err),
-    }
+        Command::Mine { reward_to } => {
+            if let Err(err) = blockchain.mine_pending_transactions(reward_to) {
+                error!("Error mining block: {}", err);
+                return ExitCode::FAILURE;
+            }
 
-    match transaction.is_valid() {
-        Ok(valid) => {
-            if valid {
-                println!("Transaction is valid.");
-            } else {
-                println!("Transaction is NOT valid.");
+            println!("Block mined: {}", blockchain.get_latest_block().hash);
+            save(&blockchain, &cli.chain)
+        }
+        Command::Balance { address } => {
+            println!(
+                "{}",
+                Amount::new(blockchain.get_balance_of_address(&address))
+            );
+            ExitCode::SUCCESS
+        }
+        Command::Validate => match blockchain.validate() {
+            Ok(()) => {
+                println!("The chain is valid");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                println!("The chain is NOT valid: {}", err);
+                ExitCode::FAILURE
             }
+        },
+        Command::Status => {
+            println!("{}", blockchain.stats());
+            ExitCode::SUCCESS
         }
-        Err(err) => println!("Error verifying transaction: {}", err),
     }
+}
 
-    match blockchain.add_transaction(transaction) {
-        Ok(()) => {
-            println!("Transaction added to the chain pending transactions!");
+/// Saves the chain to its file.
+///
+/// # Parameters
+///
+/// * `blockchain` - The chain to save
+/// * `path` - The path of the chain file
+///
+/// # Returns
+///
+/// `ExitCode::SUCCESS` if the chain was saved, `ExitCode::FAILURE` otherwise.
+fn save(blockchain: &Blockchain, path: &Path) -> ExitCode {
+    match blockchain.save_to_file(path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Could not save {}: {}", path.display(), err);
+            ExitCode::FAILURE
         }
-        Err(_err) => println!("Invalid Transaction!"),
     }
-
-    println!("Starting the miner...");
-
-    blockchain.mine_pending_transactions(my_wallet_address.to_string());
-
-    println!(
-        "Balance of my wallet address: {}",
-        blockchain.get_balance_of_address(my_wallet_address)
-    );
-
-    println!("Is the chain valid? {}", blockchain.is_valid());
 }
@@ -0,0 +1,601 @@
+use crate::block::*;
+use crate::blockchain::*;
+use crate::transaction::*;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The largest message accepted from a peer, in bytes.
+pub const MAX_MESSAGE_SIZE: u32 = 64 * 1024 * 1024;
+
+/// The most bytes allocated for a message before they are received, so a peer
+/// announcing a large message without sending it cannot exhaust the memory.
+pub const MAX_MESSAGE_PREALLOCATION: usize = 64 * 1024;
+
+/// The number of transaction hashes remembered as seen by a node.
+pub const MAX_SEEN_TRANSACTIONS: usize = 10_000;
+
+/// Message enum.
+///
+/// Represents a message exchanged between nodes. On the wire, each message is
+/// a big-endian u32 length followed by the JSON encoded message.
+///
+/// # Variants
+///
+/// * `GetHeight` - Asks the peer for the height and total work of its chain.
+/// * `Height` - Answers GetHeight with the height and total work of the chain.
+/// * `GetBlocks` - Asks the peer for all the blocks of its chain.
+/// * `Blocks` - Answers GetBlocks with all the blocks of the chain.
+/// * `NewBlock` - Announces a newly mined or accepted block.
+/// * `Transaction` - Relays a transaction accepted into the pending transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    GetHeight,
+    Height { height: u64, total_work: u128 },
+    GetBlocks,
+    Blocks(Vec<Block>),
+    NewBlock(Block),
+    Transaction(Transaction),
+}
+
+/// Writes a length-prefixed JSON message.
+///
+/// # Parameters
+///
+/// * `writer` - The stream to write to
+/// * `message` - The message to write
+///
+/// # Returns
+///
+/// `io::Result<()>`
+///
+/// - `Ok(())` if the message was written
+/// - `Err(io::Error)` if serialization or writing failed, or the message is too large
+pub fn write_message(writer: &mut impl Write, message: &Message) -> io::Result<()> {
+    let json = serde_json::to_vec(message)?;
+    let length = u32::try_from(json.len())
+        .ok()
+        .filter(|&length| length <= MAX_MESSAGE_SIZE)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Message is too large"))?;
+
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&json)?;
+    writer.flush()
+}
+
+/// Reads a length-prefixed JSON message.
+///
+/// # Parameters
+///
+/// * `reader` - The stream to read from
+///
+/// # Returns
+///
+/// `io::Result<Message>`
+///
+/// - `Ok(Message)` with the message read
+/// - `Err(io::Error)` if reading or parsing failed, or the announced length
+///   exceeds MAX_MESSAGE_SIZE
+///
+/// # Functionality
+///
+/// - Allocates at most MAX_MESSAGE_PREALLOCATION bytes up front, the buffer
+///   growing with the bytes actually received
+pub fn read_message(reader: &mut impl Read) -> io::Result<Message> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length);
+
+    if length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Message is too large",
+        ));
+    }
+
+    let mut json = Vec::with_capacity((length as usize).min(MAX_MESSAGE_PREALLOCATION));
+    reader.take(length as u64).read_to_end(&mut json)?;
+
+    if json.len() != length as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Message is truncated",
+        ));
+    }
+
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Peer struct.
+///
+/// Represents a connection to another node.
+///
+/// # Fields
+///
+/// * `addr` - The address of the peer
+/// * `writer` - The stream messages are sent on, locked so messages never interleave
+struct Peer {
+    addr: SocketAddr,
+    writer: Mutex<TcpStream>,
+}
+
+impl Peer {
+    /// Sends a message to the peer.
+    ///
+    /// # Parameters
+    ///
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<()>` - The result of writing the message
+    fn send(&self, message: &Message) -> io::Result<()> {
+        let mut writer = self.writer.lock().expect("Peer writer lock poisoned");
+        write_message(&mut *writer, message)
+    }
+}
+
+/// SeenTransactions struct.
+///
+/// Remembers the hashes of the latest transactions accepted by a node, so a
+/// transaction is never relayed twice. The oldest hash is forgotten once
+/// MAX_SEEN_TRANSACTIONS hashes are remembered.
+///
+/// # Fields
+///
+/// * `hashes` - The remembered hashes
+/// * `order` - The remembered hashes, oldest first
+#[derive(Debug, Default)]
+struct SeenTransactions {
+    hashes: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenTransactions {
+    /// Checks a transaction hash is remembered.
+    ///
+    /// # Parameters
+    ///
+    /// * `hash` - The transaction hash
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the hash is remembered, False otherwise
+    fn contains(&self, hash: &str) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    /// Remembers a transaction hash.
+    ///
+    /// # Parameters
+    ///
+    /// * `hash` - The transaction hash
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the hash was not remembered yet, False otherwise
+    ///
+    /// # Functionality
+    ///
+    /// - Forgets the oldest hash if MAX_SEEN_TRANSACTIONS hashes are remembered
+    fn insert(&mut self, hash: String) -> bool {
+        if self.hashes.contains(&hash) {
+            return false;
+        }
+
+        if self.order.len() >= MAX_SEEN_TRANSACTIONS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+
+        self.hashes.insert(hash.clone());
+        self.order.push_back(hash);
+        true
+    }
+}
+
+/// Shared struct.
+///
+/// Holds the state shared by a node and its connection threads.
+///
+/// # Fields
+///
+/// * `blockchain` - The chain of the node
+/// * `peers` - The connected peers
+/// * `seen_transactions` - The hashes of the latest transactions accepted, and
+///   so relayed, by the node
+struct Shared {
+    blockchain: Arc<Mutex<Blockchain>>,
+    peers: Arc<Mutex<Vec<Arc<Peer>>>>,
+    seen_transactions: Arc<Mutex<SeenTransactions>>,
+}
+
+/// Node struct.
+///
+/// Represents a network node syncing its chain with its peers over TCP.
+///
+/// # Fields
+///
+/// * `shared` - The state shared with the connection threads
+/// * `local_addr` - The address the node accepts connections on
+pub struct Node {
+    shared: Arc<Shared>,
+    local_addr: SocketAddr,
+}
+
+impl Node {
+    /// Starts a node accepting peer connections.
+    ///
+    /// # Parameters
+    ///
+    /// * `addr` - The address to listen on, e.g. "127.0.0.1:0" for any free port
+    /// * `blockchain` - The chain of the node
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<Node>`
+    ///
+    /// - `Ok(Node)` with the started node
+    /// - `Err(io::Error)` if the address could not be bound
+    ///
+    /// # Functionality
+    ///
+    /// - Binds a TCP listener to the address
+    /// - Subscribes to the chain so every block it mines or accepts from a peer is
+    ///   broadcast to the peers
+    /// - Subscribes to the chain so every transaction accepted by add_transaction(),
+    ///   added locally or relayed by a peer, is marked seen and relayed to the peers
+    /// - Spawns a thread accepting connections, each handled like connect()
+    pub fn bind(addr: impl ToSocketAddrs, mut blockchain: Blockchain) -> io::Result<Node> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+
+        let peers: Arc<Mutex<Vec<Arc<Peer>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let broadcast_peers = Arc::clone(&peers);
+        blockchain.subscribe(Box::new(move |block| {
+            broadcast(&broadcast_peers, &Message::NewBlock(block.clone()), None);
+        }));
+
+        let seen_transactions: Arc<Mutex<SeenTransactions>> =
+            Arc::new(Mutex::new(SeenTransactions::default()));
+
+        let relay_peers = Arc::clone(&peers);
+        let relay_seen = Arc::clone(&seen_transactions);
+        blockchain.subscribe_transactions(Box::new(move |transaction| {
+            let is_new = relay_seen
+                .lock()
+                .expect("Seen transactions lock poisoned")
+                .insert(transaction.compute_hash());
+
+            if is_new {
+                broadcast(
+                    &relay_peers,
+                    &Message::Transaction(transaction.clone()),
+                    None,
+                );
+            }
+        }));
+
+        let shared = Arc::new(Shared {
+            blockchain: Arc::new(Mutex::new(blockchain)),
+            peers,
+            seen_transactions,
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = add_peer(&accept_shared, stream) {
+                            warn!("Could not accept peer: {}", err);
+                        }
+                    }
+                    Err(err) => warn!("Could not accept connection: {}", err),
+                }
+            }
+        });
+
+        Ok(Node { shared, local_addr })
+    }
+
+    /// Gets the address the node accepts connections on.
+    ///
+    /// # Returns
+    ///
+    /// The bound socket address
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Gets the chain of the node.
+    ///
+    /// Blocks mined with mine_pending_transactions() and transactions added with
+    /// add_transaction() on the chain are broadcast to the peers.
+    ///
+    /// # Returns
+    ///
+    /// The shared chain
+    pub fn blockchain(&self) -> Arc<Mutex<Blockchain>> {
+        Arc::clone(&self.shared.blockchain)
+    }
+
+    /// Gets the number of connected peers.
+    ///
+    /// # Returns
+    ///
+    /// The number of peers as a usize
+    pub fn peer_count(&self) -> usize {
+        self.shared.peers.lock().expect("Peers lock poisoned").len()
+    }
+
+    /// Connects to a peer.
+    ///
+    /// # Parameters
+    ///
+    /// * `peer_addr` - The address of the peer node
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<()>`
+    ///
+    /// - `Ok(())` if the peer was connected
+    /// - `Err(io::Error)` if the connection failed
+    ///
+    /// # Functionality
+    ///
+    /// - Opens a TCP connection to the peer
+    /// - Registers the peer and asks for its chain height and work, so a heavier chain is synced
+    /// - Spawns a thread handling the messages of the peer
+    pub fn connect(&self, peer_addr: impl ToSocketAddrs) -> io::Result<()> {
+        add_peer(&self.shared, TcpStream::connect(peer_addr)?)
+    }
+}
+
+/// Registers a connected peer and starts handling its messages.
+///
+/// # Parameters
+///
+/// * `shared` - The state of the node
+/// * `stream` - The connection to the peer
+///
+/// # Returns
+///
+/// `io::Result<()>` - The result of setting up the connection
+fn add_peer(shared: &Arc<Shared>, stream: TcpStream) -> io::Result<()> {
+    let peer = Arc::new(Peer {
+        addr: stream.peer_addr()?,
+        writer: Mutex::new(stream.try_clone()?),
+    });
+
+    shared
+        .peers
+        .lock()
+        .expect("Peers lock poisoned")
+        .push(Arc::clone(&peer));
+
+    peer.send(&Message::GetHeight)?;
+
+    let shared = Arc::clone(shared);
+    thread::spawn(move || {
+        let mut reader = stream;
+
+        loop {
+            let message = match read_message(&mut reader) {
+                Ok(message) => message,
+                Err(err) => {
+                    debug!("Peer {} disconnected: {}", peer.addr, err);
+                    break;
+                }
+            };
+
+            if let Err(err) = handle_message(&shared, &peer, message) {
+                warn!("Could not answer peer {}: {}", peer.addr, err);
+                break;
+            }
+        }
+
+        remove_peer(&shared.peers, &peer);
+    });
+
+    Ok(())
+}
+
+/// Unregisters a peer.
+///
+/// # Parameters
+///
+/// * `peers` - The connected peers
+/// * `peer` - The peer to remove
+fn remove_peer(peers: &Mutex<Vec<Arc<Peer>>>, peer: &Arc<Peer>) {
+    peers
+        .lock()
+        .expect("Peers lock poisoned")
+        .retain(|other| !Arc::ptr_eq(other, peer));
+}
+
+/// Sends a message to all connected peers.
+///
+/// # Parameters
+///
+/// * `peers` - The connected peers
+/// * `message` - The message to send
+/// * `except` - A peer not to send the message to, e.g. the peer that sent it
+///
+/// # Functionality
+///
+/// - Sends the message to each peer but the excepted one
+/// - Disconnects the peers the message could not be sent to
+fn broadcast(peers: &Mutex<Vec<Arc<Peer>>>, message: &Message, except: Option<&Arc<Peer>>) {
+    let mut peers = peers.lock().expect("Peers lock poisoned");
+
+    peers.retain(|peer| {
+        if except.is_some_and(|except| Arc::ptr_eq(peer, except)) {
+            return true;
+        }
+
+        match peer.send(message) {
+            Ok(()) => true,
+            Err(err) => {
+                debug!("Dropping peer {}: {}", peer.addr, err);
+                false
+            }
+        }
+    });
+}
+
+/// Handles a message received from a peer.
+///
+/// # Parameters
+///
+/// * `shared` - The state of the node
+/// * `peer` - The peer that sent the message
+/// * `message` - The received message
+///
+/// # Returns
+///
+/// `io::Result<()>` - The result of answering the peer
+///
+/// # Functionality
+///
+/// - Answers GetHeight and GetBlocks with the height and blocks of the chain
+/// - Asks for the blocks of a peer announcing a chain with more total work
+/// - Calls replace_chain() with the blocks of a peer, keeping the chain if the
+///   offered one is not longer or is invalid, and announces the new tip to the
+///   other peers if the chain was replaced
+/// - Calls accept_block() with an announced block extending or competing with the
+///   chain tip, the chain subscription relaying it to the peers if it was added
+/// - Asks for the blocks of a peer announcing a block further ahead
+/// - Adds a relayed transaction not seen before to the pending transactions, the
+///   transaction subscription marking it seen and relaying it to the peers only if
+///   add_transaction() accepted it, so a transaction rejected before its funding
+///   block arrived is accepted when relayed again
+fn handle_message(shared: &Shared, peer: &Arc<Peer>, message: Message) -> io::Result<()> {
+    match message {
+        Message::GetHeight => {
+            let blockchain = shared.blockchain.lock().expect("Blockchain lock poisoned");
+            let message = Message::Height {
+                height: blockchain.height(),
+                total_work: blockchain.total_work(),
+            };
+            drop(blockchain);
+
+            peer.send(&message)
+        }
+        Message::Height { total_work, .. } => {
+            let our_work = shared
+                .blockchain
+                .lock()
+                .expect("Blockchain lock poisoned")
+                .total_work();
+
+            if total_work > our_work {
+                peer.send(&Message::GetBlocks)?;
+            }
+            Ok(())
+        }
+        Message::GetBlocks => {
+            let blocks = shared
+                .blockchain
+                .lock()
+                .expect("Blockchain lock poisoned")
+                .blocks()
+                .cloned()
+                .collect();
+            peer.send(&Message::Blocks(blocks))
+        }
+        Message::Blocks(blocks) => {
+            let mut blockchain = shared.blockchain.lock().expect("Blockchain lock poisoned");
+
+            match blockchain.replace_chain(blocks) {
+                Ok(true) => {
+                    let latest_block = blockchain.get_latest_block().clone();
+                    drop(blockchain);
+                    broadcast(&shared.peers, &Message::NewBlock(latest_block), Some(peer));
+                }
+                Ok(false) => {}
+                Err(err) => warn!("Peer {} offered an invalid chain: {}", peer.addr, err),
+            }
+            Ok(())
+        }
+        Message::NewBlock(block) => {
+            let mut blockchain = shared.blockchain.lock().expect("Blockchain lock poisoned");
+
+            if block.index > blockchain.get_latest_block().index + 1 {
+                drop(blockchain);
+                return peer.send(&Message::GetBlocks);
+            }
+
+            if let Err(err) = blockchain.accept_block(block) {
+                warn!("Peer {} announced an invalid block: {}", peer.addr, err);
+            }
+            Ok(())
+        }
+        Message::Transaction(transaction) => {
+            let is_seen = shared
+                .seen_transactions
+                .lock()
+                .expect("Seen transactions lock poisoned")
+                .contains(&transaction.compute_hash());
+
+            if is_seen {
+                return Ok(());
+            }
+
+            let added = shared
+                .blockchain
+                .lock()
+                .expect("Blockchain lock poisoned")
+                .add_transaction(transaction);
+
+            if let Err(err) = added {
+                warn!("Peer {} relayed an invalid transaction: {}", peer.addr, err);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn announced_length_without_the_bytes_is_an_error() {
+        let mut bytes = MAX_MESSAGE_SIZE.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"\"GetHeight\"");
+
+        let err = read_message(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn message_round_trips_through_the_wire_format() {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, &Message::GetBlocks).unwrap();
+
+        assert!(matches!(
+            read_message(&mut bytes.as_slice()),
+            Ok(Message::GetBlocks)
+        ));
+    }
+
+    #[test]
+    fn seen_transactions_forget_the_oldest_hash() {
+        let mut seen = SeenTransactions::default();
+        for position in 0..=MAX_SEEN_TRANSACTIONS {
+            assert!(seen.insert(position.to_string()));
+        }
+
+        assert!(!seen.contains("0"));
+        assert!(seen.contains(&MAX_SEEN_TRANSACTIONS.to_string()));
+        assert_eq!(seen.order.len(), MAX_SEEN_TRANSACTIONS);
+        assert!(!seen.insert("1".to_string()));
+    }
+}
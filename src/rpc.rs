@@ -0,0 +1,365 @@
+use crate::address::*;
+use crate::blockchain::*;
+use crate::transaction::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// The error code of a request body that is not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// The error code of a JSON value that is not a JSON-RPC 2.0 request.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// The error code of an unknown method.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The error code of missing or malformed method parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The error code of a transaction rejected by the chain.
+pub const TRANSACTION_REJECTED: i64 = -32000;
+
+/// The error code of a block height the chain has not reached.
+pub const BLOCK_NOT_FOUND: i64 = -32001;
+
+/// RpcRequest struct.
+///
+/// Represents a JSON-RPC 2.0 request.
+///
+/// # Fields
+///
+/// * `jsonrpc` - The protocol version, always "2.0"
+/// * `method` - The name of the method to call
+/// * `params` - The positional parameters of the method, null if absent
+/// * `id` - The id echoed in the response, None for a notification, which gets no response
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default, deserialize_with = "deserialize_id")]
+    pub id: Option<Value>,
+}
+
+/// Deserializes a request id, so an explicit null id is told apart from a missing one.
+fn deserialize_id<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// RpcError struct.
+///
+/// Represents the error object of a failed JSON-RPC 2.0 call.
+///
+/// # Fields
+///
+/// * `code` - The error code, e.g. METHOD_NOT_FOUND
+/// * `message` - The description of the error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    /// Creates a new RpcError instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `code` - The error code
+    /// * `message` - The description of the error
+    ///
+    /// # Returns
+    ///
+    /// A new RpcError instance
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// RpcServer struct.
+///
+/// Answers JSON-RPC 2.0 requests on a chain shared with other threads.
+///
+/// # Fields
+///
+/// * `blockchain` - The shared chain, locked for the duration of each call
+#[derive(Clone)]
+pub struct RpcServer {
+    blockchain: Arc<Mutex<Blockchain>>,
+}
+
+impl RpcServer {
+    /// Creates a new RpcServer instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `blockchain` - The shared chain
+    ///
+    /// # Returns
+    ///
+    /// A new RpcServer instance
+    pub fn new(blockchain: Arc<Mutex<Blockchain>>) -> Self {
+        Self { blockchain }
+    }
+
+    /// Answers a JSON-RPC 2.0 request.
+    ///
+    /// # Parameters
+    ///
+    /// * `body` - The JSON request
+    ///
+    /// # Returns
+    ///
+    /// The JSON response, or None for a notification
+    ///
+    /// # Functionality
+    ///
+    /// - Locks the chain and calls handle_request()
+    pub fn handle(&self, body: &str) -> Option<String> {
+        let mut blockchain = self.blockchain.lock().expect("Blockchain lock poisoned");
+        handle_request(&mut blockchain, body)
+    }
+}
+
+/// Answers a JSON-RPC 2.0 request.
+///
+/// # Parameters
+///
+/// * `blockchain` - The chain
+/// * `body` - The JSON request
+///
+/// # Returns
+///
+/// The JSON response with the result or error object of the call and the
+/// request id, or None for a notification
+///
+/// # Functionality
+///
+/// - Answers PARSE_ERROR if the body is not JSON, and INVALID_REQUEST if it is
+///   not a JSON-RPC 2.0 request, with a null id
+/// - Calls the method with call()
+/// - Returns None if the request has no id, after calling the method
+pub fn handle_request(blockchain: &mut Blockchain, body: &str) -> Option<String> {
+    let value: Value = match serde_json::from_str(body) {
+        Ok(value) => value,
+        Err(err) => {
+            let error = RpcError::new(PARSE_ERROR, format!("Parse error: {}", err));
+            return Some(response(Value::Null, Err(error)));
+        }
+    };
+
+    let request = match serde_json::from_value::<RpcRequest>(value) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => {
+            let error = RpcError::new(INVALID_REQUEST, "Invalid request");
+            return Some(response(Value::Null, Err(error)));
+        }
+    };
+
+    let result = call(blockchain, &request.method, &request.params);
+
+    request.id.map(|id| response(id, result))
+}
+
+/// Calls a method on the chain.
+///
+/// # Parameters
+///
+/// * `blockchain` - The chain
+/// * `method` - The name of the method
+/// * `params` - The positional parameters of the method
+///
+/// # Returns
+///
+/// `Result<Value, RpcError>`
+///
+/// - `Ok(Value)` with the result of the method
+/// - `Err(RpcError)` if the method is unknown, the parameters are invalid or the
+///   call failed
+///
+/// # Methods
+///
+/// * `getblockcount` - Returns the height of the chain
+/// * `getblock [height]` - Returns the block at the height
+/// * `getbalance [address]` - Returns the balance of the address, in smallest units
+/// * `sendtransaction [transaction]` - Adds a signed transaction to the pending
+///   transactions and returns its hash
+/// * `getmempoolinfo` - Returns the number of pending transactions, their size in
+///   bytes and their total fee
+/// * `estimatefee [blocks]` - Returns the fee a transaction needs to be mined within
+///   the number of blocks, in smallest units
+pub fn call(blockchain: &mut Blockchain, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "getblockcount" => Ok(json!(blockchain.height())),
+        "getblock" => {
+            let height: u64 = param(params, 0)?;
+
+            blockchain
+                .block_at(height)
+                .map(|block| json!(block))
+                .ok_or_else(|| RpcError::new(BLOCK_NOT_FOUND, "Block not found"))
+        }
+        "getbalance" => {
+            let address: Address = param(params, 0)?;
+            Ok(json!(blockchain.get_balance_of_address(&address)))
+        }
+        "sendtransaction" => {
+            let transaction: Transaction = param(params, 0)?;
+            let hash = transaction.compute_hash();
+
+            blockchain
+                .add_transaction(transaction)
+                .map(|()| json!(hash))
+                .map_err(|err| RpcError::new(TRANSACTION_REJECTED, err.to_string()))
+        }
+        "getmempoolinfo" => {
+            let pending = blockchain.pending();
+
+            Ok(json!({
+                "size": pending.len(),
+                "bytes": pending.iter().map(Transaction::serialized_size).sum::<usize>(),
+                "total_fee": pending.iter().map(|transaction| transaction.fee).fold(0u64, u64::saturating_add),
+            }))
+        }
+        "estimatefee" => {
+            let within_blocks: u64 = param(params, 0)?;
+            Ok(json!(blockchain.estimate_fee(within_blocks)))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+/// Gets a positional parameter of a call.
+///
+/// # Parameters
+///
+/// * `params` - The parameters of the call
+/// * `position` - The position of the parameter
+///
+/// # Returns
+///
+/// `Result<T, RpcError>`
+///
+/// - `Ok(T)` with the parameter
+/// - `Err(RpcError)` with INVALID_PARAMS if the parameter is missing or malformed
+fn param<T: serde::de::DeserializeOwned>(params: &Value, position: usize) -> Result<T, RpcError> {
+    let value = params
+        .get(position)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Missing parameter {}", position)))?;
+
+    T::deserialize(value).map_err(|err| {
+        RpcError::new(
+            INVALID_PARAMS,
+            format!("Invalid parameter {}: {}", position, err),
+        )
+    })
+}
+
+/// Builds a JSON-RPC 2.0 response.
+///
+/// # Parameters
+///
+/// * `id` - The id of the request
+/// * `result` - The result or error of the call
+///
+/// # Returns
+///
+/// The JSON response string
+fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => json!({ "jsonrpc": "2.0", "error": error, "id": id }),
+    };
+
+    response.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::address;
+
+    #[test]
+    fn getblockcount_returns_the_chain_height() {
+        let miner = address(1);
+        let blockchain = Arc::new(Mutex::new(Blockchain::new(1, 100)));
+        for _ in 0..2 {
+            blockchain
+                .lock()
+                .unwrap()
+                .mine_pending_transactions(miner)
+                .unwrap();
+        }
+        let server = RpcServer::new(Arc::clone(&blockchain));
+
+        let response = server
+            .handle(r#"{"jsonrpc":"2.0","method":"getblockcount","params":[],"id":7}"#)
+            .unwrap();
+
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(
+            response["result"],
+            json!(blockchain.lock().unwrap().height())
+        );
+        assert_eq!(response["result"], json!(2));
+        assert_eq!(response["id"], json!(7));
+    }
+
+    #[test]
+    fn failed_requests_get_error_objects() {
+        let mut blockchain = Blockchain::new(1, 100);
+        let mut error_code = |body: &str| {
+            let response = handle_request(&mut blockchain, body).unwrap();
+            serde_json::from_str::<Value>(&response).unwrap()["error"]["code"].clone()
+        };
+
+        assert_eq!(error_code("{"), json!(PARSE_ERROR));
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"1.0","method":"getblockcount","id":1}"#),
+            json!(INVALID_REQUEST)
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","method":"mine","id":1}"#),
+            json!(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","method":"getblock","params":["one"],"id":1}"#),
+            json!(INVALID_PARAMS)
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","method":"getblock","params":[5],"id":1}"#),
+            json!(BLOCK_NOT_FOUND)
+        );
+        assert!(handle_request(
+            &mut blockchain,
+            r#"{"jsonrpc":"2.0","method":"getblockcount"}"#
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn getbalance_accepts_compressed_and_uncompressed_addresses() {
+        let miner = address(1);
+        let mut blockchain = Blockchain::new(1, 100);
+        blockchain.mine_pending_transactions(miner).unwrap();
+        let uncompressed = hex::encode(miner.public_key().serialize_uncompressed());
+
+        for encoded in [miner.to_string(), uncompressed] {
+            assert_eq!(
+                call(&mut blockchain, "getbalance", &json!([encoded])),
+                Ok(json!(100))
+            );
+        }
+        assert_eq!(
+            call(&mut blockchain, "getbalance", &json!(["not a key"])).map_err(|err| err.code),
+            Err(INVALID_PARAMS)
+        );
+    }
+}
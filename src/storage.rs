@@ -0,0 +1,533 @@
+use crate::block::*;
+use crate::blockchain::Checkpoint;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Storage trait.
+///
+/// Represents where the blocks of a chain are kept, keyed by their height.
+/// The heights of the stored blocks are contiguous, starting at 0 for the
+/// genesis block.
+pub trait Storage {
+    /// Stores a block at its height.
+    ///
+    /// # Parameters
+    ///
+    /// * `block` - The block to store, at most one past the tip height
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), io::Error>`
+    ///
+    /// - `Ok(())` if the block was stored
+    /// - `Err(io::Error)` if the block could not be stored, the storage being left unchanged
+    ///
+    /// # Functionality
+    ///
+    /// - Appends the block if its index is the number of stored blocks
+    /// - Replaces the stored block with the same index otherwise
+    fn put_block(&mut self, block: Block) -> Result<(), io::Error>;
+
+    /// Gets the block at a height.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Option<&Block>, io::Error>`
+    ///
+    /// - `Ok(Some(&Block))` with the block at the height
+    /// - `Ok(None)` if no block is stored at the height
+    /// - `Err(io::Error)` if the stored block could not be read
+    fn get_block(&self, height: u64) -> Result<Option<&Block>, io::Error>;
+
+    /// Reads the block at a height without keeping it in the storage.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Option<Block>, io::Error>`
+    ///
+    /// - `Ok(Some(Block))` with a copy of the block at the height
+    /// - `Ok(None)` if no block is stored at the height
+    /// - `Err(io::Error)` if the stored block could not be read
+    fn read_block(&self, height: u64) -> Result<Option<Block>, io::Error> {
+        self.get_block(height).map(|block| block.cloned())
+    }
+
+    /// Gets the block with the greatest height.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Option<&Block>, io::Error>`
+    ///
+    /// - `Ok(Some(&Block))` with the latest stored block
+    /// - `Ok(None)` if the storage is empty
+    /// - `Err(io::Error)` if the latest block could not be read
+    fn tip(&self) -> Result<Option<&Block>, io::Error>;
+
+    /// Gets the number of stored blocks.
+    ///
+    /// # Returns
+    ///
+    /// The number of blocks as a u64, the tip height plus one
+    fn len(&self) -> u64;
+
+    /// Checks if no block is stored.
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the storage is empty, False otherwise
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the blocks from a height onwards.
+    ///
+    /// # Parameters
+    ///
+    /// * `len` - The number of blocks to keep
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), io::Error>`
+    ///
+    /// - `Ok(())` if the blocks were dropped
+    /// - `Err(io::Error)` if a block could not be dropped, the blocks above it
+    ///   being dropped already
+    fn truncate(&mut self, len: u64) -> Result<(), io::Error>;
+
+    /// Stores the pruning checkpoint of the chain.
+    ///
+    /// # Parameters
+    ///
+    /// * `checkpoint` - The checkpoint covering the pruned blocks, replacing any stored one
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), io::Error>`
+    ///
+    /// - `Ok(())` if the checkpoint was stored
+    /// - `Err(io::Error)` if the checkpoint could not be stored, the storage being left unchanged
+    fn put_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), io::Error>;
+
+    /// Gets the pruning checkpoint of the chain.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Option<Checkpoint>, io::Error>`
+    ///
+    /// - `Ok(Some(Checkpoint))` with the stored checkpoint
+    /// - `Ok(None)` if the chain was never pruned
+    /// - `Err(io::Error)` if the stored checkpoint could not be read
+    fn checkpoint(&self) -> Result<Option<Checkpoint>, io::Error>;
+
+    /// Iterates over the blocks from a height to the tip.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the first block
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the blocks, in height order, or the errors reading them
+    fn blocks_from(&self, height: u64) -> Box<dyn Iterator<Item = Result<&Block, io::Error>> + '_> {
+        Box::new((height..self.len()).map_while(move |height| self.get_block(height).transpose()))
+    }
+}
+
+/// MemoryStorage struct.
+///
+/// A storage keeping the blocks in memory, in a map from height to block.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    blocks: BTreeMap<u64, Block>,
+    checkpoint: Option<Checkpoint>,
+}
+
+impl MemoryStorage {
+    /// Creates a new empty MemoryStorage instance.
+    ///
+    /// # Returns
+    ///
+    /// A new MemoryStorage instance without blocks
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<Vec<Block>> for MemoryStorage {
+    /// Stores blocks in chain order, the first one at height 0.
+    fn from(blocks: Vec<Block>) -> Self {
+        Self {
+            blocks: (0..).zip(blocks).collect(),
+            checkpoint: None,
+        }
+    }
+}
+
+/// Storing blocks in memory never fails.
+impl Storage for MemoryStorage {
+    fn put_block(&mut self, block: Block) -> Result<(), io::Error> {
+        self.blocks.insert(block.index, block);
+        Ok(())
+    }
+
+    fn get_block(&self, height: u64) -> Result<Option<&Block>, io::Error> {
+        Ok(self.blocks.get(&height))
+    }
+
+    fn tip(&self) -> Result<Option<&Block>, io::Error> {
+        Ok(self.blocks.last_key_value().map(|(_, block)| block))
+    }
+
+    fn len(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), io::Error> {
+        self.blocks.split_off(&len);
+        Ok(())
+    }
+
+    fn put_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), io::Error> {
+        self.checkpoint = Some(checkpoint.clone());
+        Ok(())
+    }
+
+    fn checkpoint(&self) -> Result<Option<Checkpoint>, io::Error> {
+        Ok(self.checkpoint.clone())
+    }
+
+    fn blocks_from(&self, height: u64) -> Box<dyn Iterator<Item = Result<&Block, io::Error>> + '_> {
+        Box::new(self.blocks.range(height..).map(|(_, block)| Ok(block)))
+    }
+}
+
+/// FileStorage struct.
+///
+/// A storage keeping each block in its own JSON file in a directory, named
+/// after the block height, and the pruning checkpoint in `checkpoint.json`.
+/// Blocks are written as they are stored and read from their file when they
+/// are requested. A block read is kept until the next block is stored, so
+/// only the blocks requested since then are in memory.
+///
+/// # Fields
+///
+/// * `dir` - The directory holding the block files
+/// * `blocks` - One slot per stored block, filled once the block has been read
+#[derive(Debug)]
+pub struct FileStorage {
+    dir: PathBuf,
+    blocks: Vec<OnceLock<Block>>,
+}
+
+impl FileStorage {
+    /// Opens the storage in a directory, creating it if it does not exist.
+    ///
+    /// # Parameters
+    ///
+    /// * `dir` - The directory holding the block files
+    ///
+    /// # Returns
+    ///
+    /// `Result<FileStorage, io::Error>`
+    ///
+    /// - `Ok(FileStorage)` with the blocks found in the directory
+    /// - `Err(io::Error)` if the directory could not be created
+    ///
+    /// # Functionality
+    ///
+    /// - Creates the directory and its parents
+    /// - Counts the block files from height 0 up to the first missing one,
+    ///   without reading them
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut storage = Self {
+            dir,
+            blocks: Vec::new(),
+        };
+
+        while storage.block_path(storage.len()).is_file() {
+            storage.blocks.push(OnceLock::new());
+        }
+
+        Ok(storage)
+    }
+
+    /// Gets the path of the file of a block.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    ///
+    /// # Returns
+    ///
+    /// The path `<dir>/<height>.json`
+    fn block_path(&self, height: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", height))
+    }
+
+    /// Gets the path of the file of the pruning checkpoint.
+    ///
+    /// # Returns
+    ///
+    /// The path `<dir>/checkpoint.json`
+    fn checkpoint_path(&self) -> PathBuf {
+        self.dir.join("checkpoint.json")
+    }
+
+    /// Writes a value to a JSON file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file
+    /// * `value` - The block or checkpoint to write
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), io::Error>`
+    ///
+    /// - `Ok(())` if the value was written
+    /// - `Err(io::Error)` if the file could not be written
+    ///
+    /// # Functionality
+    ///
+    /// - Writes the JSON value to a temporary file and renames it over the
+    ///   file, so the file is never left half written
+    fn write_json(path: &Path, value: &impl Serialize) -> Result<(), io::Error> {
+        let temp_path = path.with_extension("json.tmp");
+
+        fs::write(&temp_path, serde_json::to_vec(value)?)?;
+        fs::rename(temp_path, path)
+    }
+
+    /// Reads a block from its file.
+    ///
+    /// # Parameters
+    ///
+    /// * `height` - The index of the block
+    ///
+    /// # Returns
+    ///
+    /// `Result<Block, io::Error>`
+    ///
+    /// - `Ok(Block)` with the block read
+    /// - `Err(io::Error)` if the file could not be read or is not a JSON block
+    fn read_block_file(&self, height: u64) -> Result<Block, io::Error> {
+        let json = fs::read_to_string(self.block_path(height))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+impl Storage for FileStorage {
+    /// Writes the block file, then drops the blocks read from their files so
+    /// far. A block whose file could not be written is not stored.
+    fn put_block(&mut self, block: Block) -> Result<(), io::Error> {
+        Self::write_json(&self.block_path(block.index), &block)?;
+
+        for slot in &mut self.blocks {
+            slot.take();
+        }
+        if block.index == self.len() {
+            self.blocks.push(OnceLock::new());
+        }
+
+        Ok(())
+    }
+
+    /// Reads the block file the first time the block is requested since the
+    /// last block was stored.
+    fn get_block(&self, height: u64) -> Result<Option<&Block>, io::Error> {
+        let Some(slot) = usize::try_from(height)
+            .ok()
+            .and_then(|position| self.blocks.get(position))
+        else {
+            return Ok(None);
+        };
+
+        if slot.get().is_none() {
+            let _ = slot.set(self.read_block_file(height)?);
+        }
+
+        Ok(slot.get())
+    }
+
+    /// Reads the block file unless the block is in memory already, without
+    /// keeping the block read.
+    fn read_block(&self, height: u64) -> Result<Option<Block>, io::Error> {
+        let Some(slot) = usize::try_from(height)
+            .ok()
+            .and_then(|position| self.blocks.get(position))
+        else {
+            return Ok(None);
+        };
+
+        match slot.get() {
+            Some(block) => Ok(Some(block.clone())),
+            None => self.read_block_file(height).map(Some),
+        }
+    }
+
+    fn tip(&self) -> Result<Option<&Block>, io::Error> {
+        match self.len().checked_sub(1) {
+            Some(height) => self.get_block(height),
+            None => Ok(None),
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    /// Removes the block files from the tip down, keeping the blocks from the
+    /// first file that could not be removed.
+    fn truncate(&mut self, len: u64) -> Result<(), io::Error> {
+        for height in (len..self.len()).rev() {
+            fs::remove_file(self.block_path(height))?;
+            self.blocks.truncate(height as usize);
+        }
+
+        Ok(())
+    }
+
+    fn put_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), io::Error> {
+        Self::write_json(&self.checkpoint_path(), checkpoint)
+    }
+
+    /// Reads the checkpoint file, a missing file meaning the chain was never pruned.
+    fn checkpoint(&self) -> Result<Option<Checkpoint>, io::Error> {
+        match fs::read_to_string(self.checkpoint_path()) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::test_utils::address;
+
+    #[test]
+    fn mined_blocks_are_stored_by_height() {
+        let mut blockchain =
+            Blockchain::with_storage(1, 100, Box::new(MemoryStorage::new())).unwrap();
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(address(1)).unwrap();
+        }
+
+        let mut storage = MemoryStorage::new();
+        for block in blockchain.blocks() {
+            storage.put_block(block.clone()).unwrap();
+        }
+
+        assert_eq!(storage.len(), 4);
+        for (height, block) in (0..).zip(blockchain.blocks()) {
+            assert_eq!(storage.get_block(height).unwrap().unwrap().hash, block.hash);
+        }
+        assert_eq!(
+            storage.tip().unwrap().unwrap().hash,
+            blockchain.get_latest_block().hash
+        );
+        assert!(storage.get_block(4).unwrap().is_none());
+
+        storage.truncate(2).unwrap();
+        assert_eq!(storage.tip().unwrap().unwrap().index, 1);
+    }
+
+    #[test]
+    fn file_backed_chain_reopens_with_the_same_tip() {
+        let dir = std::env::temp_dir().join(format!("cryptobeca-storage-{}", std::process::id()));
+        let open = || Blockchain::with_storage(1, 100, Box::new(FileStorage::open(&dir).unwrap()));
+
+        let mut blockchain = open().unwrap();
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(address(1)).unwrap();
+        }
+        let tip = blockchain.get_latest_block().hash.clone();
+        drop(blockchain);
+
+        let reopened = open().map(|reopened| {
+            (
+                reopened.height(),
+                reopened.get_latest_block().hash.clone(),
+                reopened.get_balance_of_address(&address(1)),
+            )
+        });
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reopened.unwrap(), (3, tip, 300));
+    }
+
+    #[test]
+    fn pruned_file_backed_chain_reopens_with_its_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("cryptobeca-pruned-{}", std::process::id()));
+        let open = || Blockchain::with_storage(1, 100, Box::new(FileStorage::open(&dir).unwrap()));
+
+        let mut blockchain = open().unwrap();
+        for _ in 0..4 {
+            blockchain.mine_pending_transactions(address(1)).unwrap();
+        }
+        blockchain.prune(1).unwrap();
+        let checkpoint = blockchain.pruned_checkpoint.clone();
+        drop(blockchain);
+
+        let reopened = open().map(|reopened| {
+            (
+                reopened.pruned_checkpoint.clone(),
+                reopened.block_at(1).map(|block| block.pruned),
+                reopened.height(),
+                reopened.get_balance_of_address(&address(1)),
+            )
+        });
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(checkpoint.is_some());
+        assert_eq!(reopened.unwrap(), (checkpoint, Some(true), 4, 400));
+    }
+
+    #[test]
+    fn file_storage_reads_blocks_on_demand() {
+        let dir = std::env::temp_dir().join(format!("cryptobeca-on-demand-{}", std::process::id()));
+        let blockchain = Blockchain::new(1, 100);
+        let mut storage = FileStorage::open(&dir).unwrap();
+        storage
+            .put_block(blockchain.get_latest_block().clone())
+            .unwrap();
+        let cached = |storage: &FileStorage| {
+            storage
+                .blocks
+                .iter()
+                .filter(|slot| slot.get().is_some())
+                .count()
+        };
+
+        let read = storage.read_block(0).unwrap();
+        let after_read = cached(&storage);
+        let tip = storage.tip().unwrap().cloned();
+        let after_tip = cached(&storage);
+        storage
+            .put_block(blockchain.get_latest_block().clone())
+            .unwrap();
+        let after_put = cached(&storage);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(read.unwrap().hash, blockchain.genesis_hash());
+        assert_eq!(tip.unwrap().hash, blockchain.genesis_hash());
+        assert_eq!((after_read, after_tip, after_put), (0, 1, 0));
+    }
+}
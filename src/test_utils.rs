@@ -0,0 +1,32 @@
+use crate::address::Address;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+/// Gets a deterministic key and its address, for the tests of every module.
+///
+/// # Parameters
+///
+/// * `seed` - The byte repeated over the 32 bytes of the key, from 1 to 255
+///
+/// # Returns
+///
+/// The secret key and the address of its public key
+pub(crate) fn keypair(seed: u8) -> (SecretKey, Address) {
+    let key = SecretKey::from_slice(&[seed; 32]).expect("Seed is a valid key");
+    (
+        key,
+        PublicKey::from_secret_key(&Secp256k1::new(), &key).into(),
+    )
+}
+
+/// Gets the address of a deterministic key.
+///
+/// # Parameters
+///
+/// * `seed` - The seed of the key, as for keypair()
+///
+/// # Returns
+///
+/// The address of the public key of the key
+pub(crate) fn address(seed: u8) -> Address {
+    keypair(seed).1
+}
@@ -1,7 +1,32 @@
+use std::fmt;
 use std::str::FromStr;
 
-use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey};
-use sha3::{Digest, Sha3_256};
+use crate::address::*;
+use crate::error::*;
+use crate::hasher::*;
+use chrono::{DateTime, Utc};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{All, Message, PublicKey, Secp256k1, SecretKey, Verification};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// The maximum size of a transaction memo accepted by Blockchain::add_transaction(), in bytes.
+pub const MAX_MEMO_BYTES: usize = 80;
+
+/// The secp256k1 context shared by all the signing and verification, created on first use.
+static SECP256K1: OnceLock<Secp256k1<All>> = OnceLock::new();
+
+/// Gets the shared secp256k1 context.
+///
+/// Creating a context allocates and randomizes its tables, so the same context
+/// is reused instead of creating one for each signature.
+///
+/// # Returns
+///
+/// The context, able to both sign and verify
+pub fn secp256k1_context() -> &'static Secp256k1<All> {
+    SECP256K1.get_or_init(Secp256k1::new)
+}
 
 /// Transaction struct.
 ///
@@ -10,17 +35,46 @@ use sha3::{Digest, Sha3_256};
 /// # Fields
 ///
 /// * `from_address` - The sender address. Optional, for mining rewards.
-/// * `to_address` - The recipient address. 
-/// * `amount` - The amount transferred.
-/// * `signature` - The cryptographic signature of the transaction.
+/// * `outputs` - The recipients and the amounts transferred to each of them.
+/// * `fee` - The fee paid by the sender to the miner, in smallest units.
+/// * `nonce` - The sender's sequence number, distinguishing identical transfers.
+/// * `signature` - The hex encoded 65-byte recoverable signature of the transaction,
+///   the 64-byte compact signature followed by the recovery id.
 /// * `hash` - The hash of the transaction.
-#[derive(Debug, Clone)]
+/// * `valid_until` - The index of the last block the transaction can be mined in, None
+///   if it never expires.
+/// * `received_at` - When the transaction entered the pending transactions of this
+///   node, None once mined. Not part of the hash.
+/// * `memo` - An arbitrary payload attached by the sender, e.g. an invoice id, None
+///   if absent. Part of the hash, it does not affect balances.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
-    pub from_address: Option<String>,
-    pub to_address: String,
-    pub amount: f64,
+    pub from_address: Option<Address>,
+    pub outputs: Vec<TransactionOutput>,
+    pub fee: u64,
+    pub nonce: u64,
     pub signature: Option<String>,
     pub hash: Option<String>,
+    #[serde(default)]
+    pub valid_until: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<Vec<u8>>,
+}
+
+/// TransactionOutput struct.
+///
+/// Represents an amount paid to a recipient by a transaction.
+///
+/// # Fields
+///
+/// * `to_address` - The recipient address.
+/// * `amount` - The amount transferred, in smallest units.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionOutput {
+    pub to_address: Address,
+    pub amount: u64,
 }
 
 /// TransactionError enum.
@@ -30,169 +84,1084 @@ pub struct Transaction {
 /// # Variants
 ///
 /// * `InvalidTransaction` - Returned when the transaction is invalid.
+/// * `InsufficientFunds` - Returned when the sender balance does not cover the amount.
+/// * `DuplicateTransaction` - Returned when the transaction is already pending or mined.
+/// * `InvalidNonce` - Returned when the nonce is not greater than the sender's last nonce.
+/// * `HashMismatch` - Returned when the stored hash does not match the transaction contents.
+/// * `TooLarge` - Returned when the transaction does not fit in a block.
+/// * `Expired` - Returned when the next block is past the transaction valid_until height.
+/// * `FeeTooLow` - Returned when the fee is below the chain minimum relay fee.
+/// * `MemoTooLarge` - Returned when the memo is longer than MAX_MEMO_BYTES.
 #[derive(Debug)]
 pub enum TransactionError {
     InvalidTransaction,
+    InsufficientFunds,
+    DuplicateTransaction,
+    InvalidNonce,
+    HashMismatch,
+    TooLarge,
+    Expired,
+    FeeTooLow,
+    MemoTooLarge,
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            TransactionError::InvalidTransaction => "invalid transaction",
+            TransactionError::InsufficientFunds => "insufficient funds",
+            TransactionError::DuplicateTransaction => "duplicate transaction",
+            TransactionError::InvalidNonce => "invalid nonce",
+            TransactionError::HashMismatch => "hash does not match the transaction contents",
+            TransactionError::TooLarge => "transaction does not fit in a block",
+            TransactionError::Expired => "transaction is past its valid_until height",
+            TransactionError::FeeTooLow => "fee is below the minimum relay fee",
+            TransactionError::MemoTooLarge => "memo is larger than the maximum memo size",
+        };
+        write!(f, "{}", reason)
+    }
 }
 
+impl std::error::Error for TransactionError {}
+
 impl Transaction {
-    /// Calculates the hash for the transaction.
+    /// Creates a new unsigned Transaction instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `from` - The sender address, None for mining rewards
+    /// * `to` - The recipient address
+    /// * `amount` - The amount transferred, in smallest units
+    ///
+    /// # Returns
+    ///
+    /// A new Transaction instance with a single output, no fee, a nonce of 0, and no
+    /// signature or hash
+    pub fn new(from: Option<Address>, to: Address, amount: u64) -> Self {
+        Self::with_outputs(
+            from,
+            vec![TransactionOutput {
+                to_address: to,
+                amount,
+            }],
+        )
+    }
+
+    /// Creates a new unsigned Transaction instance paying several recipients.
+    ///
+    /// # Parameters
+    ///
+    /// * `from` - The sender address, None for mining rewards
+    /// * `outputs` - The recipients and the amounts transferred to each of them
+    ///
+    /// # Returns
+    ///
+    /// A new Transaction instance with no fee, a nonce of 0, no expiry, and no signature,
+    /// hash or reception time
+    pub fn with_outputs(from: Option<Address>, outputs: Vec<TransactionOutput>) -> Self {
+        Self {
+            from_address: from,
+            outputs,
+            fee: 0,
+            nonce: 0,
+            signature: None,
+            hash: None,
+            valid_until: None,
+            received_at: None,
+            memo: None,
+        }
+    }
+
+    /// Creates a new mining reward Transaction instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `to` - The address receiving the reward
+    /// * `amount` - The reward amount, in smallest units
+    ///
+    /// # Returns
+    ///
+    /// A new Transaction instance with no sender
+    pub fn coinbase(to: Address, amount: u64) -> Self {
+        Self::new(None, to, amount)
+    }
+
+    /// Gets the total amount transferred by the transaction.
+    ///
+    /// # Returns
+    ///
+    /// The sum of the output amounts in smallest units, not including the fee,
+    /// saturating at u64::MAX so an oversized sum can never be afforded
+    pub fn total_amount(&self) -> u64 {
+        self.outputs
+            .iter()
+            .fold(0u64, |total, output| total.saturating_add(output.amount))
+    }
+
+    /// Gets the total amount transferred by the transaction, failing on overflow.
+    ///
+    /// # Returns
+    ///
+    /// The sum of the output amounts in smallest units, not including the fee,
+    /// or None if it exceeds MAX_AMOUNT
+    pub fn checked_total_amount(&self) -> Option<u64> {
+        self.outputs
+            .iter()
+            .try_fold(0u64, |total, output| total.checked_add(output.amount))
+    }
+
+    /// Gets the amount the transaction pays to the provided address.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The recipient address
+    ///
+    /// # Returns
+    ///
+    /// The sum of the amounts of the outputs to the address in smallest units,
+    /// saturating at u64::MAX
+    pub fn amount_to(&self, address: &Address) -> u64 {
+        self.outputs
+            .iter()
+            .filter(|output| output.to_address == *address)
+            .fold(0u64, |total, output| total.saturating_add(output.amount))
+    }
+
+    /// Checks if the transaction pays the provided address.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The recipient address
+    ///
+    /// # Returns
+    ///
+    /// bool - True if any output is to the address, False otherwise
+    pub fn pays_to(&self, address: &Address) -> bool {
+        self.outputs
+            .iter()
+            .any(|output| output.to_address == *address)
+    }
+
+    /// Checks if the transaction is sent by the provided address.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - The sender address
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the from address is the address, False otherwise or for mining rewards
+    pub fn is_from(&self, address: &Address) -> bool {
+        self.from_address
+            .is_some_and(|from_address| from_address == *address)
+    }
+
+    /// Serializes the semantically relevant transaction fields into a fixed byte layout.
+    ///
+    /// The layout does not depend on the `Debug` format, so hashes and
+    /// signatures stay stable across compiler and formatting changes.
+    ///
+    /// # Returns
+    ///
+    /// The canonical bytes of the transaction.
+    ///
+    /// # Functionality
+    ///
+    /// - Writes the sender as a 0 byte if absent, or a 1 byte followed by the
+    ///   big-endian u32 length and UTF-8 bytes of the hex encoded address
+    /// - Writes the number of outputs as a big-endian u32
+    /// - Writes each output as the big-endian u32 length and UTF-8 bytes of the
+    ///   recipient, followed by the amount as a big-endian u64
+    /// - Writes the fee as a big-endian u64
+    /// - Writes the nonce as a big-endian u64
+    /// - Writes a 1 byte followed by valid_until as a big-endian u64 if set
+    /// - Writes a 2 byte followed by the big-endian u32 length and the bytes of the
+    ///   memo if set, so transactions without memo keep their hash
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        match self.from_address {
+            Some(ref from_address) => {
+                let from_address = from_address.to_string();
+                bytes.push(1);
+                bytes.extend_from_slice(&(from_address.len() as u32).to_be_bytes());
+                bytes.extend_from_slice(from_address.as_bytes());
+            }
+            None => bytes.push(0),
+        }
+
+        bytes.extend_from_slice(&(self.outputs.len() as u32).to_be_bytes());
+        for output in &self.outputs {
+            let to_address = output.to_address.to_string();
+            bytes.extend_from_slice(&(to_address.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(to_address.as_bytes());
+            bytes.extend_from_slice(&output.amount.to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.fee.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+
+        if let Some(valid_until) = self.valid_until {
+            bytes.push(1);
+            bytes.extend_from_slice(&valid_until.to_be_bytes());
+        }
+
+        if let Some(ref memo) = self.memo {
+            bytes.push(2);
+            bytes.extend_from_slice(&(memo.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(memo);
+        }
+
+        bytes
+    }
+
+    /// Checks if the transaction can no longer be mined in a block.
     ///
     /// # Parameters
     ///
-    /// * `from_address` - The sender address 
-    /// * `to_address` - The recipient address
-    /// * `amount` - The amount transferred
+    /// * `height` - The index of the block the transaction would be mined in
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the height is past valid_until, False otherwise or without valid_until
+    pub fn is_expired_at(&self, height: u64) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| height > valid_until)
+    }
+
+    /// Gets the size of the memo.
     ///
     /// # Returns
-    /// 
+    ///
+    /// The length of the memo in bytes, 0 without memo
+    pub fn memo_len(&self) -> usize {
+        self.memo.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Gets the size of the transaction in bytes.
+    ///
+    /// # Returns
+    ///
+    /// The length of canonical_bytes() plus the length of the decoded signature,
+    /// 0 for an unsigned transaction
+    pub fn serialized_size(&self) -> usize {
+        let signature_size = self
+            .signature
+            .as_ref()
+            .map_or(0, |signature| signature.len() / 2);
+
+        self.canonical_bytes().len() + signature_size
+    }
+
+    /// Computes the hash of the transaction from its current fields.
+    ///
+    /// This is the hash that is signed and identifies the transaction.
+    ///
+    /// # Returns
+    ///
     /// The SHA3-256 hash of the transaction details as a hex encoded string.
     ///
     /// # Functionality
     ///
-    /// - Formats the transaction details into an input string
-    /// - Feeds the input string into a SHA3-256 hasher
-    /// - Finalizes the hash 
+    /// - Calls compute_hash_with() with a SHA3-256 hasher
+    pub fn compute_hash(&self) -> String {
+        self.compute_hash_with(&Sha3_256Hasher)
+    }
+
+    /// Computes the hash of the transaction from its current fields with the provided hasher.
+    ///
+    /// # Parameters
+    ///
+    /// * `hasher` - The hash function to use
+    ///
+    /// # Returns
+    ///
+    /// The hash of the transaction details as a hex encoded string.
+    ///
+    /// # Functionality
+    ///
+    /// - Ignores the stored `hash` field
+    /// - Feeds the canonical bytes of the transaction into the hasher
     /// - Encodes the hash bytes as hex
-    fn calculate_hash(
-        &self,
-        from_address: Option<String>,
-        to_address: String,
-        amount: f64,
-    ) -> String {
-        let mut hasher = Sha3_256::new();
-        let input = format!("{:?}:{:?}:{:?}", from_address, to_address, amount);
-        hasher.update(input.as_bytes());
-        let result = hasher.finalize();
-        hex::encode(result)
+    pub fn compute_hash_with(&self, hasher: &dyn Hasher) -> String {
+        hasher.hash_hex(&self.canonical_bytes())
     }
 
-    /// Signs the transaction using the provided private key.
+    /// Signs the transaction using the provided hex encoded private key.
     ///
     /// # Parameters
     ///
-    /// * `signing_key` - The private key to sign the transaction with 
+    /// * `signing_key` - The hex encoded private key to sign the transaction with
     ///
     /// # Returns
     ///
-    /// `Result<(), String>`
+    /// `Result<(), BlockchainError>`
     ///
     /// - `Ok(())` if signing succeeded 
-    /// - `Err(String)` containing the error message if signing failed
+    /// - `Err(BlockchainError::Signing)` containing the error message if signing failed
     ///
     /// # Functionality
     ///
-    /// - Validates the provided public and private keys match
+    /// - Parses the private key
+    /// - Calls sign_with() with the parsed key
+    pub fn sign(&mut self, signing_key: &str) -> Result<(), BlockchainError> {
+        let private_key = SecretKey::from_str(signing_key)
+            .map_err(|_| BlockchainError::Signing("Invalid private key format".to_string()))?;
+
+        self.sign_with(&private_key)
+    }
+
+    /// Signs the transaction using the provided private key.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The private key to sign the transaction with
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), BlockchainError>`
+    ///
+    /// - `Ok(())` if signing succeeded
+    /// - `Err(BlockchainError::Signing)` containing the error message if signing failed
+    ///
+    /// # Functionality
+    ///
+    /// - Validates the private key matches the public key of the from address
     /// - Calculates the transaction hash 
     /// - Creates a secp256k1 message from the hash 
-    /// - Signs the message using the private key  
-    /// - Serializes the signature to DER format
+    /// - Signs the message using the private key into a recoverable signature,
+    ///   normalized to a low S value by libsecp256k1 so it passes is_valid()
+    /// - Serializes the signature to its compact format followed by the recovery id
     /// - Sets the transaction signature
-    pub fn sign(&mut self, signing_key: &str) -> Result<(), String> {
-        if let Some(ref from_address) = self.from_address {
-            let secp = Secp256k1::new();
-
-            let public_key = PublicKey::from_str(from_address)
-                .map_err(|_| "Invalid public key format".to_string())?;
-
-            let private_key = SecretKey::from_str(signing_key)
-                .map_err(|_| "Invalid private key format".to_string())?;
+    pub fn sign_with(&mut self, key: &SecretKey) -> Result<(), BlockchainError> {
+        if let Some(from_address) = self.from_address {
+            let secp = secp256k1_context();
 
-            let derived_public_key = PublicKey::from_secret_key(&secp, &private_key);
+            let derived_public_key = PublicKey::from_secret_key(secp, key);
 
-            if derived_public_key != public_key {
-                return Err(
+            if derived_public_key != *from_address.public_key() {
+                return Err(BlockchainError::Signing(
                     "The private key does not correspond to the provided public key".to_string(),
-                );
+                ));
             }
 
-            let hash_transaction = self.calculate_hash(
-                Some(
-                    self.from_address
-                        .clone()
-                        .unwrap_or("Mining reward".to_string()),
-                ),
-                self.to_address.clone(),
-                self.amount,
-            );
+            let hash_transaction = self.compute_hash();
 
             self.hash = Some(hash_transaction.clone());
 
-            let decoded_hash =
-                hex::decode(&hash_transaction).map_err(|_| "Invalid hex format".to_string())?;
-            let message = match Message::from_slice(&decoded_hash) {
-                Ok(message) => message,
-                Err(_) => return Err("Invalid message format".to_string()),
-            };
-
-            let signature = secp.sign_ecdsa(&message, &private_key);
-
-            let signature_bytes = signature.serialize_der();
+            let decoded_hash = hex::decode(&hash_transaction)
+                .map_err(|_| BlockchainError::Signing("Invalid hex format".to_string()))?;
+            let message = Message::from_slice(&decoded_hash)
+                .map_err(|_| BlockchainError::Signing("Invalid message format".to_string()))?;
 
-            self.signature = Some(hex::encode(signature_bytes));
+            self.signature = Some(sign_recoverable(&message, key));
 
             Ok(())
         } else {
-            Err("Transaction cannot be signed as it does not have a from address".to_string())
+            Err(BlockchainError::Signing(
+                "Transaction cannot be signed as it does not have a from address".to_string(),
+            ))
+        }
+    }
+
+    /// Gets the hash to sign for the transaction, e.g. on an offline machine.
+    ///
+    /// # Returns
+    ///
+    /// `Result<String, BlockchainError>`
+    ///
+    /// - `Ok(String)` with the hex encoded 32-byte hash to sign
+    /// - `Err(BlockchainError::Signing)` if the transaction has no from address
+    pub fn signable_hash(&self) -> Result<String, BlockchainError> {
+        if self.from_address.is_none() {
+            return Err(BlockchainError::Signing(
+                "Transaction cannot be signed as it does not have a from address".to_string(),
+            ));
         }
+
+        Ok(self.compute_hash())
+    }
+
+    /// Attaches a signature made elsewhere over signable_hash().
+    ///
+    /// # Parameters
+    ///
+    /// * `sig_hex` - The hex encoded signature, either the 65-byte recoverable
+    ///   signature produced by sign(), or a 64-byte compact signature without
+    ///   recovery id
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), BlockchainError>`
+    ///
+    /// - `Ok(())` if the signature was made by the key of from_address and was attached
+    /// - `Err(BlockchainError::Signing)` if the signature is malformed
+    /// - `Err(BlockchainError::Verification)` if the signature was not made by the key of
+    ///   from_address, leaving the transaction unchanged
+    ///
+    /// # Functionality
+    ///
+    /// - Calls signable_hash() to get the signed hash
+    /// - Decodes the signature, trying each recovery id for a compact signature
+    /// - Sets the hash and signature, and keeps them once is_valid() passes
+    /// - Restores the previous hash and signature otherwise
+    pub fn attach_signature(&mut self, sig_hex: &str) -> Result<(), BlockchainError> {
+        let hash = self.signable_hash()?;
+
+        let signature_bytes = hex::decode(sig_hex)
+            .map_err(|_| BlockchainError::Signing("Invalid signature format".to_string()))?;
+
+        let candidates: Vec<Vec<u8>> = match signature_bytes.len() {
+            65 => vec![signature_bytes],
+            64 => (0..4u8)
+                .map(|recovery_id| {
+                    let mut candidate = signature_bytes.clone();
+                    candidate.push(recovery_id);
+                    candidate
+                })
+                .collect(),
+            _ => {
+                return Err(BlockchainError::Signing(
+                    "The signature must be 64 or 65 bytes long".to_string(),
+                ))
+            }
+        };
+
+        let previous_signature = self.signature.take();
+        let previous_hash = self.hash.replace(hash);
+
+        for candidate in candidates {
+            self.signature = Some(hex::encode(candidate));
+
+            if let Ok(true) = self.is_valid() {
+                return Ok(());
+            }
+        }
+
+        self.signature = previous_signature;
+        self.hash = previous_hash;
+
+        Err(BlockchainError::Verification(
+            "The signature was not made by the key of from_address".to_string(),
+        ))
     }
 
     /// Validates the transaction's signature.
     ///
     /// # Returns
     ///
-    /// `Result<bool, String>`
+    /// `Result<bool, BlockchainError>`
     ///
     /// - `Ok(true)` if signature is valid
-    /// - `Ok(false)` if no signature present 
-    /// - `Err(String)` containing error message if validation failed
+    /// - `Ok(false)` if the signature was not made by the key of from_address
+    /// - `Err(BlockchainError::Verification)` containing error message if validation failed
     ///
     /// # Functionality
     ///
-    /// - Returns Ok(true) if no from_address  
-    /// - Checks signature is present
-    /// - Decodes signature from hex
-    /// - Decodes public key from address
-    /// - Decodes hash from transaction hash
-    /// - Constructs secp256k1 message from hash
-    /// - Verifies signature against public key & message 
-    /// - Returns result of signature verification
-    pub fn is_valid(&self) -> Result<bool, String> {
-        if self.from_address.is_none() {
+    /// - Calls is_valid_with() with the shared context of secp256k1_context()
+    pub fn is_valid(&self) -> Result<bool, BlockchainError> {
+        self.is_valid_with(secp256k1_context())
+    }
+
+    /// Validates the transaction's signature with an existing secp256k1 context,
+    /// so the context can be shared by many transactions.
+    ///
+    /// # Parameters
+    ///
+    /// * `secp` - The secp256k1 context to recover the signing key with
+    ///
+    /// # Returns
+    ///
+    /// `Result<bool, BlockchainError>`, as for is_valid()
+    ///
+    /// # Functionality
+    ///
+    /// - Returns Ok(true) if no from_address
+    /// - Checks the stored hash matches the hash recomputed from the current fields,
+    ///   so the signed message covers the actual transfer
+    /// - Calls recover_public_key_with() and compares the key with the public key
+    ///   of the address
+    pub fn is_valid_with<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> Result<bool, BlockchainError> {
+        let Some(from_address) = self.from_address else {
             return Ok(true);
+        };
+
+        if !self.has_matching_hash() {
+            return Err(BlockchainError::Verification(
+                "Transaction hash does not match its contents".to_string(),
+            ));
         }
-        if let Some(ref signature) = self.signature {
-            if signature.is_empty() {
-                return Err("No signature in this transaction".to_string());
-            }
 
-            let secp = Secp256k1::new();
+        Ok(self.recover_public_key_with(secp)? == *from_address.public_key())
+    }
 
-            let public_key =
-                PublicKey::from_str(self.from_address.as_ref().ok_or("Missing from_address")?)
-                    .map_err(|_| "Invalid public key format".to_string())?;
+    /// Checks the stored hash matches the transaction contents.
+    ///
+    /// # Returns
+    ///
+    /// bool - True if the stored hash equals compute_hash(), False if it differs or is missing
+    pub fn has_matching_hash(&self) -> bool {
+        self.hash.as_deref() == Some(self.compute_hash().as_str())
+    }
 
-            let message_bytes = hex::decode(
-                self.hash
-                    .as_ref()
-                    .ok_or("Transaction hash not found".to_string())?,
+    /// Verifies the transaction's signature against a known public key.
+    ///
+    /// Unlike is_valid(), the signature is not checked against the
+    /// self-reported from_address, so a transaction whose address and
+    /// signature were both swapped does not pass.
+    ///
+    /// # Parameters
+    ///
+    /// * `expected_pubkey` - The public key expected to have signed the transaction
+    ///
+    /// # Returns
+    ///
+    /// `Result<bool, BlockchainError>`
+    ///
+    /// - `Ok(true)` if the signature was made by the expected key
+    /// - `Ok(false)` if the signature was not made by the expected key
+    /// - `Err(BlockchainError::Verification)` containing error message if validation failed
+    ///
+    /// # Functionality
+    ///
+    /// - Calls recover_public_key() to recover the signing key
+    /// - Returns whether the recovered key is the expected public key
+    pub fn verify_against(&self, expected_pubkey: &PublicKey) -> Result<bool, BlockchainError> {
+        Ok(self.recover_public_key()? == *expected_pubkey)
+    }
+
+    /// Recovers the public key that signed the transaction.
+    ///
+    /// # Returns
+    ///
+    /// `Result<PublicKey, BlockchainError>`
+    ///
+    /// - `Ok(PublicKey)` with the key that signed the current transaction contents
+    /// - `Err(BlockchainError::Verification)` containing error message if recovery failed
+    ///
+    /// # Functionality
+    ///
+    /// - Calls recover_public_key_with() with the shared context of secp256k1_context()
+    pub fn recover_public_key(&self) -> Result<PublicKey, BlockchainError> {
+        self.recover_public_key_with(secp256k1_context())
+    }
+
+    /// Recovers the public key that signed the transaction with an existing
+    /// secp256k1 context.
+    ///
+    /// # Parameters
+    ///
+    /// * `secp` - The secp256k1 context to recover the key with
+    ///
+    /// # Returns
+    ///
+    /// `Result<PublicKey, BlockchainError>`, as for recover_public_key()
+    ///
+    /// # Functionality
+    ///
+    /// - Checks signature is present
+    /// - Decodes the compact signature and recovery id from hex
+    /// - Rejects signatures whose S value is in the upper half of the curve order,
+    ///   as flipping S yields a second valid encoding of the same signature
+    /// - Constructs secp256k1 message from the recomputed transaction hash, so
+    ///   tampered contents recover a different key
+    /// - Recovers the public key from the signature & message
+    pub fn recover_public_key_with<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> Result<PublicKey, BlockchainError> {
+        let signature = self
+            .signature
+            .as_ref()
+            .filter(|signature| !signature.is_empty())
+            .ok_or_else(|| {
+                BlockchainError::Verification("No signature in this transaction".to_string())
+            })?;
+
+        let message_bytes = hex::decode(self.compute_hash()).map_err(|_| {
+            BlockchainError::Verification("Error decoding transaction hash".to_string())
+        })?;
+
+        let message = Message::from_slice(&message_bytes)
+            .map_err(|_| BlockchainError::Verification("Invalid message format".to_string()))?;
+
+        recover_signer(secp, &message, signature)
+    }
+}
+
+/// Signs a message into a hex encoded recoverable signature.
+///
+/// # Parameters
+///
+/// * `message` - The 32-byte message to sign
+/// * `key` - The private key to sign with
+///
+/// # Returns
+///
+/// The hex encoded 64-byte compact signature followed by the recovery id, with a
+/// low S value
+fn sign_recoverable(message: &Message, key: &SecretKey) -> String {
+    let signature = secp256k1_context().sign_ecdsa_recoverable(message, key);
+
+    let (recovery_id, compact_bytes) = signature.serialize_compact();
+    let mut signature_bytes = compact_bytes.to_vec();
+    signature_bytes.push(recovery_id.to_i32() as u8);
+
+    hex::encode(signature_bytes)
+}
+
+/// Recovers the public key that signed a message.
+///
+/// # Parameters
+///
+/// * `secp` - The secp256k1 context to recover the key with
+/// * `message` - The signed 32-byte message
+/// * `signature` - The hex encoded recoverable signature, as made by sign_recoverable()
+///
+/// # Returns
+///
+/// `Result<PublicKey, BlockchainError>`
+///
+/// - `Ok(PublicKey)` with the key that signed the message
+/// - `Err(BlockchainError::Verification)` if the signature is malformed, has a high S
+///   value or no key can be recovered from it
+fn recover_signer<C: Verification>(
+    secp: &Secp256k1<C>,
+    message: &Message,
+    signature: &str,
+) -> Result<PublicKey, BlockchainError> {
+    let signature_bytes = hex::decode(signature)
+        .map_err(|_| BlockchainError::Verification("Invalid signature format".to_string()))?;
+
+    let [compact_bytes @ .., recovery_byte] = signature_bytes.as_slice() else {
+        return Err(BlockchainError::Verification(
+            "Invalid signature".to_string(),
+        ));
+    };
+
+    let signature = RecoveryId::from_i32(*recovery_byte as i32)
+        .and_then(|recovery_id| RecoverableSignature::from_compact(compact_bytes, recovery_id))
+        .map_err(|_| BlockchainError::Verification("Invalid signature".to_string()))?;
+
+    let standard_signature = signature.to_standard();
+    let mut normalized_signature = standard_signature;
+    normalized_signature.normalize_s();
+
+    if normalized_signature != standard_signature {
+        return Err(BlockchainError::Verification(
+            "Signature S value is not in the lower half of the curve order".to_string(),
+        ));
+    }
+
+    secp.recover_ecdsa(message, &signature)
+        .map_err(|_| BlockchainError::Verification("Invalid signature".to_string()))
+}
+
+/// The prefix hashed before a signed message, so a message signature can never be
+/// passed off as the signature of a transaction with the same hash.
+const SIGNED_MESSAGE_PREFIX: &[u8] = b"cryptobeca signed message:\n";
+
+/// Hashes a message to sign with sign_message().
+///
+/// # Parameters
+///
+/// * `msg` - The message bytes
+///
+/// # Returns
+///
+/// The secp256k1 message of the SHA3-256 hash of SIGNED_MESSAGE_PREFIX followed by the message
+fn message_digest(msg: &[u8]) -> Message {
+    let digest = Sha3_256Hasher.hash(&[SIGNED_MESSAGE_PREFIX, msg].concat());
+    Message::from_slice(&digest).expect("SHA3-256 hashes are 32 bytes long")
+}
+
+/// Signs an arbitrary message, e.g. a sign-in challenge, to prove ownership of an address.
+///
+/// # Parameters
+///
+/// * `key` - The private key of the address
+/// * `msg` - The message bytes
+///
+/// # Returns
+///
+/// The hex encoded 65-byte recoverable signature, in the format of transaction signatures
+///
+/// # Functionality
+///
+/// - Hashes SIGNED_MESSAGE_PREFIX followed by the message with SHA3-256
+/// - Signs the hash with secp256k1 as sign_with() signs a transaction hash
+pub fn sign_message(key: &SecretKey, msg: &[u8]) -> String {
+    sign_recoverable(&message_digest(msg), key)
+}
+
+/// Verifies a message signature made by sign_message().
+///
+/// # Parameters
+///
+/// * `address` - The hex encoded address expected to have signed the message
+/// * `msg` - The message bytes
+/// * `sig_hex` - The hex encoded signature
+///
+/// # Returns
+///
+/// `Result<bool, String>`
+///
+/// - `Ok(true)` if the signature of the message was made by the key of the address
+/// - `Ok(false)` if it was made by another key or for another message
+/// - `Err(String)` containing the error message if the address or signature is malformed
+///
+/// # Functionality
+///
+/// - Parses the address
+/// - Recovers the signing key from the signature and the hash of the message, with the
+///   same checks as the signature of a transaction
+/// - Returns whether the recovered key is the public key of the address
+pub fn verify_message(address: &str, msg: &[u8], sig_hex: &str) -> Result<bool, String> {
+    let address = Address::from_str(address).map_err(|err| err.to_string())?;
+
+    let signer = recover_signer(secp256k1_context(), &message_digest(msg), sig_hex)
+        .map_err(|err| err.to_string())?;
+
+    Ok(signer == *address.public_key())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::keypair;
+
+    #[test]
+    fn amount_changed_after_signing_is_detected() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.sign_with(&key).unwrap();
+        assert!(transaction.has_matching_hash());
+        assert!(transaction.is_valid().unwrap());
+
+        transaction.outputs[0].amount = 1_000;
+
+        assert!(!transaction.has_matching_hash());
+        assert!(matches!(
+            transaction.is_valid(),
+            Err(BlockchainError::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn transaction_from_a_compressed_address_round_trips() {
+        let (key, _) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let public_key = PublicKey::from_secret_key(secp256k1_context(), &key);
+        let compressed = hex::encode(public_key.serialize());
+        let uncompressed = hex::encode(public_key.serialize_uncompressed());
+        assert_eq!(compressed.len(), 66);
+        assert_eq!(uncompressed.len(), 130);
+        assert_eq!(
+            Address::from_str(&compressed).unwrap(),
+            Address::from_str(&uncompressed).unwrap()
+        );
+
+        let sender = Address::from_str(&compressed).unwrap();
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.sign(&hex::encode(key.secret_bytes())).unwrap();
+        assert!(transaction.is_valid().unwrap());
+        assert_eq!(transaction.from_address.unwrap().to_string(), compressed);
+
+        let json = serde_json::to_string(&transaction).unwrap();
+        let restored: Transaction = serde_json::from_str(&json).unwrap();
+        assert!(restored.is_valid().unwrap());
+        assert!(restored
+            .verify_against(&PublicKey::from_str(&uncompressed).unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn signing_with_the_key_of_another_address_fails() {
+        let (_, sender) = keypair(1);
+        let (other_key, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+
+        assert!(matches!(
+            transaction.sign_with(&other_key),
+            Err(BlockchainError::Signing(_))
+        ));
+        assert!(transaction.signature.is_none());
+    }
+
+    #[test]
+    fn constructed_transaction_signs_like_a_struct_literal() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut constructed = Transaction::new(Some(sender), recipient, 10);
+        let mut literal = Transaction {
+            from_address: Some(sender),
+            outputs: vec![TransactionOutput {
+                to_address: recipient,
+                amount: 10,
+            }],
+            fee: 0,
+            nonce: 0,
+            signature: None,
+            hash: None,
+            valid_until: None,
+            received_at: None,
+            memo: None,
+        };
+
+        constructed.sign_with(&key).unwrap();
+        literal.sign_with(&key).unwrap();
+
+        assert!(constructed.is_valid().unwrap());
+        assert!(literal.is_valid().unwrap());
+        assert_eq!(constructed.hash, literal.hash);
+        assert_eq!(constructed.signature, literal.signature);
+    }
+
+    #[test]
+    fn coinbase_has_no_sender_and_checks_as_valid() {
+        let (_, miner) = keypair(1);
+        let reward = Transaction::coinbase(miner, 100);
+
+        assert!(reward.from_address.is_none());
+        assert_eq!(reward.amount_to(&miner), 100);
+        assert!(reward.is_valid().unwrap());
+    }
+
+    #[test]
+    fn signature_verifies_only_against_the_signing_key() {
+        let (key_a, sender) = keypair(1);
+        let (key_b, other) = keypair(2);
+        let secp = secp256k1_context();
+        let mut transaction = Transaction::new(Some(sender), other, 10);
+        transaction.sign_with(&key_a).unwrap();
+
+        let public_key_a = PublicKey::from_secret_key(secp, &key_a);
+        let public_key_b = PublicKey::from_secret_key(secp, &key_b);
+        assert!(transaction.verify_against(&public_key_a).unwrap());
+        assert!(!transaction.verify_against(&public_key_b).unwrap());
+
+        let mut swapped = transaction.clone();
+        swapped.from_address = Some(other);
+        assert!(!swapped.verify_against(&public_key_b).unwrap());
+    }
+
+    #[test]
+    fn unsigned_transaction_cannot_be_verified_against_a_key() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let transaction = Transaction::new(Some(sender), recipient, 10);
+
+        assert!(matches!(
+            transaction.verify_against(&PublicKey::from_secret_key(secp256k1_context(), &key)),
+            Err(BlockchainError::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn canonical_bytes_are_pinned() {
+        let (_, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.fee = 2;
+        transaction.nonce = 3;
+
+        assert_eq!(
+            hex::encode(transaction.canonical_bytes()),
+            concat!(
+                "01",
+                "00000042",
+                "303331623834633535363762313236343430393935643365643561616261303536",
+                "356437316531383334363034383139666639633137663565396435646430373866",
+                "00000001",
+                "00000042",
+                "303234643462366364313336313033326361396264326165623964393030616134",
+                "643435643965616438306163393432333337346334353161373235346430373636",
+                "000000000000000a",
+                "0000000000000002",
+                "0000000000000003",
             )
-            .map_err(|_| "Error decoding transaction hash".to_string())?;
+        );
+        assert_eq!(
+            transaction.compute_hash(),
+            Sha3_256Hasher.hash_hex(&transaction.canonical_bytes())
+        );
 
-            let message = Message::from_slice(&message_bytes)
-                .map_err(|_| "Invalid message format".to_string())?;
+        let hash = transaction.compute_hash();
+        transaction.received_at = Some(Utc::now());
+        assert_eq!(transaction.compute_hash(), hash);
+    }
 
-            let signature_bytes =
-                hex::decode(signature).map_err(|_| "Invalid signature format".to_string())?;
+    #[test]
+    fn hex_and_key_signing_produce_identical_signatures() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut from_key = Transaction::new(Some(sender), recipient, 10);
+        let mut from_hex = from_key.clone();
 
-            let signature = Signature::from_der(&signature_bytes)
-                .map_err(|_| "Invalid signature".to_string())?;
+        from_key.sign_with(&key).unwrap();
+        from_hex.sign(&hex::encode(key.secret_bytes())).unwrap();
 
-            let is_valid_signature = secp.verify_ecdsa(&message, &signature, &public_key).is_ok();
+        assert!(from_key.signature.is_some());
+        assert_eq!(from_key.signature, from_hex.signature);
+        assert_eq!(from_key.hash, from_hex.hash);
+        assert!(matches!(
+            from_hex.clone().sign("not a key"),
+            Err(BlockchainError::Signing(_))
+        ));
+    }
 
-            Ok(is_valid_signature)
-        } else {
-            Err("No signature in this transaction".to_string())
+    #[test]
+    fn tampered_amount_recovers_a_different_signer() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.sign_with(&key).unwrap();
+        assert_eq!(transaction.signature.as_ref().unwrap().len(), 2 * 65);
+        assert_eq!(
+            transaction.recover_public_key().unwrap(),
+            *sender.public_key()
+        );
+
+        transaction.outputs[0].amount = 1_000;
+        transaction.hash = Some(transaction.compute_hash());
+
+        let recovered = transaction.recover_public_key().unwrap();
+        assert_ne!(Address::from(recovered), sender);
+        assert!(!transaction.is_valid().unwrap());
+    }
+
+    #[test]
+    fn high_s_variant_of_a_signature_is_rejected() {
+        const CURVE_ORDER: [u8; 32] = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c,
+            0xd0, 0x36, 0x41, 0x41,
+        ];
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.sign_with(&key).unwrap();
+        assert!(transaction.is_valid().unwrap());
+
+        let mut signature = hex::decode(transaction.signature.as_ref().unwrap()).unwrap();
+        let mut borrow = 0;
+        for i in (32..64).rev() {
+            let difference = CURVE_ORDER[i - 32] as i16 - signature[i] as i16 - borrow;
+            borrow = i16::from(difference < 0);
+            signature[i] = difference.rem_euclid(256) as u8;
         }
+        signature[64] ^= 1;
+
+        let mut flipped = transaction.clone();
+        flipped.signature = Some(hex::encode(signature));
+        assert!(matches!(
+            flipped.is_valid(),
+            Err(BlockchainError::Verification(message)) if message.contains("lower half")
+        ));
+    }
+
+    #[test]
+    fn signature_made_offline_is_attached_and_verifies() {
+        let (key, sender) = keypair(1);
+        let (other_key, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+
+        let hash = transaction.signable_hash().unwrap();
+        let message = Message::from_slice(&hex::decode(&hash).unwrap()).unwrap();
+        let sign_offline = |key: &SecretKey| {
+            hex::encode(
+                secp256k1_context()
+                    .sign_ecdsa(&message, key)
+                    .serialize_compact(),
+            )
+        };
+
+        assert!(matches!(
+            transaction.attach_signature(&sign_offline(&other_key)),
+            Err(BlockchainError::Verification(_))
+        ));
+        assert_eq!(transaction.signature, None);
+
+        transaction.attach_signature(&sign_offline(&key)).unwrap();
+        assert_eq!(transaction.hash, Some(hash));
+        assert!(transaction.is_valid().unwrap());
+    }
+
+    #[test]
+    fn transaction_with_a_malformed_address_does_not_deserialize() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.sign_with(&key).unwrap();
+        let mut json = serde_json::to_value(&transaction).unwrap();
+        assert!(serde_json::from_value::<Transaction>(json.clone()).is_ok());
+
+        json["from_address"] = transaction.hash.clone().unwrap().into();
+        assert!(serde_json::from_value::<Transaction>(json).is_err());
+    }
+
+    #[test]
+    fn thousand_signatures_verify_with_the_shared_context() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let transactions: Vec<Transaction> = (1..=1_000)
+            .map(|amount| {
+                let mut transaction = Transaction::new(Some(sender), recipient, amount);
+                transaction.sign_with(&key).unwrap();
+                transaction
+            })
+            .collect();
+        assert!(std::ptr::eq(secp256k1_context(), secp256k1_context()));
+
+        let started = std::time::Instant::now();
+        let secp = secp256k1_context();
+        assert!(transactions
+            .iter()
+            .all(|transaction| transaction.is_valid_with(secp).unwrap()));
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn signed_message_verifies_only_against_the_signer_address() {
+        let (key, signer) = keypair(1);
+        let (_, other) = keypair(2);
+        let challenge = b"sign in to cryptobeca, nonce 42";
+        let signature = sign_message(&key, challenge);
+
+        assert_eq!(
+            verify_message(&signer.to_string(), challenge, &signature),
+            Ok(true)
+        );
+        assert_eq!(
+            verify_message(&other.to_string(), challenge, &signature),
+            Ok(false)
+        );
+        assert_eq!(
+            verify_message(&signer.to_string(), b"another challenge", &signature),
+            Ok(false)
+        );
+        assert!(verify_message("not an address", challenge, &signature).is_err());
+        assert!(verify_message(&signer.to_string(), challenge, "zz").is_err());
+    }
+
+    #[test]
+    fn memo_changed_after_signing_invalidates_the_transaction() {
+        let (key, sender) = keypair(1);
+        let (_, recipient) = keypair(2);
+        let mut transaction = Transaction::new(Some(sender), recipient, 10);
+        transaction.memo = Some(b"invoice 42".to_vec());
+        transaction.sign_with(&key).unwrap();
+
+        assert!(transaction.is_valid().unwrap());
+
+        transaction.memo = Some(b"invoice 43".to_vec());
+        assert!(transaction.is_valid().is_err());
+
+        transaction.memo = None;
+        assert!(transaction.is_valid().is_err());
     }
 }
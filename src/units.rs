@@ -0,0 +1,321 @@
+use std::fmt;
+
+/// The number of decimal places of a coin.
+pub const DECIMALS: u32 = 8;
+
+/// The number of smallest units in a coin.
+///
+/// All amounts, fees and rewards are u64 counts of smallest units, so balance
+/// arithmetic is exact.
+pub const COIN: u64 = 10u64.pow(DECIMALS);
+
+/// The largest representable amount in smallest units, a little over 184 billion coins.
+///
+/// No balance, and so no supply, can exceed it: checked balance computations
+/// fail with BlockchainError::Overflow past it.
+pub const MAX_AMOUNT: u64 = u64::MAX;
+
+/// The ticker amounts are shown with.
+pub const TICKER: &str = "BECA";
+
+/// Amount struct.
+///
+/// An amount of smallest units to show to users, formatted by its Display
+/// implementation as a fixed number of decimals followed by the ticker, e.g.
+/// "10.00 BECA". Arithmetic is done on the plain u64 units.
+///
+/// # Fields
+///
+/// * `units` - The amount in smallest units
+/// * `decimals` - The number of decimal places shown, the extra places being truncated
+/// * `ticker` - The ticker shown after the number, nothing if empty
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Amount {
+    pub units: u64,
+    pub decimals: u32,
+    pub ticker: &'static str,
+}
+
+impl Amount {
+    /// Creates a new Amount instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `units` - The amount in smallest units
+    ///
+    /// # Returns
+    ///
+    /// A new Amount instance shown with DECIMALS decimal places and TICKER
+    pub fn new(units: u64) -> Self {
+        Self {
+            units,
+            decimals: DECIMALS,
+            ticker: TICKER,
+        }
+    }
+
+    /// Sets the number of decimal places shown.
+    ///
+    /// # Parameters
+    ///
+    /// * `decimals` - The number of decimal places, the places past DECIMALS being zeros
+    ///
+    /// # Returns
+    ///
+    /// The Amount with the new number of decimal places
+    pub fn with_decimals(self, decimals: u32) -> Self {
+        Self { decimals, ..self }
+    }
+
+    /// Sets the ticker shown after the number.
+    ///
+    /// # Parameters
+    ///
+    /// * `ticker` - The ticker, empty to show the number alone
+    ///
+    /// # Returns
+    ///
+    /// The Amount with the new ticker
+    pub fn with_ticker(self, ticker: &'static str) -> Self {
+        Self { ticker, ..self }
+    }
+}
+
+impl fmt::Display for Amount {
+    /// Truncates rather than rounds the places past `decimals`, so an amount is
+    /// never shown larger than it is.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let whole = self.units / COIN;
+        let fraction = format!("{:0width$}", self.units % COIN, width = DECIMALS as usize);
+        let fraction = format!(
+            "{:0<width$.width$}",
+            fraction,
+            width = self.decimals as usize
+        );
+
+        write!(f, "{}", whole)?;
+        if !fraction.is_empty() {
+            write!(f, ".{}", fraction)?;
+        }
+        if !self.ticker.is_empty() {
+            write!(f, " {}", self.ticker)?;
+        }
+        Ok(())
+    }
+}
+
+/// ParseUnitsError enum.
+///
+/// Represents the possible errors when parsing a decimal amount.
+///
+/// # Variants
+///
+/// * `Empty` - Returned when the amount is empty.
+/// * `InvalidDigit` - Returned when the amount contains anything but digits and a single dot.
+/// * `TooManyDecimals` - Returned when the amount has more than DECIMALS decimal places.
+/// * `Overflow` - Returned when the amount does not fit in a u64 of smallest units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseUnitsError {
+    Empty,
+    InvalidDigit,
+    TooManyDecimals,
+    Overflow,
+}
+
+impl fmt::Display for ParseUnitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            ParseUnitsError::Empty => "empty amount",
+            ParseUnitsError::InvalidDigit => "invalid digit in amount",
+            ParseUnitsError::TooManyDecimals => "too many decimal places in amount",
+            ParseUnitsError::Overflow => "amount is too large",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl std::error::Error for ParseUnitsError {}
+
+/// Formats an amount of smallest units as a decimal number of coins.
+///
+/// # Parameters
+///
+/// * `units` - The amount in smallest units
+///
+/// # Returns
+///
+/// The decimal number of coins, without trailing zeros, e.g. "1.5" for 150000000
+pub fn format_units(units: u64) -> String {
+    let whole = units / COIN;
+    let fraction = units % COIN;
+
+    if fraction == 0 {
+        return whole.to_string();
+    }
+
+    let fraction = format!("{:0width$}", fraction, width = DECIMALS as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Parses a decimal number of coins into smallest units.
+///
+/// # Parameters
+///
+/// * `amount` - The decimal number of coins, e.g. "1.5"
+///
+/// # Returns
+///
+/// `Result<u64, ParseUnitsError>`
+///
+/// - `Ok(u64)` with the amount in smallest units
+/// - `Err(ParseUnitsError)` if the amount is not a valid non-negative decimal
+///
+/// # Functionality
+///
+/// - Splits the amount on its optional decimal dot
+/// - Checks both parts only contain digits, and the fraction has at most DECIMALS digits
+/// - Pads the fraction to DECIMALS digits
+/// - Combines the whole and fractional parts with checked arithmetic
+pub fn parse_units(amount: &str) -> Result<u64, ParseUnitsError> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(ParseUnitsError::Empty);
+    }
+
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(ParseUnitsError::InvalidDigit);
+    }
+
+    if fraction.len() > DECIMALS as usize {
+        return Err(ParseUnitsError::TooManyDecimals);
+    }
+
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| ParseUnitsError::Overflow)?
+    };
+    let fraction: u64 = format!("{:0<width$}", fraction, width = DECIMALS as usize)
+        .parse()
+        .map_err(|_| ParseUnitsError::InvalidDigit)?;
+
+    whole
+        .checked_mul(COIN)
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or(ParseUnitsError::Overflow)
+}
+
+/// Converts a floating point number of coins into smallest units.
+///
+/// Meant to migrate amounts stored as f64 coins before the switch to integer units.
+///
+/// # Parameters
+///
+/// * `coins` - The number of coins
+///
+/// # Returns
+///
+/// The amount rounded to the nearest smallest unit, or None if it is negative,
+/// not finite or too large
+pub fn units_from_coins(coins: f64) -> Option<u64> {
+    let units = (coins * COIN as f64).round();
+
+    if !units.is_finite() || units < 0.0 || units >= u64::MAX as f64 {
+        return None;
+    }
+
+    Some(units as u64)
+}
+
+/// Converts an amount of smallest units into a floating point number of coins.
+///
+/// Meant for interop with code still expecting f64 coins, the result may be rounded.
+///
+/// # Parameters
+///
+/// * `units` - The amount in smallest units
+///
+/// # Returns
+///
+/// The number of coins as a f64
+pub fn coins_from_units(units: u64) -> f64 {
+    units as f64 / COIN as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn many_small_amounts_sum_exactly() {
+        let tenth = parse_units("0.1").unwrap();
+        let total = (0..1_000).fold(0u64, |total, _| total + tenth);
+        assert_eq!(total, 100 * COIN);
+        assert_eq!(format_units(total), "100");
+
+        let float_total = (0..1_000).fold(0.0f64, |total, _| total + 0.1);
+        assert_ne!(float_total, 100.0);
+    }
+
+    #[test]
+    fn decimal_amounts_round_trip_through_units() {
+        for (amount, units) in [
+            ("0", 0),
+            ("1.5", 150_000_000),
+            ("0.00000001", 1),
+            ("42", 42 * COIN),
+        ] {
+            assert_eq!(parse_units(amount), Ok(units));
+            assert_eq!(format_units(units), amount);
+        }
+        assert_eq!(parse_units(".5"), Ok(COIN / 2));
+        assert_eq!(parse_units("2."), Ok(2 * COIN));
+    }
+
+    #[test]
+    fn malformed_decimal_amounts_are_rejected() {
+        assert_eq!(parse_units(""), Err(ParseUnitsError::Empty));
+        assert_eq!(parse_units("."), Err(ParseUnitsError::Empty));
+        assert_eq!(parse_units("-1"), Err(ParseUnitsError::InvalidDigit));
+        assert_eq!(parse_units("1.2.3"), Err(ParseUnitsError::InvalidDigit));
+        assert_eq!(
+            parse_units("0.000000001"),
+            Err(ParseUnitsError::TooManyDecimals)
+        );
+        assert_eq!(parse_units("184467440738"), Err(ParseUnitsError::Overflow));
+    }
+
+    #[test]
+    fn float_coins_migrate_to_the_nearest_unit() {
+        assert_eq!(units_from_coins(0.1 + 0.2), Some(30_000_000));
+        assert_eq!(units_from_coins(50.0), Some(50 * COIN));
+        assert_eq!(units_from_coins(-1.0), None);
+        assert_eq!(units_from_coins(f64::NAN), None);
+        assert_eq!(units_from_coins(f64::INFINITY), None);
+        assert_eq!(coins_from_units(150_000_000), 1.5);
+    }
+
+    #[test]
+    fn amounts_are_shown_with_their_decimals_and_ticker() {
+        let ten = Amount::new(10 * COIN);
+        assert_eq!(
+            ten.with_decimals(2).with_ticker("BECA").to_string(),
+            "10.00 BECA"
+        );
+        assert_eq!(ten.to_string(), format!("10.00000000 {}", TICKER));
+        assert_eq!(ten.with_decimals(0).with_ticker("").to_string(), "10");
+        assert_eq!(
+            Amount::new(COIN + 999_999).with_decimals(2).to_string(),
+            "1.00 BECA"
+        );
+        assert_eq!(
+            Amount::new(1).with_decimals(10).with_ticker("").to_string(),
+            "0.0000000100"
+        );
+    }
+}
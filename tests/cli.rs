@@ -0,0 +1,106 @@
+mod common;
+
+use common::keypair;
+use cryptobeca::units::{Amount, COIN};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Creates an empty working directory for a test, without the .env file of the repository.
+fn working_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cryptobeca-cli-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the wallet in a working directory, signing with a key if provided.
+fn cli(dir: &Path, key: Option<&SecretKey>, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cryptobeca"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env_remove("PRIVATE_KEY")
+        .env_remove("PUBLIC_KEY");
+
+    if let Some(key) = key {
+        let public_key = PublicKey::from_secret_key(&Secp256k1::new(), key);
+        command
+            .env("PRIVATE_KEY", key.display_secret().to_string())
+            .env("PUBLIC_KEY", public_key.to_string());
+    }
+
+    command.output().expect("The wallet runs")
+}
+
+/// Gets the standard output of the wallet as trimmed text.
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn balance_of_a_fresh_address_is_zero() {
+    let dir = working_dir("balance");
+    let (_, address) = keypair(1);
+
+    let output = cli(&dir, None, &["balance", &address.to_string()]);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), Amount::new(0).to_string());
+    assert!(!dir.join("blockchain.json").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mined_and_sent_coins_are_saved_to_the_chain_file() {
+    let dir = working_dir("send");
+    let (key, miner) = keypair(1);
+    let (_, recipient) = keypair(2);
+
+    let mined = cli(&dir, None, &["mine", "--reward-to", &miner.to_string()]);
+    assert!(mined.status.success());
+    assert!(stdout(&mined).starts_with("Block mined: "));
+    assert!(dir.join("blockchain.json").exists());
+
+    let sent = cli(
+        &dir,
+        Some(&key),
+        &["send", "--to", &recipient.to_string(), "--amount", "1.5"],
+    );
+    assert!(sent.status.success());
+    assert!(
+        cli(&dir, None, &["mine", "--reward-to", &miner.to_string()])
+            .status
+            .success()
+    );
+
+    let balance = cli(&dir, None, &["balance", &recipient.to_string()]);
+    assert_eq!(stdout(&balance), Amount::new(3 * COIN / 2).to_string());
+
+    let validated = cli(&dir, None, &["validate"]);
+    assert!(validated.status.success());
+    assert_eq!(stdout(&validated), "The chain is valid");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn failing_subcommands_exit_with_an_error() {
+    let dir = working_dir("errors");
+    let (_, recipient) = keypair(2);
+
+    let unsigned = cli(
+        &dir,
+        None,
+        &["send", "--to", &recipient.to_string(), "--amount", "1"],
+    );
+    assert!(!unsigned.status.success());
+
+    let invalid = cli(&dir, None, &["balance", "not an address"]);
+    assert!(!invalid.status.success());
+
+    fs::write(dir.join("blockchain.json"), "not a chain").unwrap();
+    let corrupt = cli(&dir, None, &["validate"]);
+    assert!(!corrupt.status.success());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,11 @@
+use cryptobeca::address::Address;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+/// Gets a deterministic key and its address, for the integration tests.
+pub fn keypair(seed: u8) -> (SecretKey, Address) {
+    let key = SecretKey::from_slice(&[seed; 32]).expect("Seed is a valid key");
+    (
+        key,
+        PublicKey::from_secret_key(&Secp256k1::new(), &key).into(),
+    )
+}
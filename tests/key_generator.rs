@@ -0,0 +1,106 @@
+use std::process::{Command, Output};
+
+/// A fixed seed for the deterministic `--seed` mode.
+const SEED: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+/// Runs the key generator with arguments.
+fn key_generator(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_key_generator"))
+        .args(args)
+        .output()
+        .expect("The key generator runs")
+}
+
+/// Gets the value printed after a label, e.g. "Private Key (Hex)".
+fn printed(output: &Output, label: &str) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(label))
+        .and_then(|rest| rest.strip_prefix(": "))
+        .unwrap_or_else(|| panic!("{} is not printed in:\n{}", label, stdout))
+        .to_string()
+}
+
+#[test]
+fn compressed_flag_prints_the_33_byte_public_key() {
+    let compressed = key_generator(&["--seed", SEED, "--compressed"]);
+    let uncompressed = key_generator(&["--seed", SEED]);
+    assert!(compressed.status.success());
+    assert!(uncompressed.status.success());
+
+    let compressed_key = printed(&compressed, "Public Key (Hex, 33-byte compressed)");
+    let uncompressed_key = printed(&uncompressed, "Public Key (Hex, 65-byte uncompressed)");
+    assert_eq!(compressed_key.len(), 66);
+    assert!(compressed_key.starts_with("02") || compressed_key.starts_with("03"));
+    assert_eq!(uncompressed_key.len(), 130);
+    assert!(uncompressed_key.starts_with("04"));
+    assert_eq!(compressed_key[2..], uncompressed_key[2..66]);
+}
+
+#[test]
+fn generated_mnemonic_reimports_to_the_same_private_key() {
+    for word_count in ["12", "24"] {
+        let generated = key_generator(&["--mnemonic", word_count]);
+        assert!(generated.status.success());
+        let phrase = printed(&generated, "Mnemonic Phrase");
+        assert_eq!(phrase.split_whitespace().count().to_string(), word_count);
+
+        let imported = key_generator(&["--from-mnemonic", &phrase]);
+        assert!(imported.status.success());
+        assert_eq!(
+            printed(&imported, "Private Key (Hex)"),
+            printed(&generated, "Private Key (Hex)")
+        );
+    }
+}
+
+#[test]
+fn mnemonic_with_a_wrong_checksum_word_is_rejected() {
+    // The checksum word of an all-zero entropy is "about"
+    let valid = format!("{} about", ["abandon"; 11].join(" "));
+    let invalid = ["abandon"; 12].join(" ");
+
+    assert!(key_generator(&["--from-mnemonic", &valid]).status.success());
+
+    let imported = key_generator(&["--from-mnemonic", &invalid]);
+    assert!(!imported.status.success());
+    assert!(String::from_utf8_lossy(&imported.stderr).contains("Invalid mnemonic phrase"));
+    assert!(imported.stdout.is_empty());
+}
+
+#[test]
+fn address_flag_prints_the_base58check_address() {
+    let output = key_generator(&["--seed", SEED, "--compressed", "--address"]);
+    assert!(output.status.success());
+
+    let public_key = printed(&output, "Public Key (Hex, 33-byte compressed)");
+    let address = printed(&output, "Address (Base58Check)");
+    let decoded = cryptobeca::address::decode_address(&address).unwrap();
+    assert_eq!(hex::encode(decoded.serialize()), public_key);
+
+    let without_flag = key_generator(&["--seed", SEED]);
+    assert!(!String::from_utf8_lossy(&without_flag.stdout).contains("Base58Check"));
+}
+
+#[test]
+fn same_seed_generates_the_same_key_pair() {
+    let first = key_generator(&["--seed", SEED]);
+    let second = key_generator(&["--seed", SEED]);
+    assert!(first.status.success());
+    assert!(String::from_utf8_lossy(&first.stderr).contains("only use it for testing"));
+
+    for label in [
+        "Private Key (Hex)",
+        "Public Key (Hex, 65-byte uncompressed)",
+    ] {
+        assert_eq!(printed(&first, label), printed(&second, label));
+    }
+
+    let other_seed = key_generator(&["--seed", &"02".repeat(32)]);
+    assert_ne!(
+        printed(&other_seed, "Private Key (Hex)"),
+        printed(&first, "Private Key (Hex)")
+    );
+    assert!(!key_generator(&["--seed", "0102"]).status.success());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::keypair;
+use cryptobeca::transaction::Transaction;
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A node process serving an in-memory chain, killed when dropped.
+struct Node {
+    process: Child,
+    address: String,
+}
+
+impl Node {
+    /// Starts a node on a free local port and waits until it accepts connections.
+    fn start() -> Node {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("A local port is free")
+            .to_string();
+        let process = Command::new(env!("CARGO_BIN_EXE_node"))
+            .args(["--listen", &address])
+            .current_dir(std::env::temp_dir())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("The node runs");
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while TcpStream::connect(&address).is_err() {
+            assert!(Instant::now() < deadline, "The node did not start in time");
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        Node { process, address }
+    }
+
+    /// Sends a request to the node.
+    ///
+    /// # Returns
+    ///
+    /// The status code and the JSON body of the response
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            self.address,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[test]
+fn posted_transaction_is_credited_once_mined() {
+    let node = Node::start();
+    let (key, sender) = keypair(1);
+    let (_, recipient) = keypair(2);
+
+    let (status, block) = node.request("POST", &format!("/mine?reward={}", sender), "");
+    assert_eq!(status, 200);
+    assert_eq!(block["index"], 1);
+
+    let mut transaction = Transaction::new(Some(sender), recipient, 25);
+    transaction.sign_with(&key).unwrap();
+    let (status, posted) = node.request(
+        "POST",
+        "/transactions",
+        &serde_json::to_string(&transaction).unwrap(),
+    );
+    assert_eq!(status, 201);
+    assert_eq!(posted["hash"], transaction.compute_hash());
+
+    let (_, before) = node.request("GET", &format!("/balance/{}", recipient), "");
+    assert_eq!(before["balance"], 0);
+
+    let (status, _) = node.request("POST", &format!("/mine?reward={}", sender), "");
+    assert_eq!(status, 200);
+    let (status, after) = node.request("GET", &format!("/balance/{}", recipient), "");
+    assert_eq!(status, 200);
+    assert_eq!(after["balance"], 25);
+
+    let (status, blocks) = node.request("GET", "/blocks", "");
+    assert_eq!(status, 200);
+    assert_eq!(blocks.as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn invalid_requests_are_answered_with_client_errors() {
+    let node = Node::start();
+    let (key, sender) = keypair(1);
+    let (_, recipient) = keypair(2);
+
+    let mut unfunded = Transaction::new(Some(sender), recipient, 25);
+    unfunded.sign_with(&key).unwrap();
+    let (status, rejected) = node.request(
+        "POST",
+        "/transactions",
+        &serde_json::to_string(&unfunded).unwrap(),
+    );
+    assert_eq!(status, 400);
+    assert!(rejected["error"].is_string());
+
+    assert_eq!(node.request("POST", "/transactions", "not json").0, 400);
+    assert_eq!(node.request("POST", "/mine", "").0, 400);
+    assert_eq!(node.request("POST", "/mine?reward=nobody", "").0, 400);
+    assert_eq!(node.request("GET", "/missing", "").0, 404);
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use common::keypair;
+use cryptobeca::address::Address;
+use cryptobeca::blockchain::Blockchain;
+use cryptobeca::p2p::Node;
+use cryptobeca::transaction::Transaction;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Gets the address of a deterministic key.
+fn address(seed: u8) -> Address {
+    keypair(seed).1
+}
+
+/// Signs a transfer of 10 units from the address of a deterministic key.
+fn signed_transfer(seed: u8, to: Address) -> Transaction {
+    let (key, from) = keypair(seed);
+    let mut transaction = Transaction::new(Some(from), to, 10);
+    transaction.sign_with(&key).unwrap();
+    transaction
+}
+
+/// Waits until a node has a number of peers, failing the test after 10 seconds.
+fn wait_for_peers(node: &Node, peers: usize) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while node.peer_count() < peers {
+        assert!(
+            Instant::now() < deadline,
+            "The nodes did not connect in time"
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Waits for a condition on the chain of a node, failing the test after 10 seconds.
+fn wait_for(node: &Node, condition: impl Fn(&Blockchain) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while !condition(&node.blockchain().lock().unwrap()) {
+        assert!(Instant::now() < deadline, "The nodes did not sync in time");
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Gets the hash of the latest block of a node.
+fn tip_hash(node: &Node) -> String {
+    node.blockchain()
+        .lock()
+        .unwrap()
+        .get_latest_block()
+        .hash
+        .clone()
+}
+
+#[test]
+fn mined_blocks_propagate_between_two_nodes() {
+    let first = Node::bind("127.0.0.1:0", Blockchain::new(1, 100)).unwrap();
+    let second = Node::bind("127.0.0.1:0", Blockchain::new(1, 100)).unwrap();
+    second.connect(first.local_addr()).unwrap();
+
+    wait_for_peers(&first, 1);
+
+    first
+        .blockchain()
+        .lock()
+        .unwrap()
+        .mine_pending_transactions(address(1))
+        .unwrap();
+    let mined = tip_hash(&first);
+    wait_for(&second, |blockchain| {
+        blockchain.get_latest_block().hash == mined
+    });
+
+    second
+        .blockchain()
+        .lock()
+        .unwrap()
+        .mine_pending_transactions(address(2))
+        .unwrap();
+    let mined = tip_hash(&second);
+    wait_for(&first, |blockchain| {
+        blockchain.get_latest_block().hash == mined
+    });
+
+    for node in [&first, &second] {
+        let blockchain = node.blockchain();
+        let blockchain = blockchain.lock().unwrap();
+        assert_eq!(blockchain.height(), 2);
+        assert!(blockchain.is_valid());
+        assert_eq!(blockchain.get_balance_of_address(&address(1)), 100);
+        assert_eq!(blockchain.get_balance_of_address(&address(2)), 100);
+    }
+}
+
+#[test]
+fn connecting_node_syncs_the_heavier_chain() {
+    let mut blockchain = Blockchain::new(1, 100);
+    for _ in 0..3 {
+        blockchain.mine_pending_transactions(address(1)).unwrap();
+    }
+    let tip = blockchain.get_latest_block().hash.clone();
+
+    let first = Node::bind("127.0.0.1:0", blockchain).unwrap();
+    let second = Node::bind("127.0.0.1:0", Blockchain::new(1, 100)).unwrap();
+    second.connect(first.local_addr()).unwrap();
+
+    wait_for(&second, |blockchain| {
+        blockchain.get_latest_block().hash == tip
+    });
+    assert!(second.blockchain().lock().unwrap().is_valid());
+}
+
+#[test]
+fn added_transaction_reaches_the_peer_without_coming_back() {
+    let mut funded = Blockchain::with_genesis(1, 100, vec![(address(1), 1000)]);
+    let added = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&added);
+    funded.subscribe_transactions(Box::new(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    let first = Node::bind("127.0.0.1:0", funded).unwrap();
+    let second = Node::bind(
+        "127.0.0.1:0",
+        Blockchain::with_genesis(1, 100, vec![(address(1), 1000)]),
+    )
+    .unwrap();
+    second.connect(first.local_addr()).unwrap();
+    wait_for_peers(&first, 1);
+
+    let transaction = signed_transfer(1, address(2));
+    first
+        .blockchain()
+        .lock()
+        .unwrap()
+        .add_transaction(transaction.clone())
+        .unwrap();
+    wait_for(&second, |blockchain| {
+        blockchain
+            .pending()
+            .iter()
+            .any(|pending| pending.hash == transaction.hash)
+    });
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(added.load(Ordering::SeqCst), 1);
+    assert_eq!(first.blockchain().lock().unwrap().pending().len(), 1);
+    assert_eq!(second.blockchain().lock().unwrap().pending().len(), 1);
+}
+
+#[test]
+fn transaction_rejected_by_a_peer_is_not_relayed_further() {
+    let funded = || Blockchain::with_genesis(1, 100, vec![(address(1), 1000)]);
+    let first = Node::bind("127.0.0.1:0", funded()).unwrap();
+    let unfunded = Node::bind("127.0.0.1:0", Blockchain::with_genesis(1, 100, Vec::new())).unwrap();
+    let third = Node::bind("127.0.0.1:0", funded()).unwrap();
+    unfunded.connect(first.local_addr()).unwrap();
+    third.connect(unfunded.local_addr()).unwrap();
+    wait_for_peers(&unfunded, 2);
+
+    first
+        .blockchain()
+        .lock()
+        .unwrap()
+        .add_transaction(signed_transfer(1, address(2)))
+        .unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    assert!(unfunded.blockchain().lock().unwrap().pending().is_empty());
+    assert!(third.blockchain().lock().unwrap().pending().is_empty());
+}